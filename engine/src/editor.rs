@@ -0,0 +1,160 @@
+// imports
+use super::errors::GraphErr;
+use super::graph::{is_junction, Figure, GraphState, GRAPH};
+use super::models::{FigureKind, FIELD, LOCATION, OFF_BOARD};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// figures per player and stoppers per kind
+const FIGURES: usize = 5;
+
+// Board editor for arbitrary positions. Figures can be placed and removed freely, the position
+// is only checked for legality when asked for feedback or when converting it into a `GraphState`
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BoardEditor {
+    pieces: Vec<LOCATION>,
+}
+
+impl BoardEditor {
+    // editor with an empty board
+    pub fn new() -> BoardEditor {
+        BoardEditor::default()
+    }
+
+    pub fn from_state(state: &GraphState) -> BoardEditor {
+        BoardEditor {
+            pieces: state.locations().to_vec(),
+        }
+    }
+
+    pub fn pieces(&self) -> &[LOCATION] {
+        &self.pieces
+    }
+
+    // place a figure or move it if it's already on the board. Use `OFF_BOARD` for figures that
+    // were already brought home or stoppers that weren't placed yet
+    pub fn place(&mut self, figure: Figure, field: FIELD) -> Result<(), GraphErr> {
+        if FigureKind::of(&figure) == FigureKind::Empty {
+            return Err(GraphErr::CannotConstructState(format!(
+                "There's no figure with id {}",
+                figure
+            )));
+        } else if field != OFF_BOARD && !GRAPH.is_field(&field) {
+            return Err(GraphErr::NoSuchVertex);
+        }
+
+        match self.pieces.iter_mut().find(|piece| piece.1 == figure) {
+            Some(piece) => piece.0 = field,
+            None => self.pieces.push((field, figure)),
+        }
+
+        Ok(())
+    }
+
+    // remove a figure from the position entirely
+    pub fn remove(&mut self, figure: &Figure) -> bool {
+        let before = self.pieces.len();
+        self.pieces.retain(|piece| &piece.1 != figure);
+        before != self.pieces.len()
+    }
+
+    // all invariants the current position violates. An empty list means the position is legal
+    pub fn issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        let mut players = [0_usize; 5];
+        let (mut black, mut gray) = (0_usize, 0_usize);
+        let mut fields: HashMap<FIELD, Vec<&Figure>> = HashMap::with_capacity(self.pieces.len());
+
+        for (field, figure) in self.pieces.iter() {
+            match FigureKind::of(figure) {
+                FigureKind::Player(player, color) => {
+                    players[player as usize] += 1;
+                    if *field == [color as i16, 0, 0] {
+                        issues.push(format!("Figure {} can't stand on its own corner", figure));
+                    }
+                }
+                FigureKind::BlackStopper => {
+                    black += 1;
+                    if *field == OFF_BOARD {
+                        issues.push(format!(
+                            "Black stopper {} has to be placed on the board",
+                            figure
+                        ));
+                    }
+                }
+                FigureKind::GrayStopper => gray += 1,
+                FigureKind::Empty => (),
+            }
+
+            if *field != OFF_BOARD {
+                fields.entry(*field).or_default().push(figure);
+            }
+        }
+
+        // piece counts per player
+        for (player, count) in players.iter().enumerate() {
+            if *count != FIGURES {
+                issues.push(format!(
+                    "Player {} has {} of {} figures",
+                    player + 1,
+                    count,
+                    FIGURES
+                ));
+            }
+        }
+
+        if black != FIGURES {
+            issues.push(format!("There are {} of {} black stoppers", black, FIGURES));
+        }
+
+        if gray != FIGURES {
+            issues.push(format!("There are {} of {} gray stoppers", gray, FIGURES));
+        }
+
+        // overlapping stops. Player figures may share the junctions they start on
+        for (field, figures) in fields.iter() {
+            let stacked = is_junction(field)
+                && figures
+                    .iter()
+                    .all(|figure| matches!(FigureKind::of(figure), FigureKind::Player(..)));
+            if figures.len() > 1 && !stacked {
+                issues.push(format!(
+                    "Field {:?} is occupied by {} figures",
+                    field,
+                    figures.len()
+                ));
+            }
+        }
+
+        issues
+    }
+
+    // convert the edited position into a checked state
+    pub fn to_state(&self) -> Result<GraphState, GraphErr> {
+        let issues = self.issues();
+        if !issues.is_empty() {
+            return Err(GraphErr::CannotConstructState(issues.join(", ")));
+        }
+
+        GraphState::from_locations(&self.pieces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untouched_board_has_no_issues() {
+        let editor = BoardEditor::from_state(&GraphState::empty());
+        assert_eq!(editor.issues(), Vec::<String>::new());
+        assert!(editor.to_state().is_ok());
+    }
+
+    #[test]
+    fn stopper_on_a_stack_is_an_issue() {
+        let mut editor = BoardEditor::from_state(&GraphState::empty());
+        editor.place("26".to_owned(), [5, 0, 0]).unwrap();
+        assert_eq!(editor.issues().len(), 1);
+    }
+}
@@ -12,4 +12,7 @@ pub enum GraphErr {
 
     // Couldn't construct State from database
     CannotConstructState(String),
+
+    // The move is reachable but forbidden by the game's rule set
+    RuleViolation(String),
 }
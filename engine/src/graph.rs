@@ -0,0 +1,638 @@
+// imports
+use super::errors::GraphErr;
+use super::models::{FigureKind, FIELD, LOCATION, OFF_BOARD};
+use super::position::Handicap;
+use super::rules::MoveEffect;
+use dashmap::DashMap;
+use priority_queue::PriorityQueue;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::array;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::convert::{TryFrom, TryInto};
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+// Figures are simplified based on denomination Rules
+pub type Figure = String;
+// State containing Positions of all figures (5 figures per player, 5 gray stoppers, 5 black stoppers)
+// LOCATION: ([i16; 3], u8)
+
+#[derive(Debug, Clone, Hash)]
+pub struct GraphState([LOCATION; 35]);
+
+// Serializable variant (due to some serde constraints the array seems to not be fertilizable directly)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ResizableGraphState {
+    locations: Vec<LOCATION>,
+}
+
+impl From<GraphState> for ResizableGraphState {
+    fn from(base: GraphState) -> ResizableGraphState {
+        ResizableGraphState {
+            locations: base.0.to_vec(),
+        }
+    }
+}
+
+impl From<&GraphState> for ResizableGraphState {
+    fn from(base: &GraphState) -> ResizableGraphState {
+        ResizableGraphState {
+            locations: base.0.to_vec(),
+        }
+    }
+}
+
+// loading stored positions (e.g. a game's starting position) checks them for legality
+impl TryFrom<ResizableGraphState> for GraphState {
+    type Error = GraphErr;
+
+    fn try_from(base: ResizableGraphState) -> Result<GraphState, GraphErr> {
+        GraphState::from_locations(&base.locations)
+    }
+}
+
+// vertexmap
+pub const BASE_VERTEX_MAP: [i16; 10] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]; // in case the naming changes these are statically mapped
+
+#[derive(Deserialize, Serialize, Hash, PartialEq, Eq, Debug, Clone)]
+pub struct Field {
+    pub occupied: bool,
+    pub owner: Option<Figure>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Graph {
+    /// Mapping of vertex ids and vertex values
+    pub vertices: DashMap<FIELD, Field>,
+    // This doesn't need to hold data about figures
+}
+
+impl Default for Graph {
+    fn default() -> Graph {
+        Graph::new()
+    }
+}
+
+impl Graph {
+    pub fn new() -> Graph {
+        Graph {
+            vertices: DashMap::with_capacity(100_usize),
+        }
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.vertices.shrink_to_fit();
+    }
+
+    pub fn fetch(&self, id: FIELD) -> Result<Field, GraphErr> {
+        match self.vertices.get(&id) {
+            Some(vertex) => Ok(vertex.clone()),
+            None => Err(GraphErr::NoSuchVertex {}),
+        }
+    }
+
+    pub fn add_vertex(&mut self, id: FIELD, field: Field) -> Result<FIELD, GraphErr> {
+        match self.vertices.insert(id, field) {
+            Some(value) => {
+                // insert the old vertex again to prevent UB
+                self.vertices.insert(id, value);
+                Err(GraphErr::CannotAddVertex {})
+            }
+            None => Ok(id),
+        }
+    }
+
+    pub fn validate<'a>(
+        &'a self,
+        src: &'a FIELD,
+        dest: &'a FIELD,
+        state: &'a GraphState,
+    ) -> Result<(bool, Figure), GraphErr> {
+        // check if specified vertices exists
+        self.fetch(*src)?;
+
+        // test with a* if there's a possible path
+        Ok(self.a_star(src, dest, state))
+    }
+
+    fn a_star<'a>(
+        &'a self,
+        src: &'a FIELD,
+        dest: &'a FIELD,
+        state: &'a GraphState,
+    ) -> (bool, String) {
+        // a star with heuristics
+        let visited = VISITED.clone(); // RAM goes brrrrrrrrrrr
+
+        // prepare visited. (Ah that sweet parallel overkill)
+        let destination_owner_mutex = Mutex::new("empty".to_owned());
+        state.0.par_iter().for_each(|(field, figure)| {
+            if field[0] != -1 {
+                visited.insert(*field, true);
+            } else if field == dest {
+                *destination_owner_mutex.lock().unwrap() = figure.clone();
+            }
+        });
+        let destination_owner = destination_owner_mutex.lock().unwrap().clone();
+
+        let mut priority_queue: PriorityQueue<FIELD, i16> = PriorityQueue::new();
+        let mut item = Some((*src, 0));
+
+        // find initial neighbors for src
+        Graph::add_neighbors(src, *dest, &mut priority_queue);
+
+        // search until found
+        while item.is_some() {
+            // fetch visited state and vertex value
+            let vertex = item.unwrap().0;
+            let state = visited.get(&vertex).unwrap();
+
+            // check if destination is reached
+            if vertex == *dest {
+                return (true, destination_owner);
+            } else if !*state {
+                // Evaluate edges on the fly. Didn't know why I even bothered with static edges
+                // this actually is faster than having an ege map since calculating hashes
+                // is more expensive than simple comparisons
+                Graph::add_neighbors(&vertex, *dest, &mut priority_queue);
+            }
+
+            item = priority_queue.pop();
+        }
+
+        (false, "invalid move".to_owned())
+    }
+
+    fn add_neighbors(src: &FIELD, dest: FIELD, queue: &mut PriorityQueue<FIELD, i16>) {
+        if src[1] == 0 {
+            // src is junction or corner
+            // edge cases may
+            let (first, second, third, fourth) = match src[0] {
+                0 => ([1, 1, 0], [9, 6, 0], [4, 3, 0], [5, 6, 0]),
+                5 => ([5, 1, 0], [9, 3, 5], [5, 1, 1], [6, 2, 5]),
+                9 => ([9, 1, 8], [9, 1, 5], [9, 1, 0], [9, 1, 4]), // there's no need to calculate anything if the number is known
+                _ => {
+                    if src[0] < 4 {
+                        (
+                            [src[0], 1, src[0] - 1],
+                            [src[0] + 1, 1, src[0]],
+                            [src[0] + 5, 1, src[0]],
+                            [src[0] + 4, 1, src[0]],
+                        )
+                    } else {
+                        (
+                            [src[0], 1, src[0] - 1],
+                            [src[0] + 1, 1, src[0]],
+                            [src[0], 1, src[0] - 5],
+                            [src[0], 1, src[0] - 4],
+                        )
+                    }
+                }
+            };
+
+            queue.push(first, Graph::heuristic(first, dest));
+            queue.push(second, Graph::heuristic(second, dest));
+            queue.push(third, Graph::heuristic(third, dest));
+            queue.push(fourth, Graph::heuristic(fourth, dest));
+        } else {
+            // src is stop
+            let positive_counter = src[1] + 1;
+            let negative_counter = src[1] - 1;
+
+            if (positive_counter == 6 && (src[0] > 4 && src[2] < 5))
+                || (positive_counter == 3
+                    && ((src[0] < 4 && src[2] < 4) || (src[0] > 4 && src[2] > 4)))
+            {
+                queue.push([src[1], 0, 0], Graph::heuristic([src[1], 0, 0], dest));
+            } else {
+                queue.push(
+                    [src[0], positive_counter, src[1]],
+                    Graph::heuristic([src[0], positive_counter, src[1]], dest),
+                );
+            }
+
+            queue.push(
+                [src[0], negative_counter, src[1]],
+                Graph::heuristic([src[0], negative_counter, src[1]], dest),
+            );
+        }
+    }
+
+    fn heuristic(src: FIELD, dest: FIELD) -> i16 {
+        if src[0] > 4 {
+            // k1 ∊ J
+            if dest[0] > 4 {
+                // k2 ∊ J
+                (src[0] - dest[0]) * 4 + src[1] + dest[1]
+            } else {
+                // k2 ∊ C
+                dest[0] * 4 + src[1] + dest[1] + 6
+            }
+        } else {
+            // k1 ∊ C
+            (src[0] - dest[0]) * 4 + src[1] + dest[1]
+        }
+    }
+
+    pub fn construct_graph() -> Graph {
+        // this function doesn't rely on Result as it should have a static result
+        let mut graph: Graph = Graph::new();
+        let mut base_map: [FIELD; 10] = [[0, 0, 0]; 10];
+
+        // the base nodes (junction, corners) need to be preinserted to do effective EDGE and stop mapping
+        for i in 0..BASE_VERTEX_MAP.len() {
+            base_map[i] = graph
+                .add_vertex(
+                    [BASE_VERTEX_MAP[i], 0, 0],
+                    Field {
+                        occupied: false,
+                        owner: None,
+                    },
+                )
+                .expect("Unable to add base vertex on graph creation");
+        }
+
+        // construct edges from edgemap. See pentagraph (python)
+
+        // ensure only required space is used
+        graph.shrink_to_fit();
+
+        graph
+    }
+
+    // check if a field exists on the board (corners, junctions and the stops between them)
+    pub fn is_field(&self, field: &FIELD) -> bool {
+        let is_node = |id: i16| (0..=9).contains(&id);
+
+        if field[1] == 0 {
+            field[2] == 0 && self.vertices.contains_key(field)
+        } else {
+            is_node(field[0])
+                && is_node(field[2])
+                && field[0] != field[2]
+                && field[1] > 0
+                && field[1] < 6
+        }
+    }
+
+    // construct empty figures locations
+    pub fn construct_figure_location(&self) -> DashMap<Figure, FIELD> {
+        let figure_locations = DashMap::with_capacity(35);
+        EMPTY_STATE.clone().0.iter().for_each(|figure| {
+            figure_locations.insert(figure.1.clone(), figure.0);
+        });
+
+        figure_locations
+    }
+
+    // construct empty visited map for a*
+    pub fn construct_visited(&self) -> DashMap<FIELD, bool> {
+        let visited = DashMap::with_capacity(self.vertices.len());
+        self.vertices.iter().for_each(|multi_ref| {
+            visited.insert(*multi_ref.key(), multi_ref.value().occupied);
+        });
+
+        visited
+    }
+}
+
+impl GraphState {
+    // Creates an empty, as in no changes to the board but all player figures on board, state
+    pub fn empty() -> GraphState {
+        /*
+        This 'construction' is not especially optimized to allow for better readability
+        It doesn't really matter anyway since it's saved in a lazy constant
+        */
+        let mut figures: [LOCATION; 35] = array::from_fn(|_| ([0_i16; 3], "".to_owned()));
+
+        // adding players
+        (0..5).for_each(|figure| {
+            (0..5).for_each(|index| {
+                if index > 5 {
+                    figures[figure * 5 + index] = (
+                        [(index - (figure - 1) * 5).try_into().unwrap(), 0, 0],
+                        (figure * 5 + index + 1).to_string(),
+                    )
+                } else {
+                    figures[figure * 5 + index] = (
+                        [(index + 5).try_into().unwrap(), 0, 0],
+                        (figure * 5 + index + 1).to_string(),
+                    )
+                };
+            });
+        });
+
+        // adding black stoppers
+        for (figure, location) in figures.iter_mut().enumerate().skip(25).take(5) {
+            *location = (
+                [(figure - 25).try_into().unwrap(), 0, 0],
+                (figure + 1).to_string(),
+            );
+        }
+
+        // adding gray stoppers
+        for (figure, location) in figures.iter_mut().enumerate().skip(30) {
+            *location = ([-1, -1, -1], (figure + 1).to_string());
+        }
+
+        GraphState(figures)
+    }
+
+    pub fn locations(&self) -> &[LOCATION] {
+        &self.0
+    }
+
+    // current field of a figure
+    pub fn locate(&self, figure: &Figure) -> Option<FIELD> {
+        self.0
+            .iter()
+            .find(|location| &location.1 == figure)
+            .map(|location| location.0)
+    }
+
+    // amount of figures a player has brought home
+    pub fn exited(&self, player: u8) -> usize {
+        self.0
+            .iter()
+            .filter(|location| {
+                location.0 == OFF_BOARD
+                    && match FigureKind::of(&location.1) {
+                        FigureKind::Player(owner, _) => owner == player,
+                        _ => false,
+                    }
+            })
+            .count()
+    }
+
+    // apply a move validated by `RuleSet::evaluate`
+    pub fn apply(&mut self, figure: &Figure, dest: &FIELD, effect: &MoveEffect) {
+        let src = self.locate(figure).unwrap_or(OFF_BOARD);
+
+        for location in self.0.iter_mut() {
+            if &location.1 == figure {
+                location.0 = if effect.exited { OFF_BOARD } else { *dest };
+            } else if Some(&location.1) == effect.swapped.as_ref() {
+                location.0 = src;
+            } else if location.0 == *dest {
+                // beaten stoppers wait off board until they are placed again
+                location.0 = OFF_BOARD;
+            }
+        }
+    }
+
+    // puts an off board stopper (beaten black stopper or gray stopper) on an empty field
+    pub fn place_stopper(&mut self, figure: &Figure, field: &FIELD) -> Result<(), GraphErr> {
+        match FigureKind::of(figure) {
+            FigureKind::BlackStopper | FigureKind::GrayStopper => (),
+            _ => {
+                return Err(GraphErr::RuleViolation(
+                    "Only stoppers can be placed".to_owned(),
+                ))
+            }
+        }
+
+        if !GRAPH.is_field(field) {
+            return Err(GraphErr::RuleViolation(format!(
+                "There's no field {:?}",
+                field
+            )));
+        } else if self.0.iter().any(|location| location.0 == *field) {
+            return Err(GraphErr::RuleViolation(
+                "Stoppers can only be placed on empty fields".to_owned(),
+            ));
+        }
+
+        match self.0.iter_mut().find(|location| &location.1 == figure) {
+            Some(location) if location.0 == OFF_BOARD => {
+                location.0 = *field;
+                Ok(())
+            }
+            Some(_) => Err(GraphErr::RuleViolation(
+                "This stopper is already on the board".to_owned(),
+            )),
+            None => Err(GraphErr::RuleViolation(format!(
+                "There's no figure with id {}",
+                figure
+            ))),
+        }
+    }
+
+    // takes all figures of a player off the board (See rules::AbandonRule)
+    pub fn remove_player(&mut self, player: u8) {
+        for location in self.0.iter_mut() {
+            if let FigureKind::Player(owner, _) = FigureKind::of(&location.1) {
+                if owner == player {
+                    location.0 = OFF_BOARD;
+                }
+            }
+        }
+    }
+
+    // whether a stopper of this kind waits off board to be placed
+    pub fn has_off_board(&self, kind: FigureKind) -> bool {
+        self.0
+            .iter()
+            .any(|location| location.0 == OFF_BOARD && FigureKind::of(&location.1) == kind)
+    }
+
+    // Creates a state from `GraphState::empty` with the given figures moved to custom locations.
+    // The resulting position is checked for legality
+    pub fn from_locations(locations: &[LOCATION]) -> Result<GraphState, GraphErr> {
+        let state = GraphState::place(locations)?;
+        state.check_legal()?;
+        Ok(state)
+    }
+
+    // Creates a state saved during a game (e.g. a snapshot of a running game). Unlike starting
+    // positions it may contain beaten black stoppers waiting to be placed again
+    pub fn from_saved(base: ResizableGraphState) -> Result<GraphState, GraphErr> {
+        GraphState::place(&base.locations)
+    }
+
+    fn place(locations: &[LOCATION]) -> Result<GraphState, GraphErr> {
+        let mut state = GraphState::empty();
+        let mut seen: HashSet<&Figure> = HashSet::with_capacity(locations.len());
+
+        for (field, figure) in locations {
+            if !seen.insert(figure) {
+                return Err(GraphErr::CannotConstructState(format!(
+                    "Figure {} was placed twice",
+                    figure
+                )));
+            }
+
+            match state.0.iter_mut().find(|location| &location.1 == figure) {
+                Some(location) => location.0 = *field,
+                None => {
+                    return Err(GraphErr::CannotConstructState(format!(
+                        "There's no figure with id {}",
+                        figure
+                    )))
+                }
+            }
+        }
+
+        Ok(state)
+    }
+
+    // checks the invariants every position has to fulfill
+    pub fn check_legal(&self) -> Result<(), GraphErr> {
+        let mut occupied: HashSet<FIELD> = HashSet::with_capacity(self.0.len());
+        // junctions holding player figures. They start stacked there (See GraphState::empty)
+        let mut stacked: HashSet<FIELD> = HashSet::with_capacity(5);
+
+        for (field, figure) in self.0.iter() {
+            let kind = FigureKind::of(figure);
+
+            if *field == OFF_BOARD {
+                // black stoppers are only off board while waiting to be placed again
+                if kind == FigureKind::BlackStopper {
+                    return Err(GraphErr::CannotConstructState(format!(
+                        "Black stopper {} has to be placed on the board",
+                        figure
+                    )));
+                }
+                continue;
+            }
+
+            if !GRAPH.is_field(field) {
+                return Err(GraphErr::CannotConstructState(format!(
+                    "Figure {} is placed on {:?} which isn't a field",
+                    figure, field
+                )));
+            }
+
+            let overlaps = if is_junction(field) && matches!(kind, FigureKind::Player(..)) {
+                stacked.insert(*field);
+                occupied.contains(field)
+            } else {
+                stacked.contains(field) || !occupied.insert(*field)
+            };
+            if overlaps {
+                return Err(GraphErr::CannotConstructState(format!(
+                    "Field {:?} is occupied by more than one figure",
+                    field
+                )));
+            }
+
+            // figures on their corner would already have left the board
+            if let FigureKind::Player(_, color) = kind {
+                if *field == [color as i16, 0, 0] {
+                    return Err(GraphErr::CannotConstructState(format!(
+                        "Figure {} can't start on its own corner",
+                        figure
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn apply_handicap(&mut self, handicap: &Handicap) -> Result<(), GraphErr> {
+        handicap.validate()?;
+        let figures = handicap.figures();
+
+        for location in self.0.iter_mut() {
+            if figures.contains(&location.1) {
+                location.0 = OFF_BOARD;
+            }
+        }
+
+        Ok(())
+    }
+
+    // hash of all figure locations (used for positional ko)
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl ResizableGraphState {
+    pub fn for_players(players: u8) -> ResizableGraphState {
+        let mut locations = Vec::with_capacity((players * 7).into());
+        vec![0; (players * 7).into()];
+
+        // adding players
+        let player_figures = players * players;
+        for p in 0..players {
+            for f in 0..players {
+                locations.push(([f.into(), 0, 0], format!("{}-{}", p, f)));
+            }
+        }
+
+        // adding black stoppers
+        for figure in player_figures..player_figures + players {
+            locations.push(([(figure - 25).into(), 0, 0], (figure + 1).to_string()));
+        }
+
+        // adding gray stoppers
+        for figure in player_figures + players..player_figures + players * 2 {
+            locations.push(([-1, -1, -1], (figure + 1).to_string()));
+        }
+
+        ResizableGraphState { locations }
+    }
+}
+
+// junctions are the starting fields of the player figures (corners are 0-4)
+pub fn is_junction(field: &FIELD) -> bool {
+    field[1] == 0 && field[2] == 0 && (5..=9).contains(&field[0])
+}
+
+// There's no need to construct the graph multiple times because it loads itself from a state
+lazy_static! {
+    pub static ref EMPTY_STATE: GraphState = GraphState::empty();
+    pub static ref EMPTY_FIGURE_LOCATIONS: DashMap<Figure, FIELD> =
+        GRAPH.construct_figure_location();
+    pub static ref GRAPH: Graph = Graph::construct_graph();
+    static ref VISITED: DashMap<FIELD, bool> = GRAPH.construct_visited();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_state_is_legal() {
+        assert!(GraphState::empty().check_legal().is_ok());
+    }
+
+    #[test]
+    fn stoppers_can_not_share_a_field() {
+        let locations = vec![([5, 1, 6], "26".to_owned()), ([5, 1, 6], "27".to_owned())];
+        assert!(GraphState::from_locations(&locations).is_err());
+    }
+
+    #[test]
+    fn stoppers_can_not_join_a_stack() {
+        let locations = vec![([5, 0, 0], "26".to_owned())];
+        assert!(GraphState::from_locations(&locations).is_err());
+    }
+
+    #[test]
+    fn saved_game_in_progress_restores() {
+        // figure 2 beats black stopper 26 which waits off board to be placed again
+        let mut state = GraphState::empty();
+        let effect = MoveEffect {
+            swapped: None,
+            black_stopper: Some("26".to_owned()),
+            exited: false,
+            gray_stopper: false,
+        };
+        state.apply(&"2".to_owned(), &[0, 0, 0], &effect);
+
+        let saved = ResizableGraphState::from(&state);
+        assert!(GraphState::try_from(saved.clone()).is_err());
+
+        let restored = GraphState::from_saved(saved).unwrap();
+        assert_eq!(restored.locate(&"26".to_owned()), Some(OFF_BOARD));
+        assert_eq!(restored.fingerprint(), state.fingerprint());
+    }
+}
@@ -0,0 +1,30 @@
+/*
+pentagame-engine - board graph, rules and position notation of pentagame
+
+This crate contains the game logic only and doesn't depend on actix, diesel or the npm asset
+build. It's used by the pentagame online server and can be used by bots, CLI tools or other
+frontends.
+
+Stable API:
+    graph: `Graph` (move validation), `GraphState` (figure locations) and the shared `GRAPH`
+    rules: `RuleSet`, `KoRule`, `AbandonRule`, `TimeControl`, `Teams` and `MoveEffect` (rule evaluation of validated moves)
+    position: starting positions, handicaps and the textual position notation
+    editor: `BoardEditor` for building arbitrary positions with legality feedback
+    models: `FIELD`, `LOCATION`, `MOVE`, `FigureKind` and the `GameFile` export format
+*/
+#[macro_use]
+extern crate lazy_static;
+
+pub mod editor;
+pub mod errors;
+pub mod graph;
+pub mod models;
+pub mod position;
+pub mod rules;
+
+// re-exports of the commonly used types
+pub use errors::GraphErr;
+pub use graph::{Figure, Graph, GraphState, ResizableGraphState, GRAPH};
+pub use models::{FigureKind, GameFile, Move, FIELD, LOCATION, MOVE, OFF_BOARD};
+pub use position::{parse_position, starting_position, Handicap, StartingPosition};
+pub use rules::{AbandonRule, KoRule, MoveEffect, RuleSet, Teams, TimeControl};
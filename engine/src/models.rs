@@ -0,0 +1,86 @@
+use super::graph::Figure;
+use super::position::StartingPosition;
+use super::rules::RuleSet;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// types
+// i16 is used to be translatable to PG SMALL INT
+pub type MOVE = ([i16; 6], Figure);
+pub type FIELD = [i16; 3];
+pub type LOCATION = ([i16; 3], Figure);
+
+// location of figures that aren't on the board (exited figures, unplaced stoppers)
+pub const OFF_BOARD: FIELD = [-1, -1, -1];
+
+// Figure ids are numeric: 1-25 player figures (5 per player, one per color),
+// 26-30 black stoppers and 31-35 gray stoppers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FigureKind {
+    // (player, color)
+    Player(u8, u8),
+    BlackStopper,
+    GrayStopper,
+    Empty,
+}
+
+impl FigureKind {
+    pub fn of(figure: &str) -> FigureKind {
+        match figure.parse::<u8>() {
+            Ok(id @ 1..=25) => FigureKind::Player((id - 1) / 5, (id - 1) % 5),
+            Ok(26..=30) => FigureKind::BlackStopper,
+            Ok(31..=35) => FigureKind::GrayStopper,
+            _ => FigureKind::Empty,
+        }
+    }
+}
+
+// wrapper for MOVE to allow (de)serializing
+#[derive(Deserialize, Serialize, Debug, PartialOrd, PartialEq)]
+pub struct Move(pub MOVE);
+
+#[derive(Deserialize, Serialize, Debug, PartialOrd, PartialEq)]
+pub struct EMove {
+    pub figure: String,
+    pub player: Uuid,
+    pub source: FIELD,
+    pub steps: Vec<FIELD>,
+}
+
+// File format for exporting games (See 'GameFile' in static/ts/pentamath/interfaces.ts)
+#[derive(Deserialize, Serialize, Debug)]
+pub struct GameFile {
+    pub players: Vec<Uuid>,
+    // custom starting position and handicaps. `None` for the default position
+    pub base: Option<StartingPosition>,
+    pub moves: Vec<Move>,
+    pub rule_set: RuleSet,
+}
+
+// moves are stored by source and last step. Intermediate steps are only needed for validation
+impl From<EMove> for Move {
+    fn from(source: EMove) -> Move {
+        let [a, b, c] = source.source;
+        let [d, e, f] = source.steps.last().copied().unwrap_or(source.source);
+        Move(([a, b, c, d, e, f], source.figure))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn move_ends_on_last_step() {
+        let emove = EMove {
+            figure: "2".to_owned(),
+            player: Uuid::nil(),
+            source: [6, 0, 0],
+            steps: vec![[6, 1, 3], [6, 2, 3], [1, 0, 0]],
+        };
+        assert_eq!(
+            Move::from(emove),
+            Move(([6, 0, 0, 1, 0, 0], "2".to_owned()))
+        );
+    }
+}
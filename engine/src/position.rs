@@ -0,0 +1,152 @@
+// imports
+use super::errors::GraphErr;
+use super::graph::{Figure, GraphState, ResizableGraphState};
+use super::models::{FigureKind, FIELD, LOCATION, OFF_BOARD};
+use serde::{Deserialize, Serialize};
+
+/*
+Custom starting positions are described piece by piece with one figure per line:
+
+    <figure>: <a>,<b>,<c>
+    <figure>: off
+
+e.g. `7: 5,0,0` places figure 7 on junction 5 and `31: off` keeps gray stopper 31 off the board.
+Figures that aren't listed keep their default location from `GraphState::empty`.
+*/
+
+// standard handicaps that are applied on top of the starting position
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub enum Handicap {
+    // the (weaker) player starts with `figures` figures already brought home
+    HeadStart { player: u8, figures: u8 },
+}
+
+// starting position as stored with the game and recorded in the game file
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct StartingPosition {
+    // locations with all handicaps applied
+    pub locations: ResizableGraphState,
+    pub handicaps: Vec<Handicap>,
+}
+
+pub fn parse_position(source: &str) -> Result<Vec<LOCATION>, GraphErr> {
+    let mut locations = Vec::new();
+
+    for (number, line) in source.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = || {
+            GraphErr::CannotConstructState(format!(
+                "Line {} isn't formatted as '<figure>: <a>,<b>,<c>'",
+                number + 1
+            ))
+        };
+
+        let mut parts = line.splitn(2, ':');
+        let figure: Figure = parts.next().ok_or_else(invalid)?.trim().to_owned();
+        let raw_field = parts.next().ok_or_else(invalid)?.trim();
+
+        let field: FIELD = if raw_field == "off" {
+            OFF_BOARD
+        } else {
+            let values = raw_field
+                .split(',')
+                .map(|value| value.trim().parse::<i16>())
+                .collect::<Result<Vec<i16>, _>>()
+                .map_err(|_| invalid())?;
+
+            match values.as_slice() {
+                [a, b, c] => [*a, *b, *c],
+                _ => return Err(invalid()),
+            }
+        };
+
+        locations.push((field, figure));
+    }
+
+    Ok(locations)
+}
+
+// builds a checked starting position from the textual description and handicaps
+pub fn starting_position(
+    source: Option<&str>,
+    handicaps: &[Handicap],
+) -> Result<GraphState, GraphErr> {
+    let mut state = match source {
+        Some(source) => GraphState::from_locations(&parse_position(source)?)?,
+        None => GraphState::empty(),
+    };
+
+    for handicap in handicaps {
+        state.apply_handicap(handicap)?;
+    }
+
+    Ok(state)
+}
+
+impl Handicap {
+    // a head start is given to one of the five players and must leave them a figure to move
+    pub fn validate(&self) -> Result<(), GraphErr> {
+        match self {
+            Handicap::HeadStart { player, figures } if *player > 4 || *figures > 4 => Err(
+                GraphErr::CannotConstructState("Invalid handicap".to_owned()),
+            ),
+            Handicap::HeadStart { .. } => Ok(()),
+        }
+    }
+
+    // figures that are placed off board by this handicap. Invalid handicaps have none
+    pub fn figures(&self) -> Vec<Figure> {
+        if self.validate().is_err() {
+            return Vec::new();
+        }
+
+        match self {
+            Handicap::HeadStart { player, figures } => (0..*figures)
+                .map(|color| (*player * 5 + color + 1).to_string())
+                .filter(|figure| FigureKind::of(figure) != FigureKind::Empty)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn handicap_of_unknown_player_is_rejected() {
+        let handicaps = [Handicap::HeadStart {
+            player: 60,
+            figures: 1,
+        }];
+        assert!(handicaps[0].figures().is_empty());
+        assert!(starting_position(None, &handicaps).is_err());
+    }
+
+    #[test]
+    fn handicap_survives_round_trip() {
+        let handicaps = [Handicap::HeadStart {
+            player: 1,
+            figures: 2,
+        }];
+        let state = starting_position(None, &handicaps).unwrap();
+
+        let restored = GraphState::try_from(ResizableGraphState::from(&state)).unwrap();
+        assert_eq!(restored.locate(&"6".to_owned()), Some(OFF_BOARD));
+        assert_eq!(restored.locate(&"7".to_owned()), Some(OFF_BOARD));
+        assert_eq!(restored.fingerprint(), state.fingerprint());
+    }
+
+    #[test]
+    fn partial_position_keeps_default_locations() {
+        let state = starting_position(Some("7: 5,1,6"), &[]).unwrap();
+
+        assert_eq!(state.locate(&"7".to_owned()), Some([5, 1, 6]));
+        assert_eq!(state.locate(&"8".to_owned()), Some([7, 0, 0]));
+    }
+}
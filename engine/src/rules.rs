@@ -0,0 +1,328 @@
+// imports
+use super::errors::GraphErr;
+use super::graph::{Figure, GraphState};
+use super::models::{FigureKind, FIELD};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// defaults for new games (See 'RuleSet' in static/ts/pentamath/interfaces.ts)
+pub const DEFAULT_FIGURES: i16 = 3;
+pub const MIN_FIGURES: i16 = 1;
+pub const MAX_FIGURES: i16 = 5;
+// partnership games are played by two teams of two
+pub const TEAM_PLAYERS: usize = 4;
+pub const TEAM_SIZE: usize = 2;
+
+// How strict repeating moves/ positions are handled
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KoRule {
+    // Moves may be repeated freely
+    Off,
+    // A figure may not directly return to the field it just left
+    Simple,
+    // No move may recreate any board position seen before in this game
+    Positional,
+}
+
+// What happens to the figures of a player that resigned or abandoned a multiplayer game
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum AbandonRule {
+    // figures stay on the board as obstacles
+    #[default]
+    Freeze,
+    // figures are taken off the board
+    Remove,
+}
+
+// How the players' time is limited (See RuleSet::time_control)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeControl {
+    Untimed,
+    // base time per player plus an increment for every completed turn
+    Fischer { base: Duration, increment: Duration },
+    // fixed time for every turn
+    PerMove(Duration),
+}
+
+// Rule options chosen by the host when creating a game
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct RuleSet {
+    // figures a player has to bring home to win
+    pub figures: i16,
+    // whether a figure may land on another player's figure and swap places with it
+    pub swaps: bool,
+    // whether a gray stopper is awarded when a figure leaves the board
+    pub stoppers: bool,
+    // optional time limit per move in seconds
+    pub move_clock: Option<i32>,
+    // optional base time per player in seconds (chess-style clock)
+    #[serde(default)]
+    pub base_time: Option<i32>,
+    // seconds added to a player's base time after each of their turns
+    #[serde(default)]
+    pub increment: i32,
+    pub ko: KoRule,
+    // partnership mode (2 vs 2)
+    pub teams: bool,
+    #[serde(default)]
+    pub abandon: AbandonRule,
+}
+
+// Team of every player, indexed by player. Games without teams use `Teams::default()`
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Default)]
+pub struct Teams(pub Vec<Option<u8>>);
+
+// Side effects of a validated move the game server needs to act upon
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MoveEffect {
+    // figure that was swapped to the source field
+    pub swapped: Option<Figure>,
+    // black stopper that was beaten and needs to be placed again
+    pub black_stopper: Option<Figure>,
+    // the moved figure reached its corner and leaves the board
+    pub exited: bool,
+    // a gray stopper needs to be placed by the moving player
+    pub gray_stopper: bool,
+}
+
+impl Default for RuleSet {
+    fn default() -> RuleSet {
+        RuleSet {
+            figures: DEFAULT_FIGURES,
+            swaps: true,
+            stoppers: true,
+            move_clock: None,
+            base_time: None,
+            increment: 0,
+            ko: KoRule::Simple,
+            teams: false,
+            abandon: AbandonRule::Freeze,
+        }
+    }
+}
+
+impl RuleSet {
+    // check if the values chosen by the host are in sensible bounds
+    pub fn validate(&self) -> Result<(), GraphErr> {
+        if self.figures < MIN_FIGURES || self.figures > MAX_FIGURES {
+            return Err(GraphErr::RuleViolation(format!(
+                "Figures required to win must be between {} and {}",
+                MIN_FIGURES, MAX_FIGURES
+            )));
+        }
+
+        if self.increment < 0 {
+            return Err(GraphErr::RuleViolation(
+                "Increment can't be negative".to_owned(),
+            ));
+        }
+
+        match (self.move_clock, self.base_time) {
+            (Some(seconds), _) if seconds < 1 => Err(GraphErr::RuleViolation(
+                "Move clock must be at least one second".to_owned(),
+            )),
+            (_, Some(seconds)) if seconds < 1 => Err(GraphErr::RuleViolation(
+                "Base time must be at least one second".to_owned(),
+            )),
+            (Some(_), Some(_)) => Err(GraphErr::RuleViolation(
+                "A game can either have a move clock or a base time".to_owned(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn move_clock(&self) -> Option<Duration> {
+        self.move_clock
+            .map(|seconds| Duration::from_secs(seconds as u64))
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        match (self.base_time, self.move_clock()) {
+            (Some(base), _) => TimeControl::Fischer {
+                base: Duration::from_secs(base as u64),
+                increment: Duration::from_secs(self.increment.max(0) as u64),
+            },
+            (None, Some(limit)) => TimeControl::PerMove(limit),
+            (None, None) => TimeControl::Untimed,
+        }
+    }
+
+    // Evaluates a move that the graph already accepted as reachable against the rule set.
+    // `occupant` is the figure on `dest` as returned by `Graph::validate`
+    pub fn evaluate(
+        &self,
+        figure: &Figure,
+        dest: &FIELD,
+        occupant: &Figure,
+        previous: Option<&FIELD>,
+        teams: &Teams,
+    ) -> Result<MoveEffect, GraphErr> {
+        let mover = match FigureKind::of(figure) {
+            FigureKind::Player(player, color) => (player, color),
+            _ => {
+                return Err(GraphErr::RuleViolation(
+                    "Only player figures can be moved".to_owned(),
+                ))
+            }
+        };
+
+        // ko: simple repetition
+        if self.ko != KoRule::Off && previous == Some(dest) {
+            return Err(GraphErr::RuleViolation(
+                "A figure may not return to the field it just left".to_owned(),
+            ));
+        }
+
+        let mut effect = MoveEffect {
+            swapped: None,
+            black_stopper: None,
+            exited: false,
+            gray_stopper: false,
+        };
+
+        match FigureKind::of(occupant) {
+            FigureKind::Player(player, _) => {
+                if !self.swaps {
+                    return Err(GraphErr::RuleViolation(
+                        "Swapping figures is disabled in this game".to_owned(),
+                    ));
+                } else if player == mover.0 {
+                    return Err(GraphErr::RuleViolation(
+                        "You can't swap with your own figure".to_owned(),
+                    ));
+                }
+                // in partnership games the partner's figures stay swappable even though
+                // they count towards the same win condition
+                if self.teams && !teams.partners(mover.0, player) && teams.team(player).is_none() {
+                    return Err(GraphErr::RuleViolation(
+                        "The owner of this figure hasn't been assigned to a team".to_owned(),
+                    ));
+                }
+                effect.swapped = Some(occupant.clone());
+            }
+            FigureKind::BlackStopper => effect.black_stopper = Some(occupant.clone()),
+            FigureKind::GrayStopper => {
+                // gray stoppers are simply taken of the board
+            }
+            FigureKind::Empty => (),
+        }
+
+        // reaching the corner of the figure's color
+        if *dest == [mover.1 as i16, 0, 0] {
+            effect.exited = true;
+            effect.gray_stopper = self.stoppers;
+        }
+
+        Ok(effect)
+    }
+
+    // ko: positional repetition is checked on the resulting state with the fingerprints of all
+    // earlier positions
+    pub fn check_repetition(&self, next: &GraphState, history: &[u64]) -> Result<(), GraphErr> {
+        if self.ko == KoRule::Positional && history.contains(&next.fingerprint()) {
+            Err(GraphErr::RuleViolation(
+                "This move would repeat an earlier position".to_owned(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    // check if a player (or their team) has brought home enough figures to win.
+    // Partners share the win condition and need `figures` per team member combined
+    pub fn has_won(&self, state: &GraphState, player: u8, teams: &Teams) -> bool {
+        if self.teams {
+            let members = teams.members(player);
+            let exited: usize = members.iter().map(|member| state.exited(*member)).sum();
+            exited >= self.figures as usize * members.len()
+        } else {
+            state.exited(player) >= self.figures as usize
+        }
+    }
+}
+
+impl Teams {
+    pub fn team(&self, player: u8) -> Option<u8> {
+        self.0.get(player as usize).copied().flatten()
+    }
+
+    // all players in the same team as `player` (including `player`)
+    pub fn members(&self, player: u8) -> Vec<u8> {
+        match self.team(player) {
+            Some(team) => (0..self.0.len() as u8)
+                .filter(|other| self.team(*other) == Some(team))
+                .collect(),
+            None => vec![player],
+        }
+    }
+
+    pub fn partners(&self, first: u8, second: u8) -> bool {
+        first != second && self.team(first).is_some() && self.team(first) == self.team(second)
+    }
+
+    // check if the assignment is a valid 2 vs 2 setup
+    pub fn validate(&self) -> Result<(), GraphErr> {
+        if self.0.len() != TEAM_PLAYERS {
+            return Err(GraphErr::RuleViolation(format!(
+                "Team games require exactly {} players",
+                TEAM_PLAYERS
+            )));
+        }
+
+        for team in 0..2 {
+            if self
+                .0
+                .iter()
+                .filter(|member| **member == Some(team))
+                .count()
+                != TEAM_SIZE
+            {
+                return Err(GraphErr::RuleViolation(format!(
+                    "Every team needs exactly {} players",
+                    TEAM_SIZE
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// DB mapping (SMALLINT)
+impl From<i16> for KoRule {
+    fn from(value: i16) -> KoRule {
+        match value {
+            0 => KoRule::Off,
+            2 => KoRule::Positional,
+            _ => KoRule::Simple,
+        }
+    }
+}
+
+impl From<KoRule> for i16 {
+    fn from(rule: KoRule) -> i16 {
+        match rule {
+            KoRule::Off => 0,
+            KoRule::Simple => 1,
+            KoRule::Positional => 2,
+        }
+    }
+}
+
+impl From<i16> for AbandonRule {
+    fn from(value: i16) -> AbandonRule {
+        match value {
+            1 => AbandonRule::Remove,
+            _ => AbandonRule::Freeze,
+        }
+    }
+}
+
+impl From<AbandonRule> for i16 {
+    fn from(rule: AbandonRule) -> i16 {
+        match rule {
+            AbandonRule::Freeze => 0,
+            AbandonRule::Remove => 1,
+        }
+    }
+}
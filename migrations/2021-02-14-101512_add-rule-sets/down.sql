@@ -0,0 +1,6 @@
+ALTER TABLE games
+    DROP COLUMN figures,
+    DROP COLUMN swaps,
+    DROP COLUMN stoppers,
+    DROP COLUMN move_clock,
+    DROP COLUMN ko;
\ No newline at end of file
@@ -0,0 +1,6 @@
+ALTER TABLE games
+    ADD COLUMN figures SMALLINT NOT NULL DEFAULT 3,
+    ADD COLUMN swaps BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN stoppers BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN move_clock INT,
+    ADD COLUMN ko SMALLINT NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+ALTER TABLE games DROP COLUMN teams;
+ALTER TABLE user_games DROP COLUMN team;
\ No newline at end of file
@@ -0,0 +1,5 @@
+ALTER TABLE games
+    ADD COLUMN teams BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE user_games
+    ADD COLUMN team SMALLINT;
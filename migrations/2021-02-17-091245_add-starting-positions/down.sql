@@ -0,0 +1 @@
+ALTER TABLE games DROP COLUMN base;
\ No newline at end of file
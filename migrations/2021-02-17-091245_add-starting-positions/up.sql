@@ -0,0 +1,3 @@
+-- JSON encoded graph::position::StartingPosition
+ALTER TABLE games
+    ADD COLUMN base TEXT;
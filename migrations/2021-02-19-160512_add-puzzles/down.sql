@@ -0,0 +1 @@
+DROP TABLE puzzles;
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE PUZZLES (
+    id serial PRIMARY KEY,
+    name TEXT NOT NULL,
+    author uuid REFERENCES USERS (id) ON DELETE CASCADE NOT NULL,
+    -- JSON encoded graph::position::StartingPosition
+    base TEXT NOT NULL
+);
@@ -0,0 +1,8 @@
+CREATE TABLE CHAT_MESSAGES (
+    id serial PRIMARY KEY,
+    game_id INT REFERENCES GAMES (id) ON DELETE CASCADE NOT NULL,
+    user_id uuid REFERENCES USERS (id) ON DELETE CASCADE NOT NULL,
+    message TEXT NOT NULL,
+    -- unix timestamp (seconds)
+    sent BIGINT NOT NULL
+);
@@ -0,0 +1,3 @@
+ALTER TABLE games
+    DROP CONSTRAINT games_state_check,
+    ALTER COLUMN state DROP DEFAULT;
@@ -0,0 +1,5 @@
+-- 0 lobby, 1 running, 2 paused, 3 finished, 4 aborted (See db::models::GameStatus)
+UPDATE games SET state = 4 WHERE state NOT BETWEEN 0 AND 4;
+ALTER TABLE games
+    ALTER COLUMN state SET DEFAULT 0,
+    ADD CONSTRAINT games_state_check CHECK (state BETWEEN 0 AND 4);
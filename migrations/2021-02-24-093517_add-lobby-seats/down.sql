@@ -0,0 +1,6 @@
+ALTER TABLE user_games
+    DROP CONSTRAINT user_games_icon_unique,
+    DROP CONSTRAINT user_games_seat_unique,
+    DROP COLUMN ready,
+    DROP COLUMN icon,
+    DROP COLUMN seat;
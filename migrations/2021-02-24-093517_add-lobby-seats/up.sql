@@ -0,0 +1,7 @@
+-- seat (turn order), piece icon and ready check chosen in the lobby
+ALTER TABLE user_games
+    ADD COLUMN seat SMALLINT,
+    ADD COLUMN icon TEXT,
+    ADD COLUMN ready BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD CONSTRAINT user_games_seat_unique UNIQUE (game_id, seat),
+    ADD CONSTRAINT user_games_icon_unique UNIQUE (game_id, icon);
@@ -0,0 +1,4 @@
+DROP TABLE game_bans;
+ALTER TABLE games
+    DROP COLUMN locked,
+    DROP COLUMN host;
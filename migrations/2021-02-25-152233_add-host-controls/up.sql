@@ -0,0 +1,18 @@
+ALTER TABLE games
+    ADD COLUMN host UUID REFERENCES USERS (id) ON DELETE SET NULL,
+    ADD COLUMN locked BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- the first player to join created the game
+UPDATE games SET host = (
+    SELECT user_id FROM user_games
+    WHERE user_games.game_id = games.id
+    ORDER BY user_games.id
+    LIMIT 1
+);
+
+CREATE TABLE GAME_BANS (
+    id serial PRIMARY KEY,
+    game_id INT REFERENCES GAMES (id) ON DELETE CASCADE NOT NULL,
+    user_id uuid REFERENCES USERS (id) ON DELETE CASCADE NOT NULL,
+    UNIQUE (game_id, user_id)
+);
@@ -0,0 +1,4 @@
+ALTER TABLE user_games DROP COLUMN outcome;
+ALTER TABLE games
+    DROP COLUMN result_reason,
+    DROP COLUMN abandon;
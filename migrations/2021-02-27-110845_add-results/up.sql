@@ -0,0 +1,8 @@
+-- 0 freeze, 1 remove figures of players that resigned or abandoned (See rules::AbandonRule)
+ALTER TABLE games
+    ADD COLUMN abandon SMALLINT NOT NULL DEFAULT 0,
+    ADD COLUMN result_reason SMALLINT;
+
+-- 0 lost, 1 won, 2 draw (See db::models::Outcome)
+ALTER TABLE user_games
+    ADD COLUMN outcome SMALLINT;
@@ -0,0 +1,3 @@
+ALTER TABLE games
+    DROP COLUMN increment,
+    DROP COLUMN base_time;
@@ -0,0 +1,4 @@
+-- chess-style clocks: base time per player and increment per turn in seconds (See rules::TimeControl)
+ALTER TABLE games
+    ADD COLUMN base_time INTEGER,
+    ADD COLUMN increment INTEGER NOT NULL DEFAULT 0;
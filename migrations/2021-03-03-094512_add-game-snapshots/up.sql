@@ -0,0 +1,8 @@
+-- in-memory state of running games for continuing them after a restart (See ws::snapshot::RoomSnapshot)
+CREATE TABLE GAME_SNAPSHOTS (
+    game_id INT PRIMARY KEY REFERENCES GAMES (id) ON DELETE CASCADE,
+    -- serialized snapshot (JSON)
+    data TEXT NOT NULL,
+    -- unix timestamp (seconds)
+    taken BIGINT NOT NULL
+);
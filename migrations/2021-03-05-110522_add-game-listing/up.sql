@@ -0,0 +1,4 @@
+-- icon and pin chosen by the host. Only private games have a pin
+ALTER TABLE games
+    ADD COLUMN icon TEXT NOT NULL DEFAULT 'bi-circle-fill',
+    ADD COLUMN pin SMALLINT[];
@@ -18,12 +18,19 @@ pub type SecretKey = [u8; 4096];
 pub const DEFAULT_CONFIG_NAME: &str = "pentagame.toml";
 pub const DEFAULT_KEY_FILE: &str = "secret.key";
 pub const DEFAULT_RATE_LIMIT: usize = 100;
+pub const DEFAULT_ABANDON_TIMEOUT: u64 = 300;
+pub const DEFAULT_OUTBOUND_QUEUE: usize = 64;
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 30;
+pub const DEFAULT_SHUTDOWN_NOTICE: u64 = 30;
+pub const DEFAULT_MAX_ACTIVE_GAMES: usize = 5;
 
 #[derive(Deserialize, Clone, Serialize)]
 pub struct Config {
     pub server: ServerConfig,
     pub auth: AuthenticationConfig,
     pub admin: AdminConfig,
+    #[serde(default)]
+    pub games: GamesConfig,
 }
 
 #[derive(Deserialize, Clone, Serialize)]
@@ -39,6 +46,52 @@ pub struct AdminConfig {
     pub password: String,
 }
 
+#[derive(Deserialize, Clone, Serialize)]
+pub struct GamesConfig {
+    // seconds a player may stay disconnected from a running game before losing by abandonment
+    pub abandon_timeout: u64,
+    // events a session may have queued before it's resynced with the full state
+    #[serde(default = "default_outbound_queue")]
+    pub outbound_queue: usize,
+    // seconds between snapshots of running games (See ws::snapshot::RoomSnapshot)
+    #[serde(default = "default_snapshot_interval")]
+    pub snapshot_interval: u64,
+    // seconds players are warned before the server shuts down
+    #[serde(default = "default_shutdown_notice")]
+    pub shutdown_notice: u64,
+    // games a user may have joined at once (lobbies and running games)
+    #[serde(default = "default_max_active_games")]
+    pub max_active_games: usize,
+}
+
+fn default_outbound_queue() -> usize {
+    DEFAULT_OUTBOUND_QUEUE
+}
+
+fn default_snapshot_interval() -> u64 {
+    DEFAULT_SNAPSHOT_INTERVAL
+}
+
+fn default_shutdown_notice() -> u64 {
+    DEFAULT_SHUTDOWN_NOTICE
+}
+
+fn default_max_active_games() -> usize {
+    DEFAULT_MAX_ACTIVE_GAMES
+}
+
+impl Default for GamesConfig {
+    fn default() -> GamesConfig {
+        GamesConfig {
+            abandon_timeout: DEFAULT_ABANDON_TIMEOUT,
+            outbound_queue: DEFAULT_OUTBOUND_QUEUE,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+            shutdown_notice: DEFAULT_SHUTDOWN_NOTICE,
+            max_active_games: DEFAULT_MAX_ACTIVE_GAMES,
+        }
+    }
+}
+
 #[derive(Deserialize, Clone, Serialize)]
 pub struct ServerConfig {
     pub ip: String,
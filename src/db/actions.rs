@@ -1,4 +1,303 @@
 /*
 Database actions. This file should contain functions that are used in multiple places e.g. get_game or are to complex to be writen inline
 */
-use diesel::prelude::*;
\ No newline at end of file
+use super::models::{
+    ChatMessage, Game, GameSnapshot, GameStatus, NewChatMessage, NewGameBan, NewGameSnapshot,
+    NewPuzzle, Outcome, ResultReason, UserGame,
+};
+use super::schema::{chat_messages, game_bans, game_snapshots, games, puzzles, user_games, users};
+use diesel::prelude::*;
+use diesel::result::Error as DBError;
+use uuid::Uuid;
+
+pub fn get_game_record(conn: &PgConnection, gid: i32) -> Result<Game, DBError> {
+    games::table.find(gid).first::<Game>(conn)
+}
+
+// assign a player to a team (partnership games) or reset the assignment with `None`
+pub fn set_user_team(
+    conn: &PgConnection,
+    gid: i32,
+    uid: Uuid,
+    team: Option<i16>,
+) -> Result<usize, DBError> {
+    diesel::update(
+        user_games::table
+            .filter(user_games::game_id.eq(gid))
+            .filter(user_games::user_id.eq(uid)),
+    )
+    .set(user_games::team.eq(team))
+    .execute(conn)
+}
+
+pub fn get_game_host(conn: &PgConnection, gid: i32) -> Result<Uuid, DBError> {
+    games::table
+        .find(gid)
+        .select(games::host)
+        .first::<Option<Uuid>>(conn)?
+        .ok_or(DBError::NotFound)
+}
+
+pub fn set_game_host(conn: &PgConnection, gid: i32, host: Uuid) -> Result<usize, DBError> {
+    diesel::update(games::table.find(gid))
+        .set(games::host.eq(Some(host)))
+        .execute(conn)
+}
+
+pub fn set_game_locked(conn: &PgConnection, gid: i32, locked: bool) -> Result<usize, DBError> {
+    diesel::update(games::table.find(gid))
+        .set(games::locked.eq(locked))
+        .execute(conn)
+}
+
+// remove a player from a game (kick)
+pub fn remove_user_game(conn: &PgConnection, gid: i32, uid: Uuid) -> Result<usize, DBError> {
+    diesel::delete(
+        user_games::table
+            .filter(user_games::game_id.eq(gid))
+            .filter(user_games::user_id.eq(uid)),
+    )
+    .execute(conn)
+}
+
+// remove a player from a game and keep them from joining it again
+pub fn ban_user(conn: &PgConnection, gid: i32, uid: Uuid) -> Result<usize, DBError> {
+    conn.transaction(|| {
+        remove_user_game(conn, gid, uid)?;
+        diesel::insert_into(game_bans::table)
+            .values(&NewGameBan {
+                game_id: gid,
+                user_id: uid,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)
+    })
+}
+
+pub fn is_banned(conn: &PgConnection, gid: i32, uid: Uuid) -> Result<bool, DBError> {
+    diesel::select(diesel::dsl::exists(
+        game_bans::table
+            .filter(game_bans::game_id.eq(gid))
+            .filter(game_bans::user_id.eq(uid)),
+    ))
+    .get_result(conn)
+}
+
+// all players of a game including their lobby choices
+pub fn get_user_games(conn: &PgConnection, gid: i32) -> Result<Vec<UserGame>, DBError> {
+    user_games::table
+        .filter(user_games::game_id.eq(gid))
+        .order(user_games::id.asc())
+        .load::<UserGame>(conn)
+}
+
+// ids and names of the players of a game in join order
+pub fn get_game_users(conn: &PgConnection, gid: i32) -> Result<Vec<(Uuid, String)>, DBError> {
+    user_games::table
+        .inner_join(users::table)
+        .filter(user_games::game_id.eq(gid))
+        .order(user_games::id.asc())
+        .select((users::id, users::name))
+        .load::<(Uuid, String)>(conn)
+}
+
+// delete a game together with its players, chat, bans and snapshot
+pub fn remove_game(conn: &PgConnection, gid: i32) -> Result<usize, DBError> {
+    diesel::delete(games::table.find(gid)).execute(conn)
+}
+
+pub fn is_player(conn: &PgConnection, gid: i32, uid: Uuid) -> Result<bool, DBError> {
+    diesel::select(diesel::dsl::exists(
+        user_games::table
+            .filter(user_games::game_id.eq(gid))
+            .filter(user_games::user_id.eq(uid)),
+    ))
+    .get_result(conn)
+}
+
+// games a user joined that aren't over yet, newest first
+pub fn get_active_games(conn: &PgConnection, uid: Uuid) -> Result<Vec<Game>, DBError> {
+    user_games::table
+        .inner_join(games::table)
+        .filter(user_games::user_id.eq(uid))
+        .filter(games::state.lt(i16::from(GameStatus::Finished)))
+        .order(games::id.desc())
+        .select(games::all_columns)
+        .load::<Game>(conn)
+}
+
+// store the lobby choices of a player. Seats and icons are unique per game
+pub fn set_user_lobby(
+    conn: &PgConnection,
+    gid: i32,
+    uid: Uuid,
+    seat: Option<i16>,
+    icon: Option<&str>,
+    ready: bool,
+) -> Result<usize, DBError> {
+    diesel::update(
+        user_games::table
+            .filter(user_games::game_id.eq(gid))
+            .filter(user_games::user_id.eq(uid)),
+    )
+    .set((
+        user_games::seat.eq(seat),
+        user_games::icon.eq(icon),
+        user_games::ready.eq(ready),
+    ))
+    .execute(conn)
+}
+
+// persist a lifecycle transition. Transitions are checked by the caller (See GameStatus::can_become)
+pub fn set_game_status(
+    conn: &PgConnection,
+    gid: i32,
+    status: GameStatus,
+) -> Result<usize, DBError> {
+    diesel::update(games::table.find(gid))
+        .set(games::state.eq(i16::from(status)))
+        .execute(conn)
+}
+
+// record the final result of a game and the outcome of every player
+pub fn set_game_result(
+    conn: &PgConnection,
+    gid: i32,
+    reason: ResultReason,
+    outcomes: &[(Uuid, Outcome)],
+) -> Result<(), DBError> {
+    conn.transaction(|| {
+        diesel::update(games::table.find(gid))
+            .set(games::result_reason.eq(Some(i16::from(reason))))
+            .execute(conn)?;
+
+        for (uid, outcome) in outcomes {
+            diesel::update(
+                user_games::table
+                    .filter(user_games::game_id.eq(gid))
+                    .filter(user_games::user_id.eq(uid)),
+            )
+            .set(user_games::outcome.eq(Some(i16::from(*outcome))))
+            .execute(conn)?;
+        }
+
+        Ok(())
+    })
+}
+
+// players that won a finished game
+pub fn get_game_winners(conn: &PgConnection, gid: i32) -> Result<Vec<Uuid>, DBError> {
+    user_games::table
+        .filter(user_games::game_id.eq(gid))
+        .filter(user_games::outcome.eq(Some(i16::from(Outcome::Won))))
+        .select(user_games::user_id)
+        .load::<Uuid>(conn)
+}
+
+// games that aren't over yet with the number of their players
+pub fn get_open_games(conn: &PgConnection) -> Result<Vec<(Game, usize)>, DBError> {
+    let open = games::table
+        .left_join(user_games::table)
+        .filter(games::state.lt(i16::from(GameStatus::Finished)))
+        .group_by(games::id)
+        // diesel 1.4 can't select aggregates next to columns. Grouping by the primary key is fine
+        // for postgres though
+        .select((
+            games::all_columns,
+            diesel::dsl::sql::<diesel::sql_types::BigInt>("count(user_games.id)"),
+        ))
+        .order(games::id.desc())
+        .load::<(Game, i64)>(conn)?;
+
+    Ok(open
+        .into_iter()
+        .map(|(game, players)| (game, players as usize))
+        .collect())
+}
+
+// replace the starting position of a game (JSON encoded StartingPosition)
+pub fn set_game_base(
+    conn: &PgConnection,
+    gid: i32,
+    base: Option<String>,
+) -> Result<usize, DBError> {
+    diesel::update(games::table.find(gid))
+        .set(games::base.eq(base))
+        .execute(conn)
+}
+
+pub fn create_puzzle(
+    conn: &PgConnection,
+    name: &str,
+    author: Uuid,
+    base: &str,
+) -> Result<i32, DBError> {
+    diesel::insert_into(puzzles::table)
+        .values(&NewPuzzle { name, author, base })
+        .returning(puzzles::id)
+        .get_result(conn)
+}
+
+pub fn add_chat_message(
+    conn: &PgConnection,
+    game_id: i32,
+    user_id: Uuid,
+    message: &str,
+    sent: i64,
+) -> Result<ChatMessage, DBError> {
+    diesel::insert_into(chat_messages::table)
+        .values(&NewChatMessage {
+            game_id,
+            user_id,
+            message,
+            sent,
+        })
+        .get_result(conn)
+}
+
+// latest chat messages of a game (oldest first)
+pub fn get_chat_messages(
+    conn: &PgConnection,
+    gid: i32,
+    limit: i64,
+) -> Result<Vec<ChatMessage>, DBError> {
+    let mut messages = chat_messages::table
+        .filter(chat_messages::game_id.eq(gid))
+        .order(chat_messages::id.desc())
+        .limit(limit)
+        .load::<ChatMessage>(conn)?;
+    messages.reverse();
+    Ok(messages)
+}
+
+// store the latest snapshot of a running game. Older snapshots are replaced
+pub fn save_game_snapshot(
+    conn: &PgConnection,
+    game_id: i32,
+    data: &str,
+    taken: i64,
+) -> Result<usize, DBError> {
+    let snapshot = NewGameSnapshot {
+        game_id,
+        data,
+        taken,
+    };
+
+    diesel::insert_into(game_snapshots::table)
+        .values(&snapshot)
+        .on_conflict(game_snapshots::game_id)
+        .do_update()
+        .set(&snapshot)
+        .execute(conn)
+}
+
+pub fn get_game_snapshot(conn: &PgConnection, gid: i32) -> Result<Option<GameSnapshot>, DBError> {
+    game_snapshots::table
+        .find(gid)
+        .first::<GameSnapshot>(conn)
+        .optional()
+}
+
+pub fn remove_game_snapshot(conn: &PgConnection, gid: i32) -> Result<usize, DBError> {
+    diesel::delete(game_snapshots::table.find(gid)).execute(conn)
+}
@@ -10,16 +10,223 @@ a comprehensive mapping of PG, rust and diesel types
 */
 
 use super::schema::*;
-use diesel::{Associations, Identifiable, Queryable};
+use crate::graph::errors::GraphErr;
+use crate::graph::graph::GraphState;
+use crate::graph::position::StartingPosition;
+use crate::graph::rules::RuleSet;
+use derive_more::Display;
+use diesel::{AsChangeset, Associations, Identifiable, Insertable, Queryable};
+use schemars::JsonSchema;
 use serde::Serialize;
+use std::convert::TryFrom;
 use uuid::Uuid;
 
+/*
+Lifecycle of a game as stored in `games.state`
+---
+| state | status   | next                        |
+| ----- | -------- | --------------------------- |
+| 0     | Lobby    | Running, Aborted            |
+| 1     | Running  | Paused, Finished, Aborted   |
+| 2     | Paused   | Running, Finished, Aborted  |
+| 3     | Finished |                             |
+| 4     | Aborted  |                             |
+*/
+#[derive(Serialize, Display, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GameStatus {
+    Lobby,
+    Running,
+    Paused,
+    Finished,
+    Aborted,
+}
+
+impl GameStatus {
+    // whether the lifecycle allows moving on to `next`
+    pub fn can_become(&self, next: GameStatus) -> bool {
+        use GameStatus::*;
+
+        matches!(
+            (*self, next),
+            (Lobby, Running)
+                | (Lobby, Aborted)
+                | (Running, Paused)
+                | (Running, Finished)
+                | (Running, Aborted)
+                | (Paused, Running)
+                | (Paused, Finished)
+                | (Paused, Aborted)
+        )
+    }
+
+    // finished and aborted games can't change anymore
+    pub fn is_over(&self) -> bool {
+        matches!(self, GameStatus::Finished | GameStatus::Aborted)
+    }
+}
+
+// Why a game ended (`games.result_reason`)
+#[derive(Serialize, Display, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultReason {
+    #[display(fmt = "Won")]
+    Won,
+    #[display(fmt = "Resignation")]
+    Resigned,
+    #[display(fmt = "Abandonment")]
+    Abandoned,
+    #[display(fmt = "Draw by agreement")]
+    Draw,
+    #[display(fmt = "Timeout")]
+    Timeout,
+}
+
+// Result of a single player (`user_games.outcome`)
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Lost,
+    Won,
+    Draw,
+}
+
+impl From<i16> for ResultReason {
+    fn from(value: i16) -> ResultReason {
+        match value {
+            1 => ResultReason::Resigned,
+            2 => ResultReason::Abandoned,
+            3 => ResultReason::Draw,
+            4 => ResultReason::Timeout,
+            _ => ResultReason::Won,
+        }
+    }
+}
+
+impl From<ResultReason> for i16 {
+    fn from(reason: ResultReason) -> i16 {
+        match reason {
+            ResultReason::Won => 0,
+            ResultReason::Resigned => 1,
+            ResultReason::Abandoned => 2,
+            ResultReason::Draw => 3,
+            ResultReason::Timeout => 4,
+        }
+    }
+}
+
+impl From<i16> for Outcome {
+    fn from(value: i16) -> Outcome {
+        match value {
+            1 => Outcome::Won,
+            2 => Outcome::Draw,
+            _ => Outcome::Lost,
+        }
+    }
+}
+
+impl From<Outcome> for i16 {
+    fn from(outcome: Outcome) -> i16 {
+        match outcome {
+            Outcome::Lost => 0,
+            Outcome::Won => 1,
+            Outcome::Draw => 2,
+        }
+    }
+}
+
+impl TryFrom<i16> for GameStatus {
+    type Error = String;
+
+    fn try_from(value: i16) -> Result<GameStatus, String> {
+        match value {
+            0 => Ok(GameStatus::Lobby),
+            1 => Ok(GameStatus::Running),
+            2 => Ok(GameStatus::Paused),
+            3 => Ok(GameStatus::Finished),
+            4 => Ok(GameStatus::Aborted),
+            _ => Err(format!("Unknown game state {}", value)),
+        }
+    }
+}
+
+impl From<GameStatus> for i16 {
+    fn from(status: GameStatus) -> i16 {
+        match status {
+            GameStatus::Lobby => 0,
+            GameStatus::Running => 1,
+            GameStatus::Paused => 2,
+            GameStatus::Finished => 3,
+            GameStatus::Aborted => 4,
+        }
+    }
+}
+
 #[derive(Identifiable, Serialize, Queryable, Clone, PartialEq, Debug)]
 pub struct Game {
     pub id: i32,
     pub name: String,
     pub state: i16,
     pub description: Option<String>,
+    pub figures: i16,
+    pub swaps: bool,
+    pub stoppers: bool,
+    pub move_clock: Option<i32>,
+    pub ko: i16,
+    pub teams: bool,
+    pub base: Option<String>,
+    pub host: Option<Uuid>,
+    // locked lobbies don't accept new players
+    pub locked: bool,
+    pub abandon: i16,
+    pub result_reason: Option<i16>,
+    pub base_time: Option<i32>,
+    pub increment: i32,
+    pub icon: String,
+    // digits of the pin. Only private games have one
+    pub pin: Option<Vec<i16>>,
+}
+
+impl Game {
+    // `games.state` is constrained to the known states (See migration add-game-lifecycle)
+    pub fn status(&self) -> GameStatus {
+        GameStatus::try_from(self.state).unwrap_or(GameStatus::Aborted)
+    }
+
+    pub fn result_reason(&self) -> Option<ResultReason> {
+        self.result_reason.map(ResultReason::from)
+    }
+
+    pub fn rules(&self) -> RuleSet {
+        RuleSet {
+            figures: self.figures,
+            swaps: self.swaps,
+            stoppers: self.stoppers,
+            move_clock: self.move_clock,
+            base_time: self.base_time,
+            increment: self.increment,
+            ko: self.ko.into(),
+            teams: self.teams,
+            abandon: self.abandon.into(),
+        }
+    }
+
+    pub fn starting_position(&self) -> Result<Option<StartingPosition>, GraphErr> {
+        match &self.base {
+            Some(raw) => serde_json::from_str(raw)
+                .map(Some)
+                .map_err(|why| GraphErr::CannotConstructState(why.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    // starting state of the board. Stored positions are checked for legality again
+    pub fn base_state(&self) -> Result<GraphState, GraphErr> {
+        match self.starting_position()? {
+            Some(position) => GraphState::try_from(position.locations),
+            None => Ok(GraphState::empty()),
+        }
+    }
 }
 
 #[derive(Identifiable, Serialize, Queryable, Clone, PartialEq, Debug)]
@@ -37,4 +244,82 @@ pub struct UserGame {
     pub id: i32,
     pub user_id: Uuid,
     pub game_id: i32,
+    pub team: Option<i16>,
+    // lobby choices (See ws::models::LobbyEntry)
+    pub seat: Option<i16>,
+    pub icon: Option<String>,
+    pub ready: bool,
+    pub outcome: Option<i16>,
+}
+
+#[derive(Identifiable, Serialize, Queryable, Clone, PartialEq, Debug)]
+pub struct Puzzle {
+    pub id: i32,
+    pub name: String,
+    pub author: Uuid,
+    pub base: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "puzzles"]
+pub struct NewPuzzle<'a> {
+    pub name: &'a str,
+    pub author: Uuid,
+    pub base: &'a str,
+}
+
+#[derive(Identifiable, Associations, Serialize, Queryable, Clone, PartialEq, Debug)]
+#[table_name = "chat_messages"]
+#[belongs_to(User)]
+#[belongs_to(Game)]
+pub struct ChatMessage {
+    pub id: i32,
+    pub game_id: i32,
+    pub user_id: Uuid,
+    pub message: String,
+    pub sent: i64, // unix timestamp
+}
+
+#[derive(Insertable)]
+#[table_name = "chat_messages"]
+pub struct NewChatMessage<'a> {
+    pub game_id: i32,
+    pub user_id: Uuid,
+    pub message: &'a str,
+    pub sent: i64,
+}
+
+#[derive(Identifiable, Associations, Queryable, PartialEq, Debug)]
+#[table_name = "game_bans"]
+#[belongs_to(User)]
+#[belongs_to(Game)]
+pub struct GameBan {
+    pub id: i32,
+    pub game_id: i32,
+    pub user_id: Uuid,
+}
+
+#[derive(Insertable)]
+#[table_name = "game_bans"]
+pub struct NewGameBan {
+    pub game_id: i32,
+    pub user_id: Uuid,
+}
+
+#[derive(Identifiable, Associations, Queryable, PartialEq, Debug)]
+#[table_name = "game_snapshots"]
+#[primary_key(game_id)]
+#[belongs_to(Game)]
+pub struct GameSnapshot {
+    pub game_id: i32,
+    pub data: String, // JSON (See ws::snapshot::RoomSnapshot)
+    pub taken: i64,   // unix timestamp
+}
+
+#[derive(Insertable, AsChangeset)]
+#[table_name = "game_snapshots"]
+pub struct NewGameSnapshot<'a> {
+    pub game_id: i32,
+    pub data: &'a str,
+    pub taken: i64,
 }
@@ -1,9 +1,59 @@
+table! {
+    chat_messages (id) {
+        id -> Int4,
+        game_id -> Int4,
+        user_id -> Uuid,
+        message -> Text,
+        sent -> Int8,
+    }
+}
+
+table! {
+    game_bans (id) {
+        id -> Int4,
+        game_id -> Int4,
+        user_id -> Uuid,
+    }
+}
+
+table! {
+    game_snapshots (game_id) {
+        game_id -> Int4,
+        data -> Text,
+        taken -> Int8,
+    }
+}
+
 table! {
     games (id) {
         id -> Int4,
         name -> Text,
         description -> Nullable<Text>,
         state -> Int2,
+        figures -> Int2,
+        swaps -> Bool,
+        stoppers -> Bool,
+        move_clock -> Nullable<Int4>,
+        ko -> Int2,
+        teams -> Bool,
+        base -> Nullable<Text>,
+        host -> Nullable<Uuid>,
+        locked -> Bool,
+        abandon -> Int2,
+        result_reason -> Nullable<Int2>,
+        base_time -> Nullable<Int4>,
+        increment -> Int4,
+        icon -> Text,
+        pin -> Nullable<Array<Int2>>,
+    }
+}
+
+table! {
+    puzzles (id) {
+        id -> Int4,
+        name -> Text,
+        author -> Uuid,
+        base -> Text,
     }
 }
 
@@ -12,6 +62,11 @@ table! {
         id -> Int4,
         user_id -> Uuid,
         game_id -> Int4,
+        team -> Nullable<Int2>,
+        seat -> Nullable<Int2>,
+        icon -> Nullable<Text>,
+        ready -> Bool,
+        outcome -> Nullable<Int2>,
     }
 }
 
@@ -23,11 +78,22 @@ table! {
     }
 }
 
+joinable!(chat_messages -> games (game_id));
+joinable!(chat_messages -> users (user_id));
+joinable!(game_bans -> games (game_id));
+joinable!(game_bans -> users (user_id));
+joinable!(game_snapshots -> games (game_id));
+joinable!(games -> users (host));
+joinable!(puzzles -> users (author));
 joinable!(user_games -> games (game_id));
 joinable!(user_games -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    chat_messages,
+    game_bans,
+    game_snapshots,
     games,
+    puzzles,
     user_games,
     users,
 );
@@ -0,0 +1,100 @@
+use crate::graph::editor::BoardEditor;
+use crate::graph::errors::GraphErr;
+use crate::graph::graph::Figure;
+use crate::graph::models::{FIELD, LOCATION};
+use dashmap::DashMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+// How long an edit session is kept without changes (See sweep)
+pub const EDITOR_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+// Edit sessions a user may have open at once
+pub const MAX_EDITORS: usize = 5;
+
+// Server side board editor session bound to the user that created it
+pub struct EditSession {
+    pub owner: Uuid,
+    pub editor: BoardEditor,
+    pub updated: Instant,
+}
+
+// Edit actions shared by the REST routes and the websocket session
+#[derive(Deserialize, JsonSchema)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum EditAction {
+    Place { figure: Figure, field: FIELD },
+    Remove { figure: Figure },
+    Check,
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(tag = "target", rename_all = "snake_case")]
+pub enum SaveTarget {
+    Puzzle { name: String },
+    Game { game: i32 },
+}
+
+#[derive(Deserialize)]
+pub struct EditorQuery {
+    // start from the starting position of this game instead of an empty board
+    pub game: Option<i32>,
+    // required for private games the user isn't playing in
+    pub pin: Option<String>,
+}
+
+// current position with legality feedback from the engine
+#[derive(Serialize, JsonSchema)]
+pub struct EditResponse {
+    pub id: Uuid,
+    pub pieces: Vec<LOCATION>,
+    pub issues: Vec<String>,
+}
+
+impl EditSession {
+    pub fn new(owner: Uuid, editor: BoardEditor) -> EditSession {
+        EditSession {
+            owner,
+            editor,
+            updated: Instant::now(),
+        }
+    }
+
+    pub fn expired(&self, now: Instant) -> bool {
+        now.duration_since(self.updated) >= EDITOR_TIMEOUT
+    }
+
+    pub fn apply(&mut self, action: EditAction) -> Result<(), GraphErr> {
+        self.updated = Instant::now();
+
+        match action {
+            EditAction::Place { figure, field } => self.editor.place(figure, field),
+            EditAction::Remove { figure } => {
+                if self.editor.remove(&figure) {
+                    Ok(())
+                } else {
+                    Err(GraphErr::CannotConstructState(format!(
+                        "Figure {} isn't on the board",
+                        figure
+                    )))
+                }
+            }
+            EditAction::Check => Ok(()),
+        }
+    }
+
+    pub fn response(&self, id: Uuid) -> EditResponse {
+        EditResponse {
+            id,
+            pieces: self.editor.pieces().to_vec(),
+            issues: self.editor.issues(),
+        }
+    }
+}
+
+// discard edit sessions that weren't changed within EDITOR_TIMEOUT
+pub fn sweep(editors: &DashMap<Uuid, EditSession>) {
+    let now = Instant::now();
+    editors.retain(|_, session| !session.expired(now));
+}
@@ -0,0 +1,224 @@
+use super::models::{
+    sweep, EditAction, EditResponse, EditSession, EditorQuery, SaveTarget, MAX_EDITORS,
+};
+use super::session::WsEditorSession;
+use crate::auth::User;
+use crate::db::actions::{create_puzzle, get_game_host, get_game_record, is_player, set_game_base};
+use crate::db::models::GameStatus;
+use crate::graph::editor::BoardEditor;
+use crate::graph::position::StartingPosition;
+use crate::state::AppState;
+use crate::ws::errors::{WebResult, WebsocketError};
+use actix_web::{
+    web::block, web::Data, web::HttpResponse, web::Json, web::Path, web::Payload, web::Query,
+    HttpRequest,
+};
+use actix_web_actors::ws;
+use dashmap::mapref::one::RefMut;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::time::Instant;
+use uuid::Uuid;
+
+/*
+/editor:
+    /: post_create_editor -> new edit session (optionally from a game's starting position: ?game={id}, private games: &pin={pin})
+    /{id}: get_editor -> current position and legality feedback
+    /{id}/action: post_editor_action -> place/ remove figures (See editor::models::EditAction)
+    /{id}/save: post_save_editor -> save as puzzle or as starting position of a game (host only)
+    /{id}/close: post_close_editor -> discard edit session
+    /ws/{id}: editor_route -> websocket variant of /{id}/action
+*/
+
+#[derive(Serialize, JsonSchema)]
+pub struct SaveResponse {
+    // id of the puzzle or game the position was saved to
+    pub id: i32,
+}
+
+// fetch user and their edit session. The session is locked until it's dropped
+fn guard_editor<'a>(
+    id: Option<User>,
+    state: &'a AppState,
+    eid: &Uuid,
+) -> WebResult<(User, RefMut<'a, Uuid, EditSession>)> {
+    let user = id.ok_or(WebsocketError::AuthorizationError())?;
+
+    match state.editors.get_mut(eid) {
+        Some(session) if session.owner == user.id => Ok((user, session)),
+        Some(_) => Err(WebsocketError::AuthorizationError()),
+        None => Err(WebsocketError::ValidationError(
+            "Edit session not found".to_owned(),
+        )),
+    }
+}
+
+pub async fn post_create_editor(
+    id: Option<User>,
+    query: Query<EditorQuery>,
+    pool: Data<DbPool>,
+    state: Data<AppState>,
+) -> WebResult<HttpResponse> {
+    let user = id.ok_or(WebsocketError::AuthorizationError())?;
+
+    // expired sessions don't count towards the limit
+    sweep(&state.editors);
+    let open = state
+        .editors
+        .iter()
+        .filter(|session| session.owner == user.id)
+        .count();
+    if open >= MAX_EDITORS {
+        return Err(WebsocketError::ValidationError(format!(
+            "You can't have more than {} editors open at once",
+            MAX_EDITORS
+        )));
+    }
+
+    let editor = match query.game {
+        Some(gid) => {
+            let conn = pool.get()?;
+            let game = block(move || get_game_record(&conn, gid)).await?;
+
+            // starting positions of private games are only shown to their players or with the pin
+            if let Some(pin) = &game.pin {
+                let conn = pool.get()?;
+                let uid = user.id;
+                let player = block(move || is_player(&conn, gid, uid)).await?;
+                let stringified: String = pin.iter().map(|number| number.to_string()).collect();
+
+                if !player && query.pin.as_deref() != Some(stringified.as_str()) {
+                    return Err(WebsocketError::AuthorizationError());
+                }
+            }
+
+            BoardEditor::from_state(&game.base_state()?)
+        }
+        None => BoardEditor::new(),
+    };
+
+    let eid = Uuid::new_v4();
+    let session = EditSession::new(user.id, editor);
+    let response = session.response(eid);
+    state.editors.insert(eid, session);
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn get_editor(
+    id: Option<User>,
+    path: Path<(Uuid,)>,
+    state: Data<AppState>,
+) -> WebResult<HttpResponse> {
+    let eid = path.into_inner().0;
+    let (_, session) = guard_editor(id, &state, &eid)?;
+
+    let response: EditResponse = session.response(eid);
+    Ok(HttpResponse::Ok().json(response))
+}
+
+pub async fn post_editor_action(
+    id: Option<User>,
+    path: Path<(Uuid,)>,
+    action: Json<EditAction>,
+    state: Data<AppState>,
+) -> WebResult<HttpResponse> {
+    let eid = path.into_inner().0;
+    let (_, mut session) = guard_editor(id, &state, &eid)?;
+
+    session.apply(action.into_inner())?;
+
+    Ok(HttpResponse::Ok().json(session.response(eid)))
+}
+
+pub async fn post_save_editor(
+    id: Option<User>,
+    path: Path<(Uuid,)>,
+    target: Json<SaveTarget>,
+    pool: Data<DbPool>,
+    state: Data<AppState>,
+) -> WebResult<HttpResponse> {
+    let eid = path.into_inner().0;
+    let (user, session) = guard_editor(id, &state, &eid)?;
+
+    // only legal positions can be saved. The session is released before accessing the database
+    let base = {
+        let position = session.editor.to_state()?;
+        serde_json::to_string(&StartingPosition {
+            locations: (&position).into(),
+            handicaps: Vec::new(),
+        })
+        .map_err(|why| WebsocketError::InternalError(why.to_string()))?
+    };
+    drop(session);
+
+    let conn = pool.get()?;
+    let saved = match target.into_inner() {
+        SaveTarget::Puzzle { name } => {
+            if name.trim().is_empty() {
+                return Err(WebsocketError::ValidationError(
+                    "Puzzles need a name".to_owned(),
+                ));
+            }
+
+            block(move || create_puzzle(&conn, name.trim(), user.id, &base)).await?
+        }
+        SaveTarget::Game { game } => {
+            // only the host may change the starting position and only before the game started
+            let host = block(move || get_game_host(&conn, game)).await?;
+            if host != user.id {
+                return Err(WebsocketError::AuthorizationError());
+            }
+
+            let conn = pool.get()?;
+            let record = block(move || get_game_record(&conn, game)).await?;
+            if record.status() != GameStatus::Lobby {
+                return Err(WebsocketError::ValidationError(
+                    "The game has already been started".to_owned(),
+                ));
+            }
+
+            let conn = pool.get()?;
+            block(move || set_game_base(&conn, game, Some(base))).await?;
+            game
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(SaveResponse { id: saved }))
+}
+
+pub async fn post_close_editor(
+    id: Option<User>,
+    path: Path<(Uuid,)>,
+    state: Data<AppState>,
+) -> WebResult<HttpResponse> {
+    let eid = path.into_inner().0;
+    let (_, session) = guard_editor(id, &state, &eid)?;
+
+    drop(session);
+    state.editors.remove(&eid);
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub async fn editor_route(
+    req: HttpRequest,
+    stream: Payload,
+    path: Path<(Uuid,)>,
+    state: Data<AppState>,
+    id: Option<User>,
+) -> WebResult<HttpResponse> {
+    let eid = path.into_inner().0;
+    let (user, _) = guard_editor(id, &state, &eid)?;
+
+    ws::start(
+        WsEditorSession {
+            id: eid,
+            user,
+            hb: Instant::now(),
+            state: state.get_ref().clone(),
+        },
+        &req,
+        stream,
+    )
+    .map_err(|why| WebsocketError::InternalError(why.to_string()))
+}
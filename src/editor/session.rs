@@ -0,0 +1,94 @@
+use super::models::EditAction;
+use crate::auth::User;
+use crate::state::AppState;
+use crate::ws::errors::{ErrorMessage, WebsocketError, MESSAGE_FORMAT_ERROR, UNIMPLEMENTED_ERROR};
+use actix::prelude::*;
+use actix_web_actors::ws;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+// How often heartbeat pings are sent
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+// How long before lack of client response causes a timeout
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Websocket session for the board editor. Every action is answered with the resulting
+// position and the legality feedback of the engine (See editor::models::EditResponse)
+pub struct WsEditorSession {
+    // edit session id
+    pub id: Uuid,
+    // bound identity
+    pub user: User,
+    pub hb: Instant,
+    pub state: AppState,
+}
+
+impl Actor for WsEditorSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hb(ctx);
+    }
+}
+
+// WebSocket message handler
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsEditorSession {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        let msg = match msg {
+            Err(_) => return ctx.stop(),
+            Ok(msg) => msg,
+        };
+
+        match msg {
+            ws::Message::Ping(msg) => {
+                self.hb = Instant::now();
+                ctx.pong(&msg);
+            }
+            ws::Message::Pong(_) => {
+                self.hb = Instant::now();
+            }
+            ws::Message::Text(text) => match serde_json::from_str::<EditAction>(&text) {
+                Ok(action) => {
+                    let mut session = match self.state.editors.get_mut(&self.id) {
+                        Some(session) => session,
+                        // edit session was closed (e.g. through the REST api)
+                        None => return ctx.stop(),
+                    };
+
+                    match session.apply(action) {
+                        Ok(_) => ctx.text(
+                            serde_json::to_string(&session.response(self.id))
+                                .unwrap_or("Internal Error: Failed to parse message".to_owned()),
+                        ),
+                        Err(why) => ctx.text(ErrorMessage::text(WebsocketError::from(why))),
+                    }
+                }
+                Err(_) => ctx.text(MESSAGE_FORMAT_ERROR.clone()),
+            },
+            ws::Message::Binary(_) => ctx.text(UNIMPLEMENTED_ERROR.clone()),
+            ws::Message::Close(reason) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            ws::Message::Continuation(_) => {
+                ctx.text(UNIMPLEMENTED_ERROR.clone());
+                ctx.stop();
+            }
+            ws::Message::Nop => (),
+        }
+    }
+}
+
+impl WsEditorSession {
+    // helper method that sends ping to client every second and checks heartbeats from client
+    fn hb(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                ctx.stop();
+                return;
+            }
+
+            ctx.ping(b"");
+        });
+    }
+}
@@ -7,9 +7,31 @@ pub struct GameForm {
     pub description: Option<String>,
     pub icon: String,
     pub pin: Option<String>,
+    // rule set
+    pub figures: Option<i16>,
+    pub swaps: Option<String>,
+    pub stoppers: Option<String>,
+    pub move_clock: Option<String>,
+    // chess-style clock: base time in minutes and increment in seconds
+    pub base_time: Option<String>,
+    pub increment: Option<String>,
+    pub ko: Option<i16>,
+    pub abandon: Option<i16>,
+    pub teams: Option<String>,
+    // custom starting position (See graph::position) and handicaps
+    pub position: Option<String>,
+    pub handicap_player: Option<String>,
+    pub handicap_figures: Option<u8>,
 }
 
 #[derive(Deserialize)]
 pub struct GamePinForm {
     pub pin: String,
 }
+
+// credentials of the [admin] config section
+#[derive(Deserialize)]
+pub struct AdminForm {
+    pub username: String,
+    pub password: String,
+}
@@ -12,6 +12,18 @@ pub fn log_success(prefix: &str, msg: String) {
     println!("[{}]: {}", Green.paint(prefix), msg);
 }
 
+// compare secrets without leaking the position of the first difference through the timing
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let length = a.len().max(b.len());
+    let mut difference = a.len() ^ b.len();
+    for index in 0..length {
+        let left = a.get(index).copied().unwrap_or(0);
+        let right = b.get(index).copied().unwrap_or(0);
+        difference |= usize::from(left ^ right);
+    }
+    difference == 0
+}
+
 #[inline(always)]
 pub fn log_info(prefix: &str, msg: String) {
     use ansi_term::Color::Blue;
@@ -1,8 +1,18 @@
 // imports
 use super::errors::UserError;
+use super::helper::constant_time_eq;
 use super::{forms, templates};
 use crate::auth::User;
-use crate::state::AppState;
+use crate::config::CONFIG;
+use crate::db::actions::{
+    get_active_games, get_game_record, get_game_winners, is_banned, is_player,
+};
+use crate::db::models::{Game, GameStatus};
+use crate::graph::position::{starting_position, Handicap, StartingPosition};
+use crate::graph::rules::{AbandonRule, KoRule, RuleSet, DEFAULT_FIGURES};
+use crate::state::{AppState, Rooms};
+use crate::ws::messages::{GameListing, PublishGame};
+use crate::ws::models::{RateLimit, ADMIN_ATTEMPTS, ADMIN_ATTEMPT_WINDOW};
 use actix_identity::Identity;
 use actix_web::error::ErrorBadRequest;
 use actix_web::{
@@ -12,9 +22,11 @@ use actix_web::{
 use askama_actix::TemplateIntoResponse;
 use futures::future::{err, ok, Ready};
 use rand::{seq::SliceRandom, thread_rng};
+use schemars::JsonSchema;
 use serde::Serialize;
 use serde_json::from_str;
-use std::sync::Arc;
+use std::net::IpAddr;
+use std::sync::{mpsc::Sender, Arc};
 use uuid::Uuid;
 
 // types
@@ -58,13 +70,13 @@ General API Responses
             Post (id, title, body, published)
 */
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ActionStatus {
     code: i8,
     description: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct QueryResult {
     code: i8,
     data: (u32, String, String, bool),
@@ -105,9 +117,44 @@ INFO: All routes except overview require are guarded
     /create: get_create_game -> Simple form for creating a new game
     /view/{id}: get_view_game -> View of game and it's participants
     /join/{id}: f -> Make user join game and redirect to game 'playing' screen
-    /leave: Leave a game (a player may only join one game at a time. Can be changed anytime but works as architectural rate limiting)
+    /leave/{id}: post_game_leave_route (POST) -> Leave a lobby (running games are left by resigning)
+    /mine: get_my_games -> Lobbies and running games of the user
+
+Players may join several games at once, up to the config's `max_active_games`
 */
 
+// users can't join more than `max_active_games` games at once
+fn guard_active_games(active: usize) -> Result<(), UserError> {
+    let limit = CONFIG.games.max_active_games;
+    if active >= limit {
+        Err(UserError::ValidationError(format!(
+            "You can't be in more than {} games at once. Leave or finish one of your games first",
+            limit
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+// only open lobbies can be joined. Players of a running game keep their seat
+fn guard_join(record: &Game, banned: bool) -> Result<(), UserError> {
+    let status = record.status();
+    if banned {
+        Err(UserError::ValidationError(
+            "You have been banned from this game".to_owned(),
+        ))
+    } else if status != GameStatus::Lobby {
+        Err(UserError::ValidationError(format!(
+            "This game can't be joined anymore ({})",
+            status
+        )))
+    } else if record.locked {
+        Err(UserError::ValidationError("This lobby is locked".to_owned()))
+    } else {
+        Ok(())
+    }
+}
+
 pub async fn post_game_join(
     id: Option<User>,
     path: Path<(i32,)>,
@@ -149,48 +196,51 @@ pub async fn post_game_join(
         None => (),
     };
 
+    let conn = pool.get()?;
+    let record = block(move || get_game_record(&conn, gid)).await?;
+    let conn = pool.get()?;
+    let user_id = uid.id;
+    let banned = block(move || is_banned(&conn, gid, user_id)).await?;
+
     let conn = pool.get()?;
 
-    // check if user already joined game
+    // check if user already joined game. Players may be in several games at once
     let sacrifice = uid.id.clone();
-    match block(move || get_user_game(&conn, sacrifice)).await? {
-        Some(current_game_id) => {
-            if current_game_id != gid {
-                let sacrifice = uid.id.clone();
-                let conn = pool.get()?;
-                block(move || leave_game(&conn, sacrifice)).await?;
-                let conn = pool.get()?;
-                block(move || join_game(&conn, sacrifice, gid)).await?;
-            } else {
-                // already joined -> check if host
-                let conn = pool.get()?;
-                let host = block(move || get_game_host(&conn, current_game_id)).await?;
-                if host == uid.id {
-                    return UserError::wrap_template(
-                        templates::GameBoardTemplate {
-                            id: Some(uid),
-                            host: true,
-                        }
-                        .into_response(),
-                    );
-                }
-            }
-        }
-        None => {
-            let conn = pool.get()?;
-            match block(move || join_game(&conn, sacrifice, gid)).await {
-                Ok(_) => (),
-                Err(_) => {
-                    return Err(UserError::ValidationError("game id".to_owned()));
+    if block(move || is_player(&conn, gid, sacrifice)).await? {
+        // already joined -> check if host
+        let conn = pool.get()?;
+        let host = block(move || get_game_host(&conn, gid)).await?;
+        if host == uid.id {
+            return UserError::wrap_template(
+                templates::GameBoardTemplate {
+                    id: Some(uid),
+                    host: true,
+                    game: gid,
+                    spectate: None,
                 }
-            };
+                .into_response(),
+            );
         }
+    } else {
+        guard_join(&record, banned)?;
+        let conn = pool.get()?;
+        let active = block(move || get_active_games(&conn, sacrifice)).await?;
+        guard_active_games(active.len())?;
+        let conn = pool.get()?;
+        match block(move || join_game(&conn, sacrifice, gid)).await {
+            Ok(_) => (),
+            Err(_) => {
+                return Err(UserError::ValidationError("game id".to_owned()));
+            }
+        };
     }
 
     UserError::wrap_template(
         templates::GameBoardTemplate {
             id: Some(uid),
             host: false,
+            game: gid,
+            spectate: None,
         }
         .into_response(),
     )
@@ -218,60 +268,51 @@ pub async fn get_game_join(
                 }
             };
 
+            let conn = pool.get()?;
+            let record = block(move || get_game_record(&conn, gid)).await?;
+            let conn = pool.get()?;
+            let user_id = uid.id;
+            let banned = block(move || is_banned(&conn, gid, user_id)).await?;
+
             let conn = pool.get()?;
 
-            // check if user already joined game
+            // check if user already joined game. Players may be in several games at once
             let sacrifice = uid.id.clone();
-            match block(move || get_user_game(&conn, sacrifice)).await? {
-                Some(current_game_id) => {
-                    if current_game_id != gid {
-                        // when not current and game *and* has pin redirect to pin template
-                        if !pin.is_none() {
-                            return UserError::wrap_template(
-                                templates::GamePinTemplate {
-                                    game: gid,
-                                    id: Some(uid),
-                                    pin_error: false,
-                                }
-                                .into_response(),
-                            );
-                        } else {
-                            // otherwise join directly
-                            let sacrifice = uid.id.clone();
-                            let conn = pool.get()?;
-                            block(move || leave_game(&conn, sacrifice)).await?;
-                            let conn = pool.get()?;
-                            block(move || join_game(&conn, sacrifice, gid)).await?;
-                        }
-                    } else {
-                        // already joined -> check if host
-                        let conn = pool.get()?;
-                        let host = block(move || get_game_host(&conn, current_game_id)).await?;
-                        if host == uid.id {
-                            return UserError::wrap_template(
-                                templates::GameBoardTemplate {
-                                    id: Some(uid),
-                                    host: true,
-                                }
-                                .into_response(),
-                            );
+            if block(move || is_player(&conn, gid, sacrifice)).await? {
+                // already joined -> check if host
+                let conn = pool.get()?;
+                let host = block(move || get_game_host(&conn, gid)).await?;
+                if host == uid.id {
+                    return UserError::wrap_template(
+                        templates::GameBoardTemplate {
+                            id: Some(uid),
+                            host: true,
+                            game: gid,
+                            spectate: None,
                         }
-                    }
+                        .into_response(),
+                    );
                 }
-                None => {
+            } else {
+                guard_join(&record, banned)?;
+                let conn = pool.get()?;
+                let active = block(move || get_active_games(&conn, sacrifice)).await?;
+                guard_active_games(active.len())?;
+
+                // games with a pin redirect to the pin template
+                if !pin.is_none() {
+                    return UserError::wrap_template(
+                        templates::GamePinTemplate {
+                            game: gid,
+                            id: Some(uid),
+                            pin_error: false,
+                        }
+                        .into_response(),
+                    );
+                } else {
+                    // otherwise join directly
                     let conn = pool.get()?;
-                    if !pin.is_none() {
-                        return UserError::wrap_template(
-                            templates::GamePinTemplate {
-                                game: gid,
-                                id: Some(uid),
-                                pin_error: false,
-                            }
-                            .into_response(),
-                        );
-                    } else {
-                        block(move || join_game(&conn, sacrifice, gid)).await?;
-                    }
+                    block(move || join_game(&conn, sacrifice, gid)).await?;
                 }
             }
 
@@ -279,6 +320,8 @@ pub async fn get_game_join(
                 templates::GameBoardTemplate {
                     id: Some(uid),
                     host: false,
+                    game: gid,
+                    spectate: None,
                 }
                 .into_response(),
             )
@@ -287,11 +330,32 @@ pub async fn get_game_join(
 }
 
 pub async fn get_game_overview(id: Option<SlimUser>) -> UserResponse {
-    // this contained the logic for fetching games earlier but to ensure XSS
-    // is properly escaped it has been moved to an api route
+    // open games are pushed to the page by the lobby feed (See ws::routes::feed_route)
     UserError::wrap_template(templates::GamesOverviewTemplate { id }.into_response())
 }
 
+// lobbies and running games of the user (See guard_active_games)
+pub async fn get_my_games(
+    id: Option<User>,
+    pool: Data<DbPool>,
+    state: Data<AppState>,
+    req: HttpRequest,
+) -> UserResponse {
+    let user = guard_with_user(&req, &state, id)?;
+    let conn = pool.get()?;
+    let uid = user.id;
+    let games = block(move || get_active_games(&conn, uid)).await?;
+
+    UserError::wrap_template(
+        templates::GamesMineTemplate {
+            id: Some(user),
+            games,
+            limit: CONFIG.games.max_active_games,
+        }
+        .into_response(),
+    )
+}
+
 pub async fn get_create_game(
     id: Option<User>,
     state: Data<AppState>,
@@ -316,6 +380,7 @@ pub async fn post_create_game(
     data: Form<forms::GameForm>,
     id: Option<User>,
     state: Data<AppState>,
+    rooms: Data<Rooms>,
     req: HttpRequest,
 ) -> UserResponse {
     // constants for validation
@@ -323,6 +388,11 @@ pub async fn post_create_game(
 
     // retrieve id and guard route
     let user = guard_with_user(&req, &state, id.clone())?;
+    if rooms.closing() {
+        return Err(UserError::ValidationError(
+            "The server is shutting down. Please try again after the restart".to_owned(),
+        ));
+    }
     let conn = pool.get()?;
 
     // validates cookie checkbox
@@ -366,6 +436,101 @@ pub async fn post_create_game(
     }
     .to_owned();
 
+    // rule set chosen by the host (unchecked checkboxes aren't sent at all)
+    let rules = RuleSet {
+        figures: data.figures.unwrap_or(DEFAULT_FIGURES),
+        swaps: data.swaps.as_deref() == Some("on"),
+        stoppers: data.stoppers.as_deref() == Some("on"),
+        move_clock: match data.move_clock.as_deref() {
+            Some("") | None => None,
+            Some(value) => match value.parse::<i32>() {
+                Ok(seconds) => Some(seconds),
+                Err(_) => return Err(UserError::ValidationError("move clock".to_owned())),
+            },
+        },
+        base_time: match data.base_time.as_deref() {
+            Some("") | None => None,
+            Some(value) => match value.parse::<i32>() {
+                Ok(minutes) => Some(minutes.saturating_mul(60)),
+                Err(_) => return Err(UserError::ValidationError("base time".to_owned())),
+            },
+        },
+        increment: match data.increment.as_deref() {
+            Some("") | None => 0,
+            Some(value) => match value.parse::<i32>() {
+                Ok(seconds) => seconds,
+                Err(_) => return Err(UserError::ValidationError("increment".to_owned())),
+            },
+        },
+        ko: data.ko.map(KoRule::from).unwrap_or(KoRule::Simple),
+        teams: data.teams.as_deref() == Some("on"),
+        abandon: data.abandon.map(AbandonRule::from).unwrap_or_default(),
+    };
+
+    if let Err(why) = rules.validate() {
+        return Err(UserError::ValidationError(why.to_string()));
+    }
+
+    // custom starting position and handicaps
+    let position = data
+        .position
+        .as_deref()
+        .map(str::trim)
+        .filter(|position| !position.is_empty());
+
+    let handicaps = match data.handicap_player.as_deref() {
+        Some("") | None => Vec::new(),
+        Some(value) => match value.parse::<u8>() {
+            Ok(player) => vec![Handicap::HeadStart {
+                player,
+                figures: data.handicap_figures.unwrap_or(1),
+            }],
+            Err(_) => return Err(UserError::ValidationError("handicap".to_owned())),
+        },
+    };
+
+    for handicap in &handicaps {
+        if let Err(why) = handicap.validate() {
+            return Err(UserError::ValidationError(why.to_string()));
+        }
+    }
+
+    // a head start must not already win the game
+    if handicaps.iter().any(|handicap| match handicap {
+        Handicap::HeadStart { figures, .. } => *figures as i16 >= rules.figures,
+    }) {
+        return Err(UserError::ValidationError(
+            "Handicaps must be smaller than the figures required to win".to_owned(),
+        ));
+    }
+
+    let base = if position.is_none() && handicaps.is_empty() {
+        None
+    } else {
+        let state = match starting_position(position, &handicaps) {
+            Ok(state) => state,
+            Err(why) => return Err(UserError::ValidationError(why.to_string())),
+        };
+
+        match serde_json::to_string(&StartingPosition {
+            locations: (&state).into(),
+            handicaps,
+        }) {
+            Ok(base) => Some(base),
+            Err(why) => return Err(UserError::InternalError(why.to_string())),
+        }
+    };
+
+    // listing for the lobby feed. The host joins the game when creating it
+    let mut listing = GameListing {
+        id: 0,
+        name: data.name.clone(),
+        icon: icon.clone(),
+        private: pin.is_some(),
+        players: 1,
+        status: GameStatus::Lobby,
+    };
+
     // freeing thread because diesel doesn't support async net
     let gid = block(move || {
         create_game(
@@ -375,11 +540,16 @@ pub async fn post_create_game(
             public,
             icon,
             pin,
+            rules,
+            base,
             &user,
         )
     })
     .await?;
 
+    listing.id = gid;
+    rooms.lobby().do_send(PublishGame(listing));
+
     Ok(redirect(format!("/games/join/{}", gid)))
 }
 
@@ -395,13 +565,30 @@ pub async fn get_view_game(
     let gid = path.into_inner().0;
 
     let gdata = block(move || get_game(&conn, gid)).await?;
+    let conn = pool.get()?;
+    let record = block(move || get_game_record(&conn, gid)).await?;
+    let conn = pool.get()?;
+    let winners = block(move || get_game_winners(&conn, gid)).await?;
 
     let is_host = false;
+    let joinable = gdata.0.status == GameStatus::Lobby && !gdata.0.locked;
+
+    // final result of finished games
+    let result = record.result_reason();
+    let winners = gdata
+        .1
+        .iter()
+        .filter(|player| winners.contains(&player.0))
+        .map(|player| player.1.clone())
+        .collect();
 
     UserError::wrap_template(
         templates::GamesViewTemplate {
             id,
             is_host,
+            joinable,
+            result,
+            winners,
             game: gdata.0,
             players: gdata.1,
         }
@@ -409,6 +596,79 @@ pub async fn get_view_game(
     )
 }
 
+// read-only board of a running game. The client passes the pin of private games to the websocket
+pub async fn get_watch_game(path: Path<(i32,)>, id: Option<User>) -> UserResponse {
+    let gid = path.into_inner().0;
+    UserError::wrap_template(
+        templates::GameBoardTemplate {
+            id,
+            host: false,
+            game: gid,
+            spectate: Some(gid),
+        }
+        .into_response(),
+    )
+}
+
+/*
+Admin:
+/admin/shutdown -> post_admin_shutdown
+*/
+pub async fn post_admin_shutdown(
+    data: Form<forms::AdminForm>,
+    stopper: Data<Sender<()>>,
+    state: Data<AppState>,
+    rooms: Data<Rooms>,
+    req: HttpRequest,
+) -> UserResponse {
+    // failed attempts are limited per client, so nobody can lock the admin out
+    let client = req
+        .peer_addr()
+        .map_or(IpAddr::from([0, 0, 0, 0]), |addr| addr.ip());
+    state
+        .admin_attempts
+        .retain(|_, attempts| !attempts.expired());
+    if state
+        .admin_attempts
+        .get(&client)
+        .map_or(false, |attempts| attempts.exceeded())
+    {
+        return Err(UserError::ValidationError(
+            "Too many failed attempts. Please try again later".to_owned(),
+        ));
+    }
+
+    // both credentials are always compared
+    let username = constant_time_eq(data.username.as_bytes(), CONFIG.admin.username.as_bytes());
+    let password = constant_time_eq(data.password.as_bytes(), CONFIG.admin.password.as_bytes());
+    if !(username & password) {
+        state
+            .admin_attempts
+            .entry(client)
+            .or_insert_with(|| RateLimit::new(ADMIN_ATTEMPTS, ADMIN_ATTEMPT_WINDOW))
+            .check();
+        return Err(UserError::ValidationError(
+            "Invalid admin credentials".to_owned(),
+        ));
+    }
+
+    if !rooms.request_stop() {
+        return Err(UserError::ValidationError(
+            "The server is already stopping".to_owned(),
+        ));
+    }
+
+    // players are warned and games saved before the server stops (See server::main)
+    stopper
+        .send(())
+        .map_err(|_| UserError::InternalError("Failed to stop the server".to_owned()))?;
+
+    Ok(HttpResponse::Ok().json(ActionStatus {
+        code: 0,
+        description: format!("Shutting down in {} seconds", CONFIG.games.shutdown_notice),
+    }))
+}
+
 /*
 Static routes.
 */
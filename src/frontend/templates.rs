@@ -1,7 +1,8 @@
 use crate::auth::User;
+use crate::db::models::{Game as GameRecord, GameStatus, ResultReason};
+use crate::ws::models::Game;
 use askama_actix::Template;
 use uuid::Uuid;
-use crate::ws::models::Game;
 
 #[derive(Template)]
 #[template(path = "index.html")]
@@ -24,6 +25,16 @@ pub struct GamesOverviewTemplate {
     pub id: Option<User>,
 }
 
+#[derive(Template)]
+#[template(path = "games/mine.html")]
+pub struct GamesMineTemplate {
+    pub id: Option<User>,
+    // lobbies and running games, newest first
+    pub games: Vec<GameRecord>,
+    // games a user may have joined at once
+    pub limit: usize,
+}
+
 #[derive(Template)]
 #[template(path = "games/create.html")]
 pub struct GamesCreateTemplate {
@@ -40,11 +51,16 @@ pub struct GamesViewTemplate {
     pub id: Option<User>,
     pub game: Game,
     pub is_host: bool,
+    // only open (unlocked) lobbies can be joined
+    pub joinable: bool,
+    // why the game ended and the names of the winners
+    pub result: Option<ResultReason>,
+    pub winners: Vec<String>,
     pub players: Vec<(Uuid, String)>,
 }
 
 #[derive(Template)]
-#[template(path="robots.txt")]
+#[template(path = "robots.txt")]
 pub struct Robots();
 
 #[derive(Template)]
@@ -52,6 +68,10 @@ pub struct Robots();
 pub struct GameBoardTemplate {
     pub id: Option<User>,
     pub host: bool,
+    // played or watched game
+    pub game: i32,
+    // watched game (read-only board)
+    pub spectate: Option<i32>,
 }
 
 #[derive(Template)]
@@ -1,3 +1,2 @@
-pub mod errors;
-pub mod graph;
-pub mod models;
+// The game logic lives in the pentagame-engine library crate (See engine/)
+pub use pentagame_engine::{editor, errors, graph, models, position, rules};
@@ -1,6 +1,7 @@
 // includes
 mod auth;
 mod config;
+mod editor;
 mod frontend;
 mod graph;
 mod server;
@@ -12,12 +13,16 @@ mod db;
 use crate::config::DEFAULT_CONFIG_NAME;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use std::env::{set_var, var};
+use std::fs::write;
 use std::path::Path;
 #[macro_use]
 extern crate lazy_static;
 #[macro_use]
 extern crate diesel;
 
+// default output of `protocol` subcommand
+const PROTOCOL_TS_FILE: &str = "static/ts/interfaces/protocol.ts";
+
 pub fn main() -> std::io::Result<()> {
     let matches: ArgMatches = App::new("pentagame online 2")
         .author("Cobalt <cobalt.rocks>")
@@ -61,6 +66,31 @@ pub fn main() -> std::io::Result<()> {
                         .takes_value(true),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("protocol")
+                .about("generate TypeScript codes of the websocket protocol")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .default_value(PROTOCOL_TS_FILE)
+                        .long("output-file")
+                        .value_name("FILE")
+                        .help("Set a custom output file")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("schema")
+                .about("print JSON Schema of the websocket protocol and JSON responses")
+                .arg(
+                    Arg::with_name("file")
+                        .short("f")
+                        .long("output-file")
+                        .value_name("FILE")
+                        .help("Write schema to file instead of stdout")
+                        .takes_value(true),
+                ),
+        )
         .get_matches();
 
     // license
@@ -106,5 +136,20 @@ pub fn main() -> std::io::Result<()> {
         None => (),
     };
 
+    if let Some(subcommand_matches) = matches.subcommand_matches("protocol") {
+        let file = subcommand_matches.value_of("file").unwrap();
+        write(file, ws::messages::typescript_protocol())?;
+        println!("Wrote protocol version {} to {}", ws::messages::PROTOCOL_VERSION, file);
+    }
+
+    if let Some(subcommand_matches) = matches.subcommand_matches("schema") {
+        let schema = serde_json::to_string_pretty(&ws::schema::protocol_schema())
+            .expect("The creation of the protocol schema failed");
+        match subcommand_matches.value_of("file") {
+            Some(file) => write(file, schema)?,
+            None => println!("{}", schema),
+        }
+    }
+
     Ok(())
 }
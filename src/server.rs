@@ -1,11 +1,12 @@
 // imports
 use crate::config::{CONFIG, SECRET_KEY};
-use crate::frontend::helper::{log_info, log_success};
+use crate::editor::routes as editor_routes;
+use crate::frontend::helper::{log_error, log_info, log_success};
 use crate::frontend::routes;
-use crate::state::{AppState, GameServerState};
-use crate::ws::{actor::GameServer, routes as ws_routes};
-use actix::Actor;
+use crate::state::{AppState, Rooms};
+use crate::ws::{clock::SystemClock, routes as ws_routes};
 use actix_identity::{CookieIdentityPolicy, IdentityService};
+use actix_web::rt::signal::unix::{signal, SignalKind};
 use actix_web::{
     http::ContentEncoding, middleware::Compress, middleware::DefaultHeaders, web, App, HttpServer,
 };
@@ -13,6 +14,7 @@ use actix_web_static_files;
 use futures::executor;
 use std::env::{set_var, var};
 use std::io::Result;
+use std::time::Duration;
 use std::{sync::mpsc, sync::Arc, thread};
 
 include!(concat!(env!("OUT_DIR"), "/generated.rs"));
@@ -48,7 +50,10 @@ pub async fn main() -> Result<()> {
 
     // build app state
     let app_state = AppState::build();
-    let game_state = GameServerState::build(Arc::clone(&app_state.auth_cache));
+    let rooms = Rooms::build(Arc::new(SystemClock));
+    let shutdown_rooms = rooms.clone();
+    let signal_rooms = rooms.clone();
+    let stopper = tx.clone();
     let state = web::Data::new(app_state);
 
     let server = HttpServer::new(move || {
@@ -56,6 +61,7 @@ pub async fn main() -> Result<()> {
             App::new()
                 .app_data(state.clone())
                 .data(tx.clone())
+                .data(rooms.clone())
                 .wrap(Compress::new(ContentEncoding::Gzip))
                 .wrap(IdentityService::new(
                     CookieIdentityPolicy::new(&SECRET_KEY.clone())
@@ -77,35 +83,87 @@ pub async fn main() -> Result<()> {
                 )
                 .service(
                     web::scope("/games")
-                        .service(
-                            web::resource("/ws/")
-                                .data(GameServer::build().start())
-                                .to(ws_routes::game_route),
-                        )
+                        .route("/ws/{id}", web::get().to(ws_routes::game_route))
+                        .route("/spectate/{id}", web::get().to(ws_routes::spectator_route))
+                        .route("/feed", web::get().to(ws_routes::feed_route))
+                        .route("/watch/{id}", web::get().to(routes::get_watch_game))
                         .route("/join/{id}", web::get().to(routes::get_game_join))
                         .route("/join/{id}", web::post().to(routes::post_game_join))
-                        .route("/leave", web::get().to(ws_routes::get_game_leave_route))
+                        .route("/leave/{id}", web::post().to(ws_routes::post_game_leave_route))
+                        .route("/mine", web::get().to(routes::get_my_games))
                         .route("/", web::get().to(routes::get_game_overview))
                         .route("/create", web::get().to(routes::get_create_game))
                         .route("/create", web::post().to(routes::post_create_game))
                         .route("/view/{id}", web::get().to(routes::get_view_game)),
                 )
+                .service(
+                    web::scope("/editor")
+                        .route("/", web::post().to(editor_routes::post_create_editor))
+                        .route("/ws/{id}", web::get().to(editor_routes::editor_route))
+                        .route("/{id}", web::get().to(editor_routes::get_editor))
+                        .route("/{id}/action", web::post().to(editor_routes::post_editor_action))
+                        .route("/{id}/save", web::post().to(editor_routes::post_save_editor))
+                        .route("/{id}/close", web::post().to(editor_routes::post_close_editor)),
+                )
+                .route("/protocol/schema.json", web::get().to(ws_routes::get_protocol_schema))
+                .route("/metrics", web::get().to(ws_routes::get_metrics))
+                .route("/admin/shutdown", web::post().to(routes::post_admin_shutdown))
                 .route("/robots.txt", web::get().to(routes::get_robots_txt))
                 .route("/", web::get().to(routes::get_index))
                 .default_service(web::route().to(routes::get_error_404))
         }
     })
     .bind(host)?
+    // signals start the graceful shutdown below instead of stopping the server right away
+    .disable_signals()
     .run();
 
+    // SIGTERM (e.g. from the service manager) and SIGINT (Ctrl-C) shut down like the admin action
+    for (kind, name) in [
+        (SignalKind::terminate(), "SIGTERM"),
+        (SignalKind::interrupt(), "SIGINT"),
+    ] {
+        let stopper = stopper.clone();
+        let rooms = signal_rooms.clone();
+        actix_web::rt::spawn(async move {
+            let mut received = match signal(kind) {
+                Ok(received) => received,
+                Err(why) => {
+                    return log_error("SERVER", format!("Failed to listen for {}: {}", name, why))
+                }
+            };
+
+            while received.recv().await.is_some() {
+                if rooms.request_stop() {
+                    let _ = stopper.send(());
+                } else {
+                    log_info(
+                        "SERVER",
+                        format!("{} received, already shutting down", name),
+                    );
+                }
+            }
+        });
+    }
+
     // clone the Server handle
     let srv = server.clone();
     thread::spawn(move || {
-        // wait for shutdown signal
-        rx.recv().unwrap();
+        // wait for shutdown signal. The senders only go away with the server itself
+        if rx.recv().is_err() {
+            log_error("SERVER", "Shutdown channel closed unexpectedly".to_owned());
+            return;
+        }
+
+        // warn players and refuse new games during the countdown
+        let countdown = CONFIG.games.shutdown_notice;
+        log_info("SERVER", format!("Shutting down in {} seconds", countdown));
+        shutdown_rooms.notice_shutdown(countdown);
+        thread::sleep(Duration::from_secs(countdown));
 
-        // tear down games
-        log_info("SERVER", "Couldn't tear down games or users due to missing implementation".to_owned());
+        // store running games so they continue after the restart and close all connections
+        log_info("SERVER", "Saving games and closing connections".to_owned());
+        executor::block_on(shutdown_rooms.shut_down());
 
         // stop server gracefully
         executor::block_on(srv.stop(true))
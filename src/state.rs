@@ -1,39 +1,158 @@
 use crate::auth::AuthCache;
-use crate::graph::graph::GraphState;
-use crate::ws::messages::SessionMessage;
-use crate::ws::models::Game;
+use crate::editor::models::EditSession;
+use crate::ws::actor::GameRoom;
+use crate::ws::clock::Clock;
+use crate::ws::errors::WebsocketError;
+use crate::ws::feed::LobbyFeed;
+use crate::ws::messages::{NoticeShutdownMessage, ShutdownMessage};
+use crate::ws::metrics::Metrics;
+use crate::ws::models::RateLimit;
 use actix::prelude::*;
-use dashmap::{DashMap, DashSet};
+use dashmap::{mapref::entry::Entry, DashMap};
+use diesel::PgConnection;
+use futures::future::join_all;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct AppState {
     pub auth_cache: Arc<AuthCache>, // This only possible due to DashMaps nature
+    pub editors: Arc<DashMap<Uuid, EditSession>>,
+    // failed admin logins per client (See frontend::routes::post_admin_shutdown)
+    pub admin_attempts: Arc<DashMap<IpAddr, RateLimit>>,
 }
 
+// Registry of the open game rooms. It's built once before the workers are spawned and shared by
+// all of them, so every session of a game ends up in the same room
 #[derive(Clone)]
-pub struct GameServerState {
-    pub players: Arc<AuthCache>,
-    pub states: Arc<DashMap<i32, (GraphState, u8)>>,
-    pub games: Arc<DashMap<i32, Game>>,
-    pub sessions: Arc<DashMap<i32, DashSet<Recipient<SessionMessage>>>>,
+pub struct Rooms {
+    rooms: Arc<DashMap<i32, Addr<GameRoom>>>,
+    // rooms run on their own arbiter, independent of the worker that opened them
+    arbiter: Arbiter,
+    clock: Arc<dyn Clock>,
+    metrics: Arc<Metrics>,
+    lobby: Addr<LobbyFeed>,
+    // the lobby feed has been seeded from the database
+    seeded: Arc<AtomicBool>,
+    // the server is shutting down. No new rooms are opened
+    closing: Arc<AtomicBool>,
+    // the shutdown was requested (by signal or admin) and the countdown runs
+    stopping: Arc<AtomicBool>,
 }
 
 impl AppState {
     pub fn build() -> AppState {
         return AppState {
             auth_cache: Arc::new(AuthCache::build()),
+            editors: Arc::new(DashMap::new()),
+            admin_attempts: Arc::new(DashMap::new()),
         };
     }
 }
 
-impl GameServerState {
-    pub fn build(players: Arc<AuthCache>) -> GameServerState {
-        GameServerState {
-            players,
-            states: Arc::new(DashMap::new()),
-            games: Arc::new(DashMap::new()),
-            sessions: Arc::new(DashMap::new()),
+impl Rooms {
+    pub fn build(clock: Arc<dyn Clock>) -> Rooms {
+        let arbiter = Arbiter::new();
+        let lobby = LobbyFeed::start_in_arbiter(&arbiter, |_| LobbyFeed::default());
+
+        Rooms {
+            rooms: Arc::new(DashMap::new()),
+            arbiter,
+            clock,
+            metrics: Arc::new(Metrics::default()),
+            lobby,
+            seeded: Arc::new(AtomicBool::new(false)),
+            closing: Arc::new(AtomicBool::new(false)),
+            stopping: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    // time source of the game clocks
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        Arc::clone(&self.clock)
+    }
+
+    // delivery counters of all rooms
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    // feed of the open games
+    pub fn lobby(&self) -> Addr<LobbyFeed> {
+        self.lobby.clone()
+    }
+
+    // the first caller seeds the lobby feed. Failed seeds are released again
+    pub fn claim_seed(&self) -> bool {
+        !self.seeded.swap(true, Ordering::SeqCst)
+    }
+
+    pub fn release_seed(&self) {
+        self.seeded.store(false, Ordering::SeqCst);
+    }
+
+    // the first caller requests the shutdown. Later requests are ignored
+    pub fn request_stop(&self) -> bool {
+        !self.stopping.swap(true, Ordering::SeqCst)
+    }
+
+    // new games are refused once the shutdown was announced
+    pub fn closing(&self) -> bool {
+        self.closing.load(Ordering::SeqCst)
+    }
+
+    // room of a game. The room is loaded and started on first use. Loading doesn't lock the map,
+    // so a game may be loaded twice at once. Only the first room is started then
+    pub fn open(&self, conn: &PgConnection, gid: i32) -> Result<Addr<GameRoom>, WebsocketError> {
+        if let Some(room) = self.get(gid) {
+            return Ok(room);
+        }
+        if self.closing() {
+            return Err(WebsocketError::ValidationError(
+                "The server is shutting down".to_owned(),
+            ));
         }
+
+        let room = GameRoom::load(conn, gid, self.clone())?;
+        match self.rooms.entry(gid) {
+            Entry::Occupied(opened) if opened.get().connected() => Ok(opened.get().clone()),
+            entry => {
+                let addr = GameRoom::start_in_arbiter(&self.arbiter, move |_| room);
+                entry.insert(addr.clone());
+                Ok(addr)
+            }
+        }
+    }
+
+    // room of a game that is already open (spectators can't open rooms)
+    pub fn get(&self, gid: i32) -> Option<Addr<GameRoom>> {
+        self.rooms
+            .get(&gid)
+            .map(|room| room.value().clone())
+            .filter(|room| room.connected())
+    }
+
+    // warn all sessions about the shutdown in `seconds` and refuse new games from now on
+    pub fn notice_shutdown(&self, seconds: u64) {
+        self.closing.store(true, Ordering::SeqCst);
+        for room in self.rooms.iter() {
+            room.value().do_send(NoticeShutdownMessage { seconds });
+        }
+    }
+
+    // store the state of every open room and close all game and lobby feed connections
+    pub async fn shut_down(&self) {
+        self.closing.store(true, Ordering::SeqCst);
+        let rooms: Vec<Addr<GameRoom>> =
+            self.rooms.iter().map(|room| room.value().clone()).collect();
+        join_all(rooms.iter().map(|room| room.send(ShutdownMessage))).await;
+        let _ = self.lobby.send(ShutdownMessage).await;
+    }
+
+    // unregister a closed room. Newer rooms of the same game are kept
+    pub fn remove(&self, gid: i32, room: &Addr<GameRoom>) {
+        self.rooms.remove_if(&gid, |_, current| current == room);
     }
 }
@@ -1,6 +1,14 @@
 pub mod actor;
+pub mod codec;
 pub mod errors;
 pub mod messages;
 pub mod routes;
+pub mod schema;
 pub mod session;
-pub mod models;
\ No newline at end of file
+pub mod spectator;
+pub mod models;
+pub mod clock;
+pub mod metrics;
+pub mod feed;
+pub mod feed_session;
+pub mod snapshot;
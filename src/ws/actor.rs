@@ -1,190 +1,1280 @@
+use super::clock::{Clock, Clocks, PlayerClock};
 use super::errors::WebsocketError;
+use super::feed::LobbyFeed;
 use super::messages::{
-    Connect, Disconnect, MakeMoveMessage, QueryGameMessage, QueryMovesMessage, SessionMessage,
-    StartGameMessage,
+    AssignTeamMessage, ChangeStatusMessage, ChatEntry, Connect, Disconnect, DrawAction,
+    DrawMessage, GameEvent, HostAction, HostActionMessage, LeaveGameMessage, LobbyChange,
+    MakeMoveMessage, MuteChatMessage, NoticeShutdownMessage, PlaceStopperMessage, PublishGame,
+    QueryGameMessage, QueryGameResponse, QueryMovesMessage, ResignMessage, ResyncedMessage,
+    SendChatMessage, ServerMessage, ShutdownMessage, SpectatorConnect, SpectatorDisconnect,
+    StartGameMessage, UnlistGame, UpdateLobbyMessage, Welcome,
 };
+use super::metrics::Metrics;
+use super::models::{
+    Game, LobbyEntry, Turn, TurnKind, CHAT_HISTORY_SIZE, CHAT_MAX_LENGTH, MAX_PLAYERS,
+    MAX_SESSIONS, MIN_PLAYERS, PIECE_ICONS,
+};
+use super::snapshot::RoomSnapshot;
+use crate::config::CONFIG;
+use crate::db::actions::{
+    add_chat_message, ban_user, get_chat_messages, get_game_host, get_game_record,
+    get_game_snapshot, get_game_users, get_user_games, remove_game, remove_game_snapshot,
+    remove_user_game, save_game_snapshot, set_game_host, set_game_locked, set_game_result,
+    set_game_status, set_user_lobby, set_user_team,
+};
+use crate::db::models::{GameStatus, Outcome, ResultReason};
 use crate::frontend::helper::log_error;
-use crate::graph::{graph::GraphState, graph::ResizableGraphState, graph::GRAPH};
-use crate::state::GameServerState;
+use crate::graph::{
+    graph::Figure, graph::GraphState, graph::ResizableGraphState, graph::GRAPH, models::FigureKind,
+    models::FIELD, models::OFF_BOARD, rules::AbandonRule,
+};
+use crate::state::Rooms;
 use actix::prelude::*;
 use cached::stores::TimedCache;
-use dashmap::{DashMap, DashSet};
-use rayon::prelude::*;
+use diesel::PgConnection;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
-// `GameServer` manages  and responsible for coordinating game sessions
-pub struct GameServer {
-    state: Arc<GameServerState>,
-    conn: Mutex<PooledConnection>,
+// `GameRoom` is the single authority of one game. A room is started once per game and shared by
+// all workers (See state::Rooms), so every session of the game talks to the same room
+pub struct GameRoom {
+    gid: i32,
+    game: Game,
+    // current board and whose turn it is
+    board: GraphState,
+    turn: Turn,
+    // sessions of every connected player in connect order. Players may use several tabs or devices
+    sessions: HashMap<Uuid, Vec<Recipient<GameEvent>>>,
+    spectators: HashSet<Recipient<GameEvent>>,
+    // sessions whose outbound queue overflowed and the sequence number of their resync
+    lagging: HashMap<Recipient<GameEvent>, u64>,
+    metrics: Arc<Metrics>,
+    // feed of the open games (See publish)
+    lobby: Addr<LobbyFeed>,
+    // registry the room leaves once it's closed
+    rooms: Rooms,
+    // time source of the game clocks
+    clock: Arc<dyn Clock>,
 }
 
-impl GameServer {
+// How long a disconnected player may resume the session before being removed from the game
+pub const RESUME_GRACE: Duration = Duration::from_secs(60);
+// How long a disconnected host keeps their rights before they're passed on to another player
+pub const HOST_GRACE: Duration = Duration::from_secs(30);
+
+impl GameRoom {
+    // load a game into a new room. Games that are over can't be opened anymore
+    pub fn load(conn: &PgConnection, gid: i32, rooms: Rooms) -> Result<GameRoom, WebsocketError> {
+        let record = get_game_record(conn, gid)?;
+        if record.status().is_over() {
+            return Err(WebsocketError::ValidationError(
+                "This game is already over".to_owned(),
+            ));
+        }
+
+        let host = get_game_host(conn, gid)?;
+        let mut game = Game::from_record(
+            &record,
+            host,
+            record.icon.clone(),
+            record.pin.clone().unwrap_or_default(),
+        );
+        for message in get_chat_messages(conn, gid, CHAT_HISTORY_SIZE as i64)? {
+            game.push_chat(message.into());
+        }
+        for record in get_user_games(conn, gid)? {
+            game.lobby.insert(record.user_id, LobbyEntry::from(&record));
+        }
+
+        // the board starts from the game's (custom) starting position. Running games continue
+        // from their latest snapshot (e.g. after a restart)
+        let mut board = record.base_state()?;
+        let mut turn = Turn::Lobby;
+        if let Some(snapshot) = get_game_snapshot(conn, gid)? {
+            let snapshot: RoomSnapshot = serde_json::from_str(&snapshot.data).map_err(|why| {
+                WebsocketError::InternalError(format!(
+                    "Corrupted snapshot of game {}: {}",
+                    gid, why
+                ))
+            })?;
+            let (restored, restored_turn) = snapshot.restore(&mut game)?;
+            board = restored;
+            turn = restored_turn;
+
+            // every player has to reconnect (See started)
+            let now = Instant::now();
+            for player in &game.players {
+                game.disconnected.insert(*player, now);
+            }
+        }
+
+        Ok(GameRoom {
+            gid,
+            game,
+            board,
+            turn,
+            sessions: HashMap::new(),
+            spectators: HashSet::new(),
+            lagging: HashMap::new(),
+            metrics: rooms.metrics(),
+            lobby: rooms.lobby(),
+            clock: rooms.clock(),
+            rooms,
+        })
+    }
+
     // Send message to all users in the room
-    fn send_message(&self, game: &i32, action: u8, data: DashMap<String, String>) {
-        if let Some(sessions) = self.0.sessions.get(game) {
-            // sweet parallel overkill
-            sessions.value().into_par_iter().for_each(|id| {
-                let _ = id.do_send(SessionMessage {
-                    action,
-                    data: data.clone(),
-                });
+    fn send_message(&mut self, message: ServerMessage) -> GameEvent {
+        // sequence and keep event for replays
+        let event = self.game.events.push(message);
+
+        // spectators receive the same events as players
+        let recipients: Vec<Recipient<GameEvent>> = self
+            .sessions
+            .values()
+            .flatten()
+            .chain(self.spectators.iter())
+            .cloned()
+            .collect();
+        for recipient in recipients {
+            self.deliver(recipient, event.clone());
+        }
+        event
+    }
+
+    // send an event the session closes on (See session::WsGameSession). Unlike `deliver` it
+    // reaches lagging sessions and sessions with a full queue as well
+    fn deliver_final(&mut self, recipient: Recipient<GameEvent>, event: GameEvent) {
+        self.lagging.remove(&recipient);
+        let _ = recipient.do_send(event);
+    }
+
+    // queue an event for a session. Sessions with a full queue skip events until they're resynced
+    fn deliver(&mut self, recipient: Recipient<GameEvent>, event: GameEvent) {
+        if self.lagging.contains_key(&recipient) {
+            self.metrics.coalesced();
+            return;
+        }
+
+        match recipient.try_send(event) {
+            Ok(_) => (),
+            Err(SendError::Full(_)) => {
+                self.metrics.dropped();
+                self.resync(recipient);
+            }
+            // closed sessions are removed by their Disconnect
+            Err(SendError::Closed(_)) => (),
+        }
+    }
+
+    // send the full state to a session that fell behind. The resync is queued behind the backlog
+    // and acknowledged once the session got through it (See ResyncedMessage)
+    fn resync(&mut self, recipient: Recipient<GameEvent>) {
+        let metadata = match self.metadata() {
+            Ok(metadata) => metadata,
+            Err(why) => {
+                log_error(
+                    "[GameRoom]",
+                    format!("Failed to resync session of game {}: {}", self.gid, why),
+                );
+                return;
+            }
+        };
+
+        let seq = self.game.events.seq();
+        self.lagging.insert(recipient.clone(), seq);
+        self.metrics.resynced();
+        let _ = recipient.do_send(GameEvent {
+            seq,
+            message: ServerMessage::Resync {
+                metadata,
+                moves: self.board.clone().into(),
+            },
+        });
+    }
+
+    // move the game along its lifecycle, persist and announce the new status
+    fn transition(
+        &mut self,
+        status: GameStatus,
+        message: Option<String>,
+    ) -> Result<(), WebsocketError> {
+        let current = self.game.status;
+        if !current.can_become(status) {
+            return Err(WebsocketError::ValidationError(format!(
+                "A {} game can't be changed to {}",
+                current, status
+            )));
+        }
+
+        set_game_status(&conn, self.gid, status)?;
+        if status.is_over() {
+            remove_game_snapshot(&conn, self.gid)?;
+        }
+        self.game.status = status;
+        self.send_message(ServerMessage::Status { status, message });
+        self.publish();
+
+        Ok(())
+    }
+
+    // checks whether the player's move request was applied before (See messages::ClientRequest)
+    fn applied(&self, uid: Uuid, request: Option<Uuid>) -> bool {
+        request.map_or(false, |request| self.game.applied(&uid, &request))
+    }
+
+    // checks that it's the turn of `seat` and that the turn asks for `kind`
+    fn check_turn(&self, seat: u8, kind: TurnKind) -> Result<(), WebsocketError> {
+        match self.game.status {
+            GameStatus::Running => (),
+            GameStatus::Paused => {
+                return Err(WebsocketError::ValidationError(
+                    "The game is paused".to_owned(),
+                ))
+            }
+            _ => {
+                return Err(WebsocketError::ValidationError(
+                    "The game isn't running".to_owned(),
+                ))
+            }
+        }
+
+        match self.turn {
+            Turn::Lobby | Turn::Finished => Err(WebsocketError::ValidationError(
+                "The game isn't running".to_owned(),
+            )),
+            turn => match turn.active() {
+                Some(active) if active == (seat, kind) => Ok(()),
+                _ => Err(WebsocketError::TurnError()),
+            },
+        }
+    }
+
+    // update the active player, switch the clocks and let everyone know who has to act next
+    fn advance_turn(&mut self, turn: Turn, ctx: &mut Context<Self>) {
+        self.turn = turn;
+
+        let now = self.clock.now();
+        let game = &mut self.game;
+        let active = turn
+            .active()
+            .and_then(|(seat, kind)| game.players.get(seat as usize).map(|user| (*user, kind)));
+
+        // stopper placements are part of the turn and keep the clock running
+        let timer = match active {
+            Some((user, _)) if game.clocks.running() == Some(user) => None,
+            Some((user, _)) if game.status == GameStatus::Running => {
+                game.clocks.stop(now, true);
+                game.clocks.start(user, now)
+            }
+            _ => {
+                game.clocks.stop(now, false);
+                None
+            }
+        };
+
+        if let Some((user, kind)) = active {
+            let clocks = self.clocks();
+            self.send_message(ServerMessage::Turn { user, kind, clocks });
+        }
+        if let Some((generation, left)) = timer {
+            self.schedule_flag(generation, left, ctx);
+        }
+    }
+
+    // store the state of a running game. Lobbies are fully stored with their records
+    fn save(&self) {
+        if !matches!(self.game.status, GameStatus::Running | GameStatus::Paused) {
+            return;
+        }
+
+        let snapshot = RoomSnapshot::take(&self.game, &self.board, self.turn, self.clock.now());
+        let taken = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as i64);
+        let saved = match serde_json::to_string(&snapshot) {
+            Ok(data) => save_game_snapshot(&conn, self.gid, &data, taken).map_err(|why| why.into()),
+            Err(why) => Err(WebsocketError::InternalError(why.to_string())),
+        };
+
+        if let Err(why) = saved {
+            log_error(
+                "[GameRoom]",
+                format!("Failed to save snapshot of game {}: {}", self.gid, why),
+            );
+        }
+    }
+
+    // keep the lobby feed up to date. Games that are over aren't listed anymore
+    fn publish(&self) {
+        if self.game.status.is_over() {
+            self.lobby.do_send(UnlistGame(self.gid));
+        } else {
+            self.lobby.do_send(PublishGame(self.game.listing()));
+        }
+    }
+
+    fn spectator_count(&self) -> usize {
+        self.spectators.len()
+    }
+
+    fn metadata(&self) -> Result<QueryGameResponse, WebsocketError> {
+        let record = get_game_record(&conn, self.gid)?;
+        let players = get_game_users(&conn, self.gid)?;
+        // TODO: consider storing users (SlimUser + usize) within the room
+        let game = &self.game;
+
+        Ok(QueryGameResponse {
+            name: record.name,
+            description: record.description.unwrap_or_default(),
+            icon: record.icon,
+            players,
+            state: self.turn.into(),
+            status: game.status,
+            lobby: game.lobby(),
+            host: game.host,
+            locked: game.locked,
+            eliminated: game.eliminated.iter().copied().collect(),
+            draw_offers: game.draw_offers.iter().copied().collect(),
+            clocks: self.clocks(),
+            pin: record.pin.unwrap_or_default(),
+            spectators: self.spectator_count(),
+        })
+    }
+
+    // give a disconnected player time to come back before they're removed, lose their host rights
+    // or lose the game
+    fn await_return(&mut self, uid: Uuid, ctx: &mut Context<Self>) {
+        ctx.run_later(RESUME_GRACE, move |act, ctx| act.expire(uid, ctx));
+        if self.game.host == uid {
+            ctx.run_later(HOST_GRACE, move |act, _| act.reassign_host(uid));
+        }
+        let timeout = Duration::from_secs(CONFIG.games.abandon_timeout);
+        ctx.run_later(timeout, move |act, ctx| act.abandon(uid, ctx));
+    }
+
+    // remove player if they didn't reconnect within the grace window
+    fn expire(&mut self, uid: Uuid, ctx: &mut Context<Self>) {
+        let expired = match self.game.disconnected.get(&uid) {
+            Some(since) if since.elapsed() >= RESUME_GRACE => {
+                // seated players stay disconnected until they're adjudicated (See abandon)
+                self.game.resume_tokens.retain(|_, player| *player != uid);
+
+                // players going offline in the lobby stay joined but aren't ready anymore
+                if self.game.status == GameStatus::Lobby {
+                    self.game.players.retain(|player| *player != uid);
+                    self.game.disconnected.remove(&uid);
+                    self.unready(uid);
+                }
+                true
+            }
+            _ => false,
+        };
+
+        if !expired {
+            return;
+        }
+
+        self.send_message(ServerMessage::PlayerLeft { user: uid });
+        self.publish();
+
+        // close the room once the last player left
+        if self.sessions.is_empty() {
+            self.close(ctx);
+        }
+    }
+
+    // withdraw the ready check of a player that went offline in the lobby
+    fn unready(&mut self, uid: Uuid) {
+        let entry = match self.game.lobby.get_mut(&uid) {
+            Some(entry) if entry.ready => entry,
+            _ => return,
+        };
+        entry.ready = false;
+        let entry = entry.clone();
+
+        if let Err(why) = set_user_lobby(
+            &conn,
+            self.gid,
+            uid,
+            entry.seat.map(i16::from),
+            entry.icon.as_deref(),
+            false,
+        ) {
+            log_error(
+                "[GameRoom]",
+                format!("Failed to update lobby of game {}: {}", self.gid, why),
+            );
+        }
+        self.send_message(ServerMessage::Lobby(entry));
+    }
+
+    // lobbies nobody joined anymore are removed. Running games are saved and stay open, so
+    // players can return until the abandon rule decides the game (See abandon)
+    fn close(&mut self, ctx: &mut Context<Self>) {
+        match self.game.status {
+            GameStatus::Running | GameStatus::Paused => return self.save(),
+            // lobbies of offline players stay until they return (or leave)
+            GameStatus::Lobby => match get_user_games(&conn, self.gid) {
+                Ok(players) if !players.is_empty() => (),
+                Ok(_) => {
+                    if let Err(why) = remove_game(&conn, self.gid) {
+                        log_error(
+                            "[GameRoom]",
+                            format!("Failed to remove game {}: {}", self.gid, why),
+                        );
+                    }
+                    self.lobby.do_send(UnlistGame(self.gid));
+                }
+                Err(why) => log_error(
+                    "[GameRoom]",
+                    format!("Failed to load players of game {}: {}", self.gid, why),
+                ),
+            },
+            _ => (),
+        }
+
+        self.rooms.remove(self.gid, &ctx.address());
+        ctx.stop();
+    }
+}
+
+impl GameRoom {
+    // persist and announce a new host
+    fn change_host(&mut self, host: Uuid) -> Result<(), WebsocketError> {
+        set_game_host(&conn, self.gid, host)?;
+        self.game.host = host;
+        self.send_message(ServerMessage::HostChanged { user: host });
+
+        Ok(())
+    }
+
+    // pass host rights on to the longest connected player if the host didn't come back in time
+    fn reassign_host(&mut self, uid: Uuid) {
+        if self.game.host != uid || !self.game.disconnected.contains_key(&uid) {
+            return;
+        }
+
+        if let Some(next) = self.game.connected().next().copied() {
+            if let Err(why) = self.change_host(next) {
+                log_error(
+                    "[GameRoom]",
+                    format!("Failed to transfer host of game {}: {}", self.gid, why),
+                );
+            }
+        }
+    }
+}
+
+impl GameRoom {
+    // record the result, stop the turn order and announce the outcome
+    fn finish(
+        &mut self,
+        winners: Vec<Uuid>,
+        reason: ResultReason,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), WebsocketError> {
+        let game = &self.game;
+        let outcomes: Vec<(Uuid, Outcome)> = game
+            .players
+            .iter()
+            .map(|player| {
+                let outcome = if winners.contains(player) {
+                    Outcome::Won
+                } else if reason == ResultReason::Draw && !game.eliminated.contains(player) {
+                    Outcome::Draw
+                } else {
+                    Outcome::Lost
+                };
+                (*player, outcome)
             })
+            .collect();
+
+        set_game_result(&conn, self.gid, reason, &outcomes)?;
+        self.advance_turn(Turn::Finished, ctx);
+        self.transition(GameStatus::Finished, None)?;
+        self.send_message(ServerMessage::GameOver { winners, reason });
+
+        Ok(())
+    }
+
+    // take a player (and their partners) out of a running game. The last side standing wins
+    fn eliminate(
+        &mut self,
+        uid: Uuid,
+        reason: ResultReason,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), WebsocketError> {
+        if !self.game.players.contains(&uid) || self.game.eliminated.contains(&uid) {
+            return Err(WebsocketError::ValidationError(
+                "You aren't playing in this game anymore".to_owned(),
+            ));
+        }
+
+        let side = self.game.side(&uid);
+        self.game.eliminated.extend(side.iter().copied());
+        self.game.draw_offers.clear();
+        let seats: Vec<u8> = side
+            .iter()
+            .filter_map(|player| self.game.player_index(player))
+            .collect();
+
+        // frozen figures stay on the board as obstacles
+        if self.game.rules.abandon == AbandonRule::Remove {
+            for seat in &seats {
+                self.board.remove_player(*seat);
+            }
+        }
+        self.send_message(ServerMessage::PlayerEliminated { user: uid, reason });
+
+        if self.game.remaining_sides() <= 1 {
+            let remaining = self.game.remaining();
+            return self.finish(remaining, reason, ctx);
+        }
+
+        // skip the turn of an eliminated player
+        if let Some((seat, _)) = self.turn.active() {
+            if seats.contains(&seat) {
+                self.game.pending_gray = false;
+                let next = self.game.next_seat(seat);
+                self.advance_turn(Turn::Move(next), ctx);
+            }
+        }
+
+        Ok(())
+    }
+
+    // end a game every player abandoned. Nobody wins
+    fn abort(
+        &mut self,
+        reason: ResultReason,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), WebsocketError> {
+        let outcomes: Vec<(Uuid, Outcome)> = self
+            .game
+            .players
+            .iter()
+            .map(|player| (*player, Outcome::Lost))
+            .collect();
+
+        set_game_result(&conn, self.gid, reason, &outcomes)?;
+        self.advance_turn(Turn::Finished, ctx);
+        self.transition(GameStatus::Aborted, None)
+    }
+
+    // a player that stayed disconnected past the abandon timeout loses the game. Games every
+    // player abandoned are aborted
+    fn abandon(&mut self, uid: Uuid, ctx: &mut Context<Self>) {
+        let timeout = Duration::from_secs(CONFIG.games.abandon_timeout);
+        let abandoned = matches!(self.game.status, GameStatus::Running | GameStatus::Paused)
+            && !self.game.eliminated.contains(&uid)
+            && self
+                .game
+                .disconnected
+                .get(&uid)
+                .map_or(false, |since| since.elapsed() >= timeout);
+
+        if !abandoned {
+            return;
+        }
+
+        // the other players are offline as well. The game is decided once the last of them
+        // abandoned it too or one of them returned
+        let offline: Option<Vec<Instant>> = self
+            .game
+            .remaining()
+            .iter()
+            .map(|player| self.game.disconnected.get(player).copied())
+            .collect();
+        let result = match offline.and_then(|since| since.into_iter().max()) {
+            Some(latest) if latest.elapsed() < timeout => {
+                let wait = timeout - latest.elapsed();
+                ctx.run_later(wait, move |act, ctx| act.abandon(uid, ctx));
+                return;
+            }
+            Some(_) => self.abort(ResultReason::Abandoned, ctx),
+            None => self.eliminate(uid, ResultReason::Abandoned, ctx),
+        };
+
+        if let Err(why) = result {
+            log_error(
+                "[GameRoom]",
+                format!("Failed to adjudicate game {}: {}", self.gid, why),
+            );
+        }
+
+        // rooms of empty games are only kept open for adjudicating them
+        if self.game.status.is_over() && self.sessions.is_empty() {
+            self.close(ctx);
         }
     }
 }
 
-// Make actor from `GameServer`
-impl Actor for GameServer {
+impl GameRoom {
+    // flag the running clock once its time is used up
+    fn schedule_flag(&self, generation: u64, left: Duration, ctx: &mut Context<Self>) {
+        ctx.run_later(left, move |act, ctx| act.flag(generation, ctx));
+    }
+
+    // a player whose time ran out loses on time. Timers of switched clocks are stale
+    fn flag(&mut self, generation: u64, ctx: &mut Context<Self>) {
+        if self.game.status != GameStatus::Running || self.game.clocks.generation() != generation {
+            return;
+        }
+
+        let now = self.clock.now();
+        let player = match self.game.clocks.running() {
+            Some(player) => player,
+            None => return,
+        };
+
+        match self.game.clocks.flagged(now) {
+            Some(uid) => {
+                if let Err(why) = self.eliminate(uid, ResultReason::Timeout, ctx) {
+                    log_error(
+                        "[GameRoom]",
+                        format!("Failed to flag player in game {}: {}", self.gid, why),
+                    );
+                }
+            }
+            // the time source may run behind the timer
+            None => {
+                let left = self.game.clocks.remaining(&player, now);
+                self.schedule_flag(generation, left, ctx);
+            }
+        }
+    }
+
+    // actions arriving after the player's time ran out lose on time
+    fn check_clock(&mut self, uid: Uuid, ctx: &mut Context<Self>) -> Result<(), WebsocketError> {
+        let now = self.clock.now();
+        if self.game.clocks.flagged(now) == Some(uid) {
+            self.eliminate(uid, ResultReason::Timeout, ctx)?;
+            return Err(WebsocketError::ValidationError(
+                "Your time ran out".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // clocks stand still while the game is paused and continue with the active player
+    fn resume_clock(&mut self, ctx: &mut Context<Self>) {
+        let now = self.clock.now();
+        let active = match self.turn.active() {
+            Some((seat, _)) => self.game.players.get(seat as usize).copied(),
+            None => None,
+        };
+        self.game.clocks.stop(now, false);
+        let timer = match active {
+            Some(user) if self.game.status == GameStatus::Running => {
+                self.game.clocks.start(user, now)
+            }
+            _ => None,
+        };
+        if let Some((generation, left)) = timer {
+            self.schedule_flag(generation, left, ctx);
+        }
+    }
+
+    fn clocks(&self) -> Vec<PlayerClock> {
+        self.game
+            .clocks
+            .snapshot(&self.game.players, self.clock.now())
+    }
+}
+
+// Make actor from `GameRoom`
+impl Actor for GameRoom {
     // We are going to use simple Context, we just need ability to communicate
     // with other actors.
     type Context = Context<Self>;
+
+    // restored games continue with the active player's clock and wait for their players
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        self.resume_clock(ctx);
+        let players: Vec<Uuid> = self.game.disconnected.keys().copied().collect();
+        for uid in players {
+            self.await_return(uid, ctx);
+        }
+
+        let interval = Duration::from_secs(CONFIG.games.snapshot_interval);
+        ctx.run_interval(interval, |act, _| act.save());
+    }
 }
 
 // Handler for Connect message.
 //
-// Register new session and assign unique id to this session
-impl Handler<Connect> for GameServer {
-    type Result = Result<(), WebsocketError>;
+// Register new session, replay missed events of resumed sessions and hand out a new resume token.
+// Every session of a player receives all events and may act for the player
+impl Handler<Connect> for GameRoom {
+    type Result = Result<Welcome, WebsocketError>;
 
     fn handle(&mut self, msg: Connect, _: &mut Context<Self>) -> Self::Result {
-        // register session with random id. The +1 ensures that 0 is never a session id
-        // to enable 0 as placeholder for nobody when skipping
-        // add to group
-        let gid = match get_user_game(&conn, msg.uid)? {
-            Some(id) => id,
-            None => {
-                return Err(WS::ValidationError("Not joined any game".to_owned()));
+        // add to game
+        let game = &mut self.game;
+
+        // seats are handed out in the lobby. Started games only take back their own players
+        if game.status != GameStatus::Lobby && !game.players.contains(&msg.uid) {
+            return Err(WebsocketError::ValidationError(
+                "This game has already been started".to_owned(),
+            ));
+        }
+
+        // resuming requires a token of the player and all missed events still being logged.
+        // Tokens are used up by resuming
+        let replay = match msg.resume {
+            Some(resume) if game.resume_tokens.get(&resume.token) == Some(&msg.uid) => {
+                game.resume_tokens.remove(&resume.token);
+                game.events.since(resume.seq)
             }
+            _ => None,
         };
 
-        // add to game
-        match self.0.games.get_mut(&gid) {
-            Some(mut game) => {
-                game.insert(msg.addr);
-            }
-            None => {
-                let mut new_game = HashSet::with_capacity(5);
-                new_game.insert(msg.addr);
-                self.0.games.insert(gid, new_game);
-            }
+        let token = Uuid::new_v4();
+        game.resume_tokens.insert(token, msg.uid);
+        let reconnected = game.disconnected.remove(&msg.uid).is_some();
+        // further tabs or devices of a connected player aren't announced
+        let connected = self.sessions.contains_key(&msg.uid);
+        if game.status == GameStatus::Lobby && !game.players.contains(&msg.uid) {
+            game.players.push(msg.uid);
+            game.lobby_entry(msg.uid);
         }
 
-        // save to internal state map
-        self.states.insert(gid, state);
+        let welcome = Welcome {
+            token,
+            seq: game.events.seq(),
+            resumed: replay.is_some(),
+            chat: game.chat.iter().cloned().collect(),
+        };
 
-        // compile data for message
-        let username = get_username(&conn, &msg.uid)?;
-        let data = DashMap::with_capacity(1);
-        data.insert(
-            "player".to_owned(),
-            format!("{}|{}", msg.uid.to_string(), username),
-        );
+        // missed events are delivered before any new ones
+        for event in replay.unwrap_or_default() {
+            let _ = msg.addr.do_send(event);
+        }
+
+        // the oldest session of the player is closed for new ones
+        let seq = game.events.seq();
+        let sessions = self.sessions.entry(msg.uid).or_default();
+        sessions.push(msg.addr);
+        if sessions.len() > MAX_SESSIONS {
+            let evicted = sessions.remove(0);
+            self.lagging.remove(&evicted);
+            let _ = evicted.do_send(GameEvent {
+                seq,
+                message: ServerMessage::Evicted,
+            });
+        }
 
         // send message to everyone
-        self.send_message(&gid, 7, data);
+        if reconnected {
+            self.send_message(ServerMessage::PlayerReconnected { user: msg.uid });
+        } else if !connected {
+            let name = get_username(&conn, &msg.uid)?;
+            self.send_message(ServerMessage::PlayerJoined {
+                user: msg.uid,
+                name,
+            });
+            self.publish();
+        }
 
-        // send id back
-        Ok(())
+        Ok(welcome)
     }
 }
 
 // Handler for Disconnect message.
-impl Handler<Disconnect> for GameServer {
-    type Result = Result<(), APIError>;
-
-    fn handle(&mut self, msg: Disconnect, _: &mut Context<Self>) -> Self::Result {
-        // remove address
-        let player_set = self.games.get_mut(&msg.gid);
-        if player_set.is_some() {
-            let mut players = player_set.unwrap();
-            if players.remove(&msg.addr) {
-                // check if game is empty and if empty remove and cleanup internal storage
-                if players.is_empty() {
-                    self.games.remove(&msg.gid);
-                    let conn = self.pool.get()?;
-                    remove_game(&conn, msg.gid)?;
-                }
+//
+// Players are disconnected once their last session closed. They're kept for RESUME_GRACE to allow
+// resuming the session
+impl Handler<Disconnect> for GameRoom {
+    type Result = Result<(), WebsocketError>;
 
-                // send notification
-                let data = DashMap::new();
-                data.insert("player".to_owned(), msg.uid.to_string());
-                self.send_message(&msg.gid, 4, data);
+    fn handle(&mut self, msg: Disconnect, ctx: &mut Context<Self>) -> Self::Result {
+        // remove address. Evicted sessions were already removed
+        let last = match self.sessions.get_mut(&msg.uid) {
+            Some(sessions) if sessions.contains(&msg.addr) => {
+                sessions.retain(|session| session != &msg.addr);
+                sessions.is_empty()
             }
+            _ => false,
+        };
+        self.lagging.remove(&msg.addr);
+        if last {
+            self.sessions.remove(&msg.uid);
+        }
+
+        // kicked players already left the game
+        if last && self.game.players.contains(&msg.uid) {
+            self.game.disconnected.insert(msg.uid, Instant::now());
+
+            // send notification
+            self.send_message(ServerMessage::PlayerDisconnected { user: msg.uid });
+            self.await_return(msg.uid, ctx);
         }
 
         Ok(())
     }
 }
 
+// Handler for SpectatorConnect message.
+//
+// Spectators can only watch games with connected players (See state::Rooms::get)
+impl Handler<SpectatorConnect> for GameRoom {
+    type Result = Result<(u64, Vec<ChatEntry>), WebsocketError>;
+
+    fn handle(&mut self, msg: SpectatorConnect, _: &mut Context<Self>) -> Self::Result {
+        let (seq, chat) = (
+            self.game.events.seq(),
+            self.game.chat.iter().cloned().collect(),
+        );
+
+        self.spectators.insert(msg.addr);
+
+        let count = self.spectator_count();
+        self.send_message(ServerMessage::Spectators { count });
+
+        Ok((seq, chat))
+    }
+}
+
+// Handler for SpectatorDisconnect message.
+impl Handler<SpectatorDisconnect> for GameRoom {
+    type Result = ();
+
+    fn handle(&mut self, msg: SpectatorDisconnect, _: &mut Context<Self>) {
+        self.lagging.remove(&msg.addr);
+        if self.spectators.remove(&msg.addr) {
+            let count = self.spectator_count();
+            self.send_message(ServerMessage::Spectators { count });
+        }
+    }
+}
+
 // handler for fetching all or latest user moves
-impl Handler<QueryMovesMessage> for GameServer {
+impl Handler<QueryMovesMessage> for GameRoom {
     type Result = Result<ResizableGraphState, WebsocketError>;
 
-    fn handle(&mut self, msg: QueryMovesMessage, _: &mut Context<Self>) -> Self::Result {
-        return Ok(self.0.states.get(&msg.gid).unwrap().0.into());
+    fn handle(&mut self, _: QueryMovesMessage, _: &mut Context<Self>) -> Self::Result {
+        return Ok(self.board.clone().into());
     }
 }
 
 // handler for host starting a game
-impl Handler<StartGameMessage> for GameServer {
+impl Handler<StartGameMessage> for GameRoom {
     type Result = Result<(), WebsocketError>;
 
-    fn handle(&mut self, msg: StartGameMessage, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, msg: StartGameMessage, ctx: &mut Context<Self>) -> Self::Result {
         // check if user is authorized
-        let host_id = self.0.games.get(&msg.gid).unwrap().value().host;
-
-        if host_id != msg.uid {
+        if self.game.host != msg.uid {
             Err(WebsocketError::AuthorizationError())
         } else {
-            // update state
-            match self.0.states.get_mut(&msg.gid) {
-                Some(mut mut_ref) => {
-                    // update internal state
-                    mut_ref.value_mut().1 = 1_u8;
+            // games are started from the lobby once enough players joined.
+            // Partnership games need a complete 2 vs 2 team assignment
+            let game = &self.game;
+            if game.status != GameStatus::Lobby {
+                return Err(WebsocketError::ValidationError(
+                    "The game has already been started".to_owned(),
+                ));
+            } else if game.lobby.len() < MIN_PLAYERS {
+                return Err(WebsocketError::ValidationError(format!(
+                    "At least {} players are needed to start",
+                    MIN_PLAYERS
+                )));
+            } else if !game.all_ready() {
+                return Err(WebsocketError::ValidationError(
+                    "Not every player is seated and ready".to_owned(),
+                ));
+            } else if game.rules.teams {
+                game.teams().validate()?;
+            }
+
+            self.transition(GameStatus::Running, None)?;
+            self.game.seat_players();
+            self.game.clocks = Clocks::new(self.game.rules.time_control(), &self.game.players);
+
+            // joined players without a session have to come back (See await_return)
+            let offline: Vec<Uuid> = self
+                .game
+                .players
+                .iter()
+                .filter(|player| {
+                    !self.sessions.contains_key(player)
+                        && !self.game.disconnected.contains_key(player)
+                })
+                .copied()
+                .collect();
+            let now = Instant::now();
+            for player in offline {
+                self.game.disconnected.insert(player, now);
+                self.await_return(player, ctx);
+            }
+
+            // the first seat opens the game
+            self.advance_turn(Turn::Move(0), ctx);
+
+            Ok(())
+        }
+    }
+}
+
+// handler for players choosing their seat, piece icon and ready check in the lobby
+impl Handler<UpdateLobbyMessage> for GameRoom {
+    type Result = Result<(), WebsocketError>;
+
+    fn handle(&mut self, msg: UpdateLobbyMessage, _: &mut Context<Self>) -> Self::Result {
+        let game = &mut self.game;
+
+        if game.status != GameStatus::Lobby {
+            return Err(WebsocketError::ValidationError(
+                "Seats can only be changed in the lobby".to_owned(),
+            ));
+        } else if !game.players.contains(&msg.uid) {
+            return Err(WebsocketError::AuthorizationError());
+        }
+
+        // validate against the choices of the other players
+        match &msg.change {
+            LobbyChange::Seat(seat) if *seat as usize >= MAX_PLAYERS => {
+                return Err(WebsocketError::ValidationError(format!(
+                    "Seat must be between 0 and {}",
+                    MAX_PLAYERS - 1
+                )));
+            }
+            LobbyChange::Seat(seat) if game.seat_taken(*seat, &msg.uid) => {
+                return Err(WebsocketError::ValidationError(
+                    "This seat is already taken".to_owned(),
+                ));
+            }
+            LobbyChange::Icon(icon) if !PIECE_ICONS.contains(&icon.as_str()) => {
+                return Err(WebsocketError::ValidationError(
+                    "Unknown piece icon".to_owned(),
+                ));
+            }
+            LobbyChange::Icon(icon) if game.icon_taken(icon, &msg.uid) => {
+                return Err(WebsocketError::ValidationError(
+                    "This icon is already taken".to_owned(),
+                ));
+            }
+            _ => (),
+        }
+
+        let mut entry = game.lobby_entry(msg.uid).clone();
+        match msg.change {
+            LobbyChange::Seat(seat) => {
+                entry.seat = Some(seat);
+                entry.ready = false;
+            }
+            LobbyChange::Icon(icon) => {
+                entry.icon = Some(icon);
+                entry.ready = false;
+            }
+            LobbyChange::Ready(ready) if ready && entry.seat.is_none() => {
+                return Err(WebsocketError::ValidationError(
+                    "Choose a seat before getting ready".to_owned(),
+                ));
+            }
+            LobbyChange::Ready(ready) => entry.ready = ready,
+        }
+
+        // persist choices
+        set_user_lobby(
+            &conn,
+            self.gid,
+            msg.uid,
+            entry.seat.map(i16::from),
+            entry.icon.as_deref(),
+            entry.ready,
+        )?;
+        game.lobby.insert(msg.uid, entry.clone());
+
+        self.send_message(ServerMessage::Lobby(entry));
+
+        Ok(())
+    }
+}
+
+// handler for host moderation: kick/ ban players, hand over host rights and lock the lobby
+impl Handler<HostActionMessage> for GameRoom {
+    type Result = Result<(), WebsocketError>;
+
+    fn handle(&mut self, msg: HostActionMessage, _: &mut Context<Self>) -> Self::Result {
+        if self.game.host != msg.uid {
+            return Err(WebsocketError::AuthorizationError());
+        }
+        let status = self.game.status;
+
+        match msg.action {
+            // offline players of the lobby can be kicked as well
+            HostAction::Kick { player, .. }
+                if player == msg.uid || !self.game.lobby.contains_key(&player) =>
+            {
+                Err(WebsocketError::ValidationError(
+                    "Player hasn't joined this game".to_owned(),
+                ))
+            }
+            HostAction::TransferHost(player)
+                if player == msg.uid || !self.game.players.contains(&player) =>
+            {
+                Err(WebsocketError::ValidationError(
+                    "Player hasn't joined this game".to_owned(),
+                ))
+            }
+            // seats are fixed once the game started
+            HostAction::Kick { .. } | HostAction::LockLobby(_) if status != GameStatus::Lobby => {
+                Err(WebsocketError::ValidationError(
+                    "This can only be done in the lobby".to_owned(),
+                ))
+            }
+            HostAction::Kick { player, ban } => {
+                if ban {
+                    ban_user(&conn, self.gid, player)?;
+                } else {
+                    remove_user_game(&conn, self.gid, player)?;
                 }
-                None => {
-                    return Err(WebsocketError::ValidationError(
-                        "Game not found. Out of sync GameServer?".to_owned(),
-                    ));
+                self.game.remove_player(&player);
+
+                // the sessions of the kicked player are closed right away
+                let kicked = self.sessions.remove(&player).unwrap_or_default();
+                let event = self.send_message(ServerMessage::PlayerKicked {
+                    user: player,
+                    banned: ban,
+                });
+                for session in kicked {
+                    self.deliver_final(session, event.clone());
                 }
-            };
+                self.publish();
+                Ok(())
+            }
+            HostAction::TransferHost(player) => self.change_host(player),
+            HostAction::LockLobby(locked) => {
+                set_game_locked(&conn, self.gid, locked)?;
+                self.game.locked = locked;
 
-            self.send_message(&msg.gid, 5, DashMap::new());
+                self.send_message(ServerMessage::LobbyLocked { locked });
+                Ok(())
+            }
+        }
+    }
+}
 
-            Ok(())
+// handler for host pausing, resuming or stopping a game
+impl Handler<ChangeStatusMessage> for GameRoom {
+    type Result = Result<(), WebsocketError>;
+
+    fn handle(&mut self, msg: ChangeStatusMessage, ctx: &mut Context<Self>) -> Self::Result {
+        if self.game.host != msg.uid {
+            return Err(WebsocketError::AuthorizationError());
+        }
+
+        // starting and finishing depend on the game itself (See StartGameMessage and MakeMoveMessage)
+        match msg.status {
+            GameStatus::Paused | GameStatus::Running | GameStatus::Aborted => (),
+            _ => {
+                return Err(WebsocketError::ValidationError(format!(
+                    "A game can't be set to {} by the host",
+                    msg.status
+                )))
+            }
+        }
+        if msg.status == GameStatus::Running && self.game.status != GameStatus::Paused {
+            return Err(WebsocketError::ValidationError(
+                "Only paused games can be resumed".to_owned(),
+            ));
+        }
+
+        self.transition(msg.status, msg.message)?;
+        self.resume_clock(ctx);
+
+        Ok(())
+    }
+}
+
+// handler for players giving up
+impl Handler<ResignMessage> for GameRoom {
+    type Result = Result<(), WebsocketError>;
+
+    fn handle(&mut self, msg: ResignMessage, ctx: &mut Context<Self>) -> Self::Result {
+        match self.game.status {
+            GameStatus::Running | GameStatus::Paused => (),
+            _ => {
+                return Err(WebsocketError::ValidationError(
+                    "The game isn't running".to_owned(),
+                ))
+            }
+        }
+
+        self.eliminate(msg.uid, ResultReason::Resigned, ctx)
+    }
+}
+
+// handler for players leaving a lobby. They give up their seat and host rights. Lobbies are
+// removed once the last player left
+impl Handler<LeaveGameMessage> for GameRoom {
+    type Result = Result<(), WebsocketError>;
+
+    fn handle(&mut self, msg: LeaveGameMessage, ctx: &mut Context<Self>) -> Self::Result {
+        if self.game.status != GameStatus::Lobby {
+            return Err(WebsocketError::ValidationError(
+                "Only lobbies can be left. Resign to leave a running game".to_owned(),
+            ));
+        } else if !self.game.players.contains(&msg.uid) {
+            return Err(WebsocketError::ValidationError(
+                "You haven't joined this game".to_owned(),
+            ));
+        }
+
+        remove_user_game(&conn, self.gid, msg.uid)?;
+        self.game.remove_player(&msg.uid);
+        self.game.disconnected.remove(&msg.uid);
+        self.send_message(ServerMessage::PlayerLeft { user: msg.uid });
+
+        if self.game.players.is_empty() {
+            self.close(ctx);
+            return Ok(());
+        }
+
+        if self.game.host == msg.uid {
+            let next = self.game.players[0];
+            self.change_host(next)?;
+        }
+        self.publish();
+
+        Ok(())
+    }
+}
+
+// handler for draw offers. Accepting counts as an offer of its own
+impl Handler<DrawMessage> for GameRoom {
+    type Result = Result<(), WebsocketError>;
+
+    fn handle(&mut self, msg: DrawMessage, ctx: &mut Context<Self>) -> Self::Result {
+        let game = &mut self.game;
+        match game.status {
+            GameStatus::Running | GameStatus::Paused => (),
+            _ => {
+                return Err(WebsocketError::ValidationError(
+                    "The game isn't running".to_owned(),
+                ))
+            }
+        }
+        if !game.remaining().contains(&msg.uid) {
+            return Err(WebsocketError::AuthorizationError());
+        }
+
+        let pending = game.draw_offers.iter().any(|player| player != &msg.uid);
+        let agreed = match msg.action {
+            DrawAction::Accept | DrawAction::Decline if !pending => {
+                return Err(WebsocketError::ValidationError(
+                    "Nobody offered a draw".to_owned(),
+                ));
+            }
+            DrawAction::Decline => {
+                game.draw_offers.clear();
+                None
+            }
+            DrawAction::Offer | DrawAction::Accept => {
+                game.draw_offers.insert(msg.uid);
+                let remaining = game.remaining();
+                Some(
+                    remaining
+                        .iter()
+                        .all(|player| game.draw_offers.contains(player)),
+                )
+            }
+        };
+
+        match agreed {
+            Some(true) => self.finish(Vec::new(), ResultReason::Draw, ctx),
+            Some(false) => {
+                self.send_message(ServerMessage::DrawOffered { user: msg.uid });
+                Ok(())
+            }
+            None => {
+                self.send_message(ServerMessage::DrawDeclined { user: msg.uid });
+                Ok(())
+            }
         }
     }
 }
 
+// Source field of a move and the field the figure left with its previous move (simple ko).
+// The board is the trusted source since swaps, custom starting positions and restored snapshots
+// move figures without a move record of their own
+fn move_source(
+    board: &GraphState,
+    figure: &Figure,
+    last: Option<&[i16]>,
+) -> Result<(FIELD, Option<FIELD>), WebsocketError> {
+    let src = match board.locate(figure) {
+        Some(field) if field == OFF_BOARD => {
+            return Err(WebsocketError::ValidationError(
+                "This figure already left the board".to_owned(),
+            ))
+        }
+        Some(field) => field,
+        None => {
+            return Err(WebsocketError::ValidationError(format!(
+                "There's no figure with id {}",
+                figure
+            )))
+        }
+    };
+
+    // ko only applies while the figure stands where its previous move ended
+    let previous = last.and_then(|action| {
+        let (last_src, last_dest) = action.split_at(3);
+        if last_dest == &src[..] {
+            last_src.try_into().ok()
+        } else {
+            None
+        }
+    });
+
+    Ok((src, previous))
+}
+
 // handler for user move
-impl Handler<MakeMoveMessage> for GameServer {
+impl Handler<MakeMoveMessage> for GameRoom {
     type Result = Result<bool, WebsocketError>;
 
-    fn handle(&mut self, msg: MakeMoveMessage, _: &mut Context<Self>) -> Self::Result {
+    fn handle(&mut self, msg: MakeMoveMessage, ctx: &mut Context<Self>) -> Self::Result {
+        // resent requests are acknowledged without applying them again
+        if self.applied(msg.uid, msg.request) {
+            return Ok(false);
+        }
+
         // get connections
         let dest = [msg.action.0[3], msg.action.0[4], msg.action.0[5]];
+        let figure = msg.action.1.clone();
+
+        // fetch rules chosen for this game and the moving player
+        let (rules, teams, player) = (
+            self.game.rules.clone(),
+            self.game.teams(),
+            self.game.player_index(&msg.uid),
+        );
+
+        // players may only move their own figures (this includes team games)
+        match (FigureKind::of(&figure), player) {
+            (FigureKind::Player(owner, _), Some(player)) if owner == player => (),
+            _ => return Err(WebsocketError::AuthorizationError()),
+        }
+        let seat = player.unwrap();
+
+        // moves are only accepted from the active player
+        self.check_turn(seat, TurnKind::Move)?;
+
+        self.check_clock(msg.uid, ctx)?;
 
-        // fetch starting point (db is trusted source)
+        // fetch the figure's previous move (simple ko)
         let db_friendly_figure: i16 = msg.action.1.into(); // SMALLINT requires i16
-        let src = match fetch_latest_move(&conn, msg.gid, msg.uid, db_friendly_figure) {
-            // take response and translate to array
-            Ok((action, _)) => {
-                let (last_src, last_dest) = action.split_at(2);
-                // ensure move isn't directly repetitive - NOTE: This doesn'z cover
-                if dest == last_dest {
-                    return Err(WebsocketError::ValidationError(
-                        "This move is repetitive".to_owned(),
-                    ));
-                } else {
-                    last_src
-                        .try_into()
-                        .expect("Mov fetching returned corrupted results")
-                }
-            }
-            // no move was made. Fall back
-            Err(DBError::NotFound { .. }) => [db_friendly_figure, 0, 0],
+        let last = match fetch_latest_move(&conn, self.gid, msg.uid, db_friendly_figure) {
+            Ok((action, _)) => Some(action),
+            // no move was made yet
+            Err(DBError::NotFound { .. }) => None,
             Err(_) => {
                 log_error("[CRITICAL ERROR]", "Corrupted Database!!!!".to_owned());
                 return Err(APIError::InternalError(
@@ -192,74 +1282,359 @@ impl Handler<MakeMoveMessage> for GameServer {
                 ));
             }
         };
+        let (src, previous) = move_source(
+            &self.board,
+            &figure,
+            last.as_ref().map(|action| &action[..]),
+        )?;
 
         // validate move
-        let state = self.states.get(&msg.gid).unwrap().value().0;
+        let mut state = self.board.clone();
         let graph = GRAPH.clone();
         let result = graph.validate(&src, &dest, &state)?;
 
-        match result.0 {
-            true => {
-                // add move to db
-                make_new_move(
-                    &conn,
-                    msg.uid,
-                    msg.gid,
-                    (
-                        [
-                            *src.get(0).unwrap(),
-                            *src.get(1).unwrap(),
-                            *src.get(2).unwrap(),
-                            dest[0],
-                            dest[1],
-                            dest[2],
-                        ],
-                        result.1,
-                    ),
-                )?;
-                // send message of move to all other players
-
-                Ok(true)
-            }
-            false => Err(APIError::ValidationError(
+        if !result.0 {
+            return Err(APIError::ValidationError(
                 "This move isn't possible".to_owned(),
-            )),
+            ));
+        }
+
+        // check move against the game's rule set
+        let effect = rules.evaluate(&figure, &dest, &result.1, previous.as_ref(), &teams)?;
+        state.apply(&figure, &dest, &effect);
+        rules.check_repetition(&state, &self.game.history)?;
+
+        // add move to db
+        make_new_move(
+            &conn,
+            msg.uid,
+            self.gid,
+            (
+                [src[0], src[1], src[2], dest[0], dest[1], dest[2]],
+                result.1,
+            ),
+        )?;
+
+        // update internal state
+        self.game.history.push(state.fingerprint());
+        if let Some(request) = msg.request {
+            self.game.push_request(msg.uid, request);
+        }
+        let won = rules.has_won(&state, seat, &teams);
+        let gray_due = effect.gray_stopper && state.has_off_board(FigureKind::GrayStopper);
+        self.board = state;
+
+        // send message of move to all other players
+        let clocks = self.clocks();
+        self.send_message(ServerMessage::MoveMade {
+            user: msg.uid,
+            figure,
+            action: msg.action.0,
+            clocks,
+        });
+
+        if won {
+            self.send_message(ServerMessage::PlayerWon { user: msg.uid });
+            let winners = self.game.side(&msg.uid);
+            self.finish(winners, ResultReason::Won, ctx)?;
+            return Ok(true);
+        }
+
+        // stoppers that need to be (re)placed stay with the moving player
+        self.game.pending_gray = effect.black_stopper.is_some() && gray_due;
+        let next = if effect.black_stopper.is_some() {
+            Turn::BlackStopper(seat)
+        } else if gray_due {
+            Turn::GrayStopper(seat)
+        } else {
+            Turn::Move(self.game.next_seat(seat))
+        };
+        self.advance_turn(next, ctx);
+
+        Ok(true)
+    }
+}
+
+// handler for (re)placing a stopper after a move
+impl Handler<PlaceStopperMessage> for GameRoom {
+    type Result = Result<bool, WebsocketError>;
+
+    fn handle(&mut self, msg: PlaceStopperMessage, ctx: &mut Context<Self>) -> Self::Result {
+        // resent requests are acknowledged without applying them again
+        if self.applied(msg.uid, msg.request) {
+            return Ok(false);
+        }
+
+        let seat = self
+            .game
+            .player_index(&msg.uid)
+            .ok_or_else(WebsocketError::AuthorizationError)?;
+
+        // the turn decides which kind of stopper has to be placed
+        let kind = match FigureKind::of(&msg.figure) {
+            FigureKind::BlackStopper => TurnKind::BlackStopper,
+            FigureKind::GrayStopper => TurnKind::GrayStopper,
+            _ => {
+                return Err(WebsocketError::ValidationError(
+                    "Only stoppers can be placed".to_owned(),
+                ));
+            }
+        };
+        self.check_turn(seat, kind)?;
+        self.check_clock(msg.uid, ctx)?;
+
+        let mut state = self.board.clone();
+        state.place_stopper(&msg.figure, &msg.field)?;
+
+        // add placement to db. Stoppers enter the board from `OFF_BOARD`
+        let field = msg.field;
+        make_new_move(
+            &conn,
+            msg.uid,
+            self.gid,
+            (
+                [
+                    OFF_BOARD[0],
+                    OFF_BOARD[1],
+                    OFF_BOARD[2],
+                    field[0],
+                    field[1],
+                    field[2],
+                ],
+                msg.figure.clone(),
+            ),
+        )?;
+
+        // update internal state
+        self.game.history.push(state.fingerprint());
+        if let Some(request) = msg.request {
+            self.game.push_request(msg.uid, request);
+        }
+        self.board = state;
+
+        // a gray stopper may still be due after the black one
+        let next = if kind == TurnKind::BlackStopper && self.game.pending_gray {
+            self.game.pending_gray = false;
+            Turn::GrayStopper(seat)
+        } else {
+            Turn::Move(self.game.next_seat(seat))
+        };
+
+        let clocks = self.clocks();
+        self.send_message(ServerMessage::StopperPlaced {
+            user: msg.uid,
+            figure: msg.figure,
+            field,
+            clocks,
+        });
+        self.advance_turn(next, ctx);
+
+        Ok(true)
+    }
+}
+
+// handler for host assigning a player to a team (partnership games)
+impl Handler<AssignTeamMessage> for GameRoom {
+    type Result = Result<(), WebsocketError>;
+
+    fn handle(&mut self, msg: AssignTeamMessage, _: &mut Context<Self>) -> Self::Result {
+        let game = &mut self.game;
+
+        if game.host != msg.uid {
+            return Err(WebsocketError::AuthorizationError());
+        } else if game.status != GameStatus::Lobby {
+            return Err(WebsocketError::ValidationError(
+                "Teams can only be assigned in the lobby".to_owned(),
+            ));
+        } else if !game.rules.teams {
+            return Err(WebsocketError::ValidationError(
+                "This game isn't played in teams".to_owned(),
+            ));
+        } else if msg.team > 1 {
+            return Err(WebsocketError::ValidationError(
+                "Team must be either 0 or 1".to_owned(),
+            ));
+        } else if !game.players.contains(&msg.player) {
+            return Err(WebsocketError::ValidationError(
+                "Player hasn't joined this game".to_owned(),
+            ));
+        }
+
+        // persist assignment
+        set_user_team(&conn, self.gid, msg.player, Some(msg.team.into()))?;
+        game.teams.insert(msg.player, msg.team);
+
+        self.send_message(ServerMessage::TeamAssigned {
+            user: msg.player,
+            team: msg.team,
+        });
+
+        Ok(())
+    }
+}
+
+// handler for chat messages of players
+impl Handler<SendChatMessage> for GameRoom {
+    type Result = Result<(), WebsocketError>;
+
+    fn handle(&mut self, msg: SendChatMessage, _: &mut Context<Self>) -> Self::Result {
+        // only players chat. Lobbies count everyone that joined, connected or not
+        let member = match self.game.status {
+            GameStatus::Lobby => self.game.lobby.contains_key(&msg.uid),
+            _ => self.game.players.contains(&msg.uid),
+        };
+
+        let message = msg.message.trim();
+        if !member {
+            return Err(WebsocketError::AuthorizationError());
+        } else if message.is_empty() {
+            return Err(WebsocketError::ValidationError(
+                "Chat messages can't be empty".to_owned(),
+            ));
+        } else if message.chars().count() > CHAT_MAX_LENGTH {
+            return Err(WebsocketError::ValidationError(format!(
+                "Chat messages are limited to {} characters",
+                CHAT_MAX_LENGTH
+            )));
+        } else if self.game.muted.contains(&msg.uid) {
+            return Err(WebsocketError::AuthorizationError());
         }
+
+        // persist with the game record for replays
+        let sent = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as i64);
+        let entry: ChatEntry = add_chat_message(&conn, self.gid, msg.uid, message, sent)?.into();
+
+        self.game.push_chat(entry.clone());
+        self.send_message(ServerMessage::Chat(entry));
+
+        Ok(())
+    }
+}
+
+// handler for host (un)muting a player in the chat
+impl Handler<MuteChatMessage> for GameRoom {
+    type Result = Result<(), WebsocketError>;
+
+    fn handle(&mut self, msg: MuteChatMessage, _: &mut Context<Self>) -> Self::Result {
+        if self.game.host != msg.uid {
+            return Err(WebsocketError::AuthorizationError());
+        } else if msg.muted {
+            self.game.muted.insert(msg.player);
+        } else {
+            self.game.muted.remove(&msg.player);
+        }
+
+        self.send_message(ServerMessage::ChatMuted {
+            user: msg.player,
+            muted: msg.muted,
+        });
+
+        Ok(())
     }
 }
 
 // handler for game query message
-impl Handler<QueryGameMessage> for GameServer {
-    type Result = Result<
-        (
-            String,              // name
-            String,              // description
-            String,              // icon
-            Vec<(Uuid, String)>, // users
-            u8,                  // state
-            Uuid,                // host
-            Vec<i16>,            // pin
-        ),
-        APIError,
-    >;
-
-    fn handle(&mut self, msg: QueryGameMessage, _: &mut Context<Self>) -> Self::Result {
-        let conn = self.pool.get()?;
-
-        let (name, description, _, icon, pin) = get_slim_game(&conn, msg.gid)?;
-        let users = get_game_users(&conn, msg.gid)?;
-        // TODO: consider storing users (SlimUser + usize) within gameserver
-        let host_id: Uuid = get_game_host(&conn, msg.gid)?;
-        let game_state = self.states.get(&msg.gid).unwrap().1;
-
-        Ok((
-            name,
-            description.unwrap_or("".to_owned()),
-            icon,
-            users,
-            game_state,
-            host_id,
-            pin.unwrap_or(Vec::new()),
-        ))
+impl Handler<QueryGameMessage> for GameRoom {
+    type Result = Result<QueryGameResponse, WebsocketError>;
+
+    fn handle(&mut self, _: QueryGameMessage, _: &mut Context<Self>) -> Self::Result {
+        self.metadata()
+    }
+}
+
+// handler for sessions that worked through their backlog after a resync.
+//
+// Events of the resync's round trip are replayed from the log
+impl Handler<ResyncedMessage> for GameRoom {
+    type Result = ();
+
+    fn handle(&mut self, msg: ResyncedMessage, _: &mut Context<Self>) {
+        let seq = match self.lagging.remove(&msg.addr) {
+            Some(seq) => seq,
+            None => return,
+        };
+
+        match self.game.events.since(seq) {
+            Some(missed) => {
+                for event in missed {
+                    self.deliver(msg.addr.clone(), event);
+                }
+            }
+            None => self.resync(msg.addr),
+        }
+    }
+}
+
+// handler for warning the room's sessions about the upcoming shutdown
+impl Handler<NoticeShutdownMessage> for GameRoom {
+    type Result = ();
+
+    fn handle(&mut self, msg: NoticeShutdownMessage, _: &mut Context<Self>) {
+        self.send_message(ServerMessage::ShutdownNotice {
+            seconds: msg.seconds,
+        });
+    }
+}
+
+// handler for the server shutting down.
+//
+// The snapshot includes the final event so sessions can resume after the restart. The room is
+// stopped without closing the game (See close)
+impl Handler<ShutdownMessage> for GameRoom {
+    type Result = ();
+
+    fn handle(&mut self, _: ShutdownMessage, ctx: &mut Context<Self>) {
+        let recipients: Vec<Recipient<GameEvent>> = self
+            .sessions
+            .values()
+            .flatten()
+            .chain(self.spectators.iter())
+            .cloned()
+            .collect();
+        let event = self.game.events.push(ServerMessage::Shutdown);
+        for recipient in recipients {
+            self.deliver_final(recipient, event.clone());
+        }
+        self.save();
+
+        self.rooms.remove(self.gid, &ctx.address());
+        ctx.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::position::starting_position;
+
+    #[test]
+    fn move_starts_from_custom_position() {
+        let board = starting_position(Some("7: 5,1,6"), &[]).unwrap();
+
+        let (src, previous) = move_source(&board, &"7".to_owned(), None).unwrap();
+        assert_eq!(src, [5, 1, 6]);
+        assert_eq!(previous, None);
+    }
+
+    #[test]
+    fn move_starts_where_swap_left_figure() {
+        // figure 7 was swapped away from [5, 1, 6] after moving there from junction 6
+        let board = starting_position(Some("7: 8,0,0"), &[]).unwrap();
+        let last = [6, 0, 0, 5, 1, 6];
+
+        let (src, previous) = move_source(&board, &"7".to_owned(), Some(&last[..])).unwrap();
+        assert_eq!(src, [8, 0, 0]);
+        assert_eq!(previous, None);
+    }
+
+    #[test]
+    fn previous_field_is_kept_for_ko() {
+        let board = starting_position(Some("7: 5,1,6"), &[]).unwrap();
+        let last = [6, 0, 0, 5, 1, 6];
+
+        let (_, previous) = move_source(&board, &"7".to_owned(), Some(&last[..])).unwrap();
+        assert_eq!(previous, Some([6, 0, 0]));
     }
 }
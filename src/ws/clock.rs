@@ -0,0 +1,263 @@
+use crate::graph::rules::TimeControl;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+// source of the current time for game clocks. Game rooms only read time through this
+// so clocks can be driven by hand (See state::Rooms::build)
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+// wall clock used in production
+pub struct SystemClock;
+
+// time left of a player as sent to clients (milliseconds)
+#[derive(Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PlayerClock {
+    pub user: Uuid,
+    pub remaining: u64,
+    // the clock is ticking (the player has to act)
+    pub running: bool,
+}
+
+// clocks of all players of a game. At most one clock runs at a time
+pub struct Clocks {
+    control: TimeControl,
+    // time left of every player when their clock was last stopped
+    remaining: HashMap<Uuid, Duration>,
+    // player whose clock is ticking and since when
+    running: Option<(Uuid, Instant)>,
+    // bumped whenever the running clock changes. Flag timers of older generations are stale
+    generation: u64,
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+impl Clocks {
+    pub fn new(control: TimeControl, players: &[Uuid]) -> Clocks {
+        let initial = match control {
+            TimeControl::Untimed => None,
+            TimeControl::Fischer { base, .. } => Some(base),
+            TimeControl::PerMove(limit) => Some(limit),
+        };
+
+        Clocks {
+            control,
+            remaining: match initial {
+                Some(initial) => players.iter().map(|player| (*player, initial)).collect(),
+                None => HashMap::new(),
+            },
+            running: None,
+            generation: 0,
+        }
+    }
+
+    // clocks continued from a snapshot (See snapshot::RoomSnapshot). All clocks are stopped until
+    // the room starts the one of the active player
+    pub fn restore(control: TimeControl, remaining: HashMap<Uuid, Duration>) -> Clocks {
+        Clocks {
+            control,
+            remaining,
+            running: None,
+            generation: 0,
+        }
+    }
+
+    // time left of every player at `now`
+    pub fn times(&self, now: Instant) -> HashMap<Uuid, Duration> {
+        self.remaining
+            .keys()
+            .map(|player| (*player, self.remaining(player, now)))
+            .collect()
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn running(&self) -> Option<Uuid> {
+        self.running.map(|(player, _)| player)
+    }
+
+    // time left of a player at `now`
+    pub fn remaining(&self, uid: &Uuid, now: Instant) -> Duration {
+        let stored = self.remaining.get(uid).copied().unwrap_or_default();
+        match self.running {
+            Some((player, since)) if &player == uid => {
+                stored.saturating_sub(now.saturating_duration_since(since))
+            }
+            _ => stored,
+        }
+    }
+
+    // player whose time ran out
+    pub fn flagged(&self, now: Instant) -> Option<Uuid> {
+        match self.running {
+            Some((player, _)) if self.remaining(&player, now) == Duration::from_secs(0) => {
+                Some(player)
+            }
+            _ => None,
+        }
+    }
+
+    // start the clock of a player. Returns the new generation and the time left for the flag timer
+    pub fn start(&mut self, uid: Uuid, now: Instant) -> Option<(u64, Duration)> {
+        if !self.remaining.contains_key(&uid) {
+            return None;
+        }
+
+        self.generation += 1;
+        self.running = Some((uid, now));
+        Some((self.generation, self.remaining(&uid, now)))
+    }
+
+    // stop the running clock. Completed turns earn the increment or a fresh time per move
+    pub fn stop(&mut self, now: Instant, completed: bool) {
+        let player = match self.running {
+            Some((player, _)) => player,
+            None => return,
+        };
+
+        let left = self.remaining(&player, now);
+        let left = match self.control {
+            TimeControl::Fischer { increment, .. } if completed => left + increment,
+            TimeControl::PerMove(limit) if completed => limit,
+            _ => left,
+        };
+
+        self.remaining.insert(player, left);
+        self.running = None;
+        self.generation += 1;
+    }
+
+    // clocks in player order. Untimed games have none
+    pub fn snapshot(&self, players: &[Uuid], now: Instant) -> Vec<PlayerClock> {
+        players
+            .iter()
+            .filter(|player| self.remaining.contains_key(player))
+            .map(|player| PlayerClock {
+                user: *player,
+                remaining: self.remaining(player, now).as_millis() as u64,
+                running: self.running() == Some(*player),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // clock that only moves when told to
+    struct ManualClock {
+        start: Instant,
+        elapsed: Mutex<Duration>,
+    }
+
+    impl ManualClock {
+        fn new() -> ManualClock {
+            ManualClock {
+                start: Instant::now(),
+                elapsed: Mutex::new(Duration::from_secs(0)),
+            }
+        }
+
+        fn advance(&self, seconds: u64) {
+            *self.elapsed.lock().unwrap() += Duration::from_secs(seconds);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            self.start + *self.elapsed.lock().unwrap()
+        }
+    }
+
+    fn fischer(base: u64, increment: u64) -> TimeControl {
+        TimeControl::Fischer {
+            base: Duration::from_secs(base),
+            increment: Duration::from_secs(increment),
+        }
+    }
+
+    #[test]
+    fn player_flags_when_time_runs_out() {
+        let clock = ManualClock::new();
+        let players = [Uuid::new_v4(), Uuid::new_v4()];
+        let mut clocks = Clocks::new(fischer(60, 0), &players);
+
+        let (_, left) = clocks.start(players[0], clock.now()).unwrap();
+        assert_eq!(left, Duration::from_secs(60));
+
+        clock.advance(59);
+        assert_eq!(clocks.flagged(clock.now()), None);
+        clock.advance(1);
+        assert_eq!(clocks.flagged(clock.now()), Some(players[0]));
+        assert_eq!(
+            clocks.remaining(&players[1], clock.now()),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn completed_turns_earn_the_increment() {
+        let clock = ManualClock::new();
+        let players = [Uuid::new_v4(), Uuid::new_v4()];
+        let mut clocks = Clocks::new(fischer(60, 5), &players);
+
+        clocks.start(players[0], clock.now());
+        clock.advance(20);
+        clocks.stop(clock.now(), true);
+        assert_eq!(
+            clocks.remaining(&players[0], clock.now()),
+            Duration::from_secs(45)
+        );
+
+        // per move limits start over after every turn
+        let mut clocks = Clocks::new(TimeControl::PerMove(Duration::from_secs(30)), &players);
+        clocks.start(players[1], clock.now());
+        clock.advance(20);
+        clocks.stop(clock.now(), true);
+        assert_eq!(
+            clocks.remaining(&players[1], clock.now()),
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn paused_clocks_keep_their_time() {
+        let clock = ManualClock::new();
+        let players = [Uuid::new_v4(), Uuid::new_v4()];
+        let mut clocks = Clocks::new(fischer(60, 5), &players);
+
+        let (started, _) = clocks.start(players[0], clock.now()).unwrap();
+        clock.advance(10);
+        clocks.stop(clock.now(), false);
+        assert_eq!(clocks.running(), None);
+
+        // time passing while paused doesn't count and flag timers of the old clock are stale
+        clock.advance(100);
+        assert_eq!(clocks.flagged(clock.now()), None);
+        let (resumed, left) = clocks.start(players[0], clock.now()).unwrap();
+        assert!(resumed > started);
+        assert_eq!(left, Duration::from_secs(50));
+    }
+
+    #[test]
+    fn untimed_games_have_no_clocks() {
+        let clock = ManualClock::new();
+        let players = [Uuid::new_v4()];
+        let mut clocks = Clocks::new(TimeControl::Untimed, &players);
+
+        assert_eq!(clocks.start(players[0], clock.now()), None);
+        assert!(clocks.snapshot(&players, clock.now()).is_empty());
+    }
+}
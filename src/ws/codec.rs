@@ -0,0 +1,77 @@
+use super::errors::{WebResult, WebsocketError};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/*
+Encodings of protocol messages (chosen at connect with /games/ws/{id}?version={version}&encoding={codec})
+---
+| encoding | frames | description                             |
+| -------- | ------ | --------------------------------------- |
+| json     | text   | default                                 |
+| msgpack  | binary | MessagePack (named fields like in JSON) |
+| cbor     | binary | CBOR                                    |
+
+Text frames are always accepted as JSON so clients may fall back when debugging.
+*/
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Codec {
+    Json,
+    Msgpack,
+    Cbor,
+}
+
+// encoded message ready to be written to a websocket
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Default for Codec {
+    fn default() -> Self {
+        Codec::Json
+    }
+}
+
+impl Codec {
+    // check encoding requested by client. Missing encoding falls back to JSON
+    pub fn negotiate(requested: Option<&str>) -> WebResult<Codec> {
+        match requested {
+            None | Some("json") => Ok(Codec::Json),
+            Some("msgpack") => Ok(Codec::Msgpack),
+            Some("cbor") => Ok(Codec::Cbor),
+            Some(unknown) => Err(WebsocketError::ValidationError(format!(
+                "Unknown encoding '{}'. Supported encodings: json, msgpack, cbor",
+                unknown
+            ))),
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, message: &T) -> WebResult<Frame> {
+        match self {
+            Codec::Json => Ok(Frame::Text(serde_json::to_string(message)?)),
+            Codec::Msgpack => rmp_serde::to_vec_named(message)
+                .map(Frame::Binary)
+                .map_err(|why| WebsocketError::InternalError(why.to_string())),
+            Codec::Cbor => serde_cbor::to_vec(message)
+                .map(Frame::Binary)
+                .map_err(|why| WebsocketError::InternalError(why.to_string())),
+        }
+    }
+
+    // decode binary frame
+    pub fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> WebResult<T> {
+        match self {
+            Codec::Json => Err(WebsocketError::ValidationError(
+                "Binary frames require a binary encoding (?encoding=msgpack or ?encoding=cbor)"
+                    .to_owned(),
+            )),
+            Codec::Msgpack => {
+                rmp_serde::from_read_ref(data).map_err(|_| WebsocketError::MessageFormatError())
+            }
+            Codec::Cbor => {
+                serde_cbor::from_slice(data).map_err(|_| WebsocketError::MessageFormatError())
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
 // imports
+use super::messages::ServerMessage;
 use crate::frontend::helper::log_error;
-use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
+use crate::graph::errors::GraphErr;
+use actix_web::{error::BlockingError, error::ResponseError, http::StatusCode, HttpResponse};
 use derive_more::Display;
+use diesel::result::Error as DBError;
+use schemars::JsonSchema;
 use serde::Serialize;
 
 pub type WebResult<R> = Result<R, WebsocketError>;
@@ -18,57 +22,68 @@ pub enum WebsocketError {
     UnimplementedError(),
     #[display(fmt = "You don't have the required permissions to perform this action")]
     AuthorizationError(),
+    #[display(fmt = "Unsupported protocol version")]
+    VersionError(String),
+    #[display(fmt = "It's not your turn")]
+    TurnError(),
 }
 
-#[derive(Serialize)]
-pub struct ErrorMessage<'a> {
-    code: u16,
-    message: &'a str,
+// error payload of `ServerMessage::Error` and of failed HTTP requests
+#[derive(Serialize, Clone, Debug, JsonSchema)]
+pub struct ErrorMessage {
+    pub code: u16,
+    pub message: String,
 }
 
-impl<'a> ErrorMessage<'a> {
-    pub fn text<'x>(error: WebsocketError) -> String {
+impl ErrorMessage {
+    // serialized `ServerMessage::Error` ready to be sent to a client
+    pub fn text(error: WebsocketError) -> String {
+        return serde_json::to_string(&ServerMessage::Error(ErrorMessage::from(&error)))
+            .expect("The creation of websocket error messages failed");
+    }
+}
+
+impl From<&WebsocketError> for ErrorMessage {
+    fn from(error: &WebsocketError) -> Self {
         let (code, message) = match error {
-            WebsocketError::InternalError(e) => {
-                (0, format!("Internal Error: {}", e).as_str())
-            }
-            WebsocketError::MessageFormatError { .. } => {
-                (1, "MessageError: Seems like your message couldn't be loaded from JSON")
-            }
-            WebsocketError::AuthorizationError { .. } => {
-                (3, "MessageError: Seems like you weren't allowed to perform this action")
-            }
-            WebsocketError::UnimplementedError { .. } => {
-                (u16::MAX, "Unimplemented: The action you tried to use is either implemented/ supported at them moment")
-            }
+            WebsocketError::InternalError(e) => (0, format!("Internal Error: {}", e)),
+            WebsocketError::MessageFormatError { .. } => (
+                1,
+                "MessageError: Seems like your message couldn't be loaded from JSON".to_owned(),
+            ),
+            WebsocketError::ValidationError(e) => (2, format!("ValidationError: {}", e)),
+            WebsocketError::AuthorizationError { .. } => (
+                3,
+                "MessageError: Seems like you weren't allowed to perform this action".to_owned(),
+            ),
+            WebsocketError::VersionError(e) => (4, format!("VersionError: {}", e)),
+            WebsocketError::TurnError { .. } => (5, "TurnError: It's not your turn".to_owned()),
+            WebsocketError::UnimplementedError { .. } => (
+                u16::MAX,
+                "Unimplemented: The action you tried to use is either implemented/ supported at them moment".to_owned(),
+            ),
         };
 
-        return serde_json::to_string(&ErrorMessage { code, message })
-            .expect("The creation of websocket error messages failed");
+        ErrorMessage { code, message }
     }
 }
 
 impl ResponseError for WebsocketError {
     fn error_response(&self) -> HttpResponse {
-        match self {
-            WebsocketError::InternalError(e) => {
-                log_error("Internal Panic", e.clone());
-                HttpResponse::Ok().json(ErrorMessage {
-                    message: e.as_str(),
-                    code: 0,
-                })
-            }
-            _ => HttpResponse::Ok().json(ErrorMessage {
-                message: ErrorMessage::text(*self).as_str(),
-                code: self.status_code().as_u16(),
-            }),
+        if let WebsocketError::InternalError(e) = self {
+            log_error("Internal Panic", e.clone());
         }
+
+        HttpResponse::build(self.status_code()).json(ErrorMessage::from(self))
     }
 
     fn status_code(&self) -> StatusCode {
         match *self {
             WebsocketError::InternalError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             WebsocketError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+            WebsocketError::VersionError { .. } => StatusCode::BAD_REQUEST,
+            WebsocketError::TurnError { .. } => StatusCode::CONFLICT,
+            WebsocketError::AuthorizationError { .. } => StatusCode::FORBIDDEN,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -80,6 +95,40 @@ impl From<serde_json::Error> for WebsocketError {
     }
 }
 
+impl From<DBError> for WebsocketError {
+    fn from(error: DBError) -> Self {
+        return WebsocketError::InternalError(error.to_string());
+    }
+}
+
+impl From<BlockingError<DBError>> for WebsocketError {
+    fn from(error: BlockingError<DBError>) -> Self {
+        match error {
+            BlockingError::Error(db_error) => db_error.into(),
+            BlockingError::Canceled => {
+                WebsocketError::InternalError("Thread blocking error".to_owned())
+            }
+        }
+    }
+}
+
+impl From<BlockingError<WebsocketError>> for WebsocketError {
+    fn from(error: BlockingError<WebsocketError>) -> Self {
+        match error {
+            BlockingError::Error(error) => error,
+            BlockingError::Canceled => {
+                WebsocketError::InternalError("Thread blocking error".to_owned())
+            }
+        }
+    }
+}
+
+impl From<GraphErr> for WebsocketError {
+    fn from(error: GraphErr) -> Self {
+        return WebsocketError::ValidationError(error.to_string());
+    }
+}
+
 // Errors with 'static' outcomes that don't feature failure specific fields may be cached here
 lazy_static! {
     pub static ref MESSAGE_FORMAT_ERROR: String =
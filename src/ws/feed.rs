@@ -0,0 +1,137 @@
+use super::errors::WebsocketError;
+use super::messages::{
+    FeedMessage, GameListing, LobbyFilter, PublishGame, SeedFeed, ShutdownMessage, Subscribe,
+    UnlistGame, Unsubscribe,
+};
+use crate::db::actions::get_open_games;
+use actix::prelude::*;
+use diesel::PgConnection;
+use std::collections::HashMap;
+
+// `LobbyFeed` lists the open games and pushes changes to lobby feed sessions. Games are published
+// by the route creating them and by their rooms afterwards (See actor::GameRoom::publish)
+#[derive(Default)]
+pub struct LobbyFeed {
+    games: HashMap<i32, GameListing>,
+    // feed sessions and the games they're interested in
+    subscribers: HashMap<Recipient<FeedMessage>, LobbyFilter>,
+}
+
+impl LobbyFeed {
+    // open games of the database for seeding the feed (See state::Rooms::claim_seed)
+    pub fn load(conn: &PgConnection) -> Result<Vec<GameListing>, WebsocketError> {
+        Ok(get_open_games(conn)?
+            .into_iter()
+            .map(|(record, players)| GameListing {
+                id: record.id,
+                status: record.status(),
+                private: record.pin.is_some(),
+                name: record.name,
+                icon: record.icon,
+                players,
+            })
+            .collect())
+    }
+
+    // open games matching a filter, newest first
+    fn listing(&self, filter: &LobbyFilter) -> Vec<GameListing> {
+        let mut games: Vec<GameListing> = self
+            .games
+            .values()
+            .filter(|listing| filter.matches(listing))
+            .cloned()
+            .collect();
+        games.sort_by(|a, b| b.id.cmp(&a.id));
+        games
+    }
+
+    // tell subscribers about a changed game. Games leaving the filter of a subscriber are unlisted
+    fn broadcast(&self, id: i32, previous: Option<&GameListing>, current: Option<&GameListing>) {
+        for (subscriber, filter) in &self.subscribers {
+            let listed = previous.map_or(false, |listing| filter.matches(listing));
+            let message = match current {
+                Some(listing) if filter.matches(listing) => {
+                    FeedMessage::GameListed(listing.clone())
+                }
+                _ if listed => FeedMessage::GameUnlisted { id },
+                _ => continue,
+            };
+            let _ = subscriber.do_send(message);
+        }
+    }
+}
+
+impl Actor for LobbyFeed {
+    type Context = Context<Self>;
+}
+
+// Handler for Subscribe message.
+//
+// Subscribing again replaces the filter. The matching games are sent right away
+impl Handler<Subscribe> for LobbyFeed {
+    type Result = ();
+
+    fn handle(&mut self, msg: Subscribe, _: &mut Context<Self>) {
+        let _ = msg
+            .addr
+            .do_send(FeedMessage::Games(self.listing(&msg.filter)));
+        self.subscribers.insert(msg.addr, msg.filter);
+    }
+}
+
+impl Handler<Unsubscribe> for LobbyFeed {
+    type Result = ();
+
+    fn handle(&mut self, msg: Unsubscribe, _: &mut Context<Self>) {
+        self.subscribers.remove(&msg.addr);
+    }
+}
+
+impl Handler<PublishGame> for LobbyFeed {
+    type Result = ();
+
+    fn handle(&mut self, msg: PublishGame, _: &mut Context<Self>) {
+        let listing = msg.0;
+        let previous = self.games.insert(listing.id, listing.clone());
+        if previous.as_ref() != Some(&listing) {
+            self.broadcast(listing.id, previous.as_ref(), Some(&listing));
+        }
+    }
+}
+
+impl Handler<UnlistGame> for LobbyFeed {
+    type Result = ();
+
+    fn handle(&mut self, msg: UnlistGame, _: &mut Context<Self>) {
+        if let Some(previous) = self.games.remove(&msg.0) {
+            self.broadcast(msg.0, Some(&previous), None);
+        }
+    }
+}
+
+// Handler for SeedFeed message.
+//
+// Games published in the meantime are more recent than the seed
+impl Handler<SeedFeed> for LobbyFeed {
+    type Result = ();
+
+    fn handle(&mut self, msg: SeedFeed, _: &mut Context<Self>) {
+        for listing in msg.0 {
+            self.games.entry(listing.id).or_insert(listing);
+        }
+    }
+}
+
+// Handler for ShutdownMessage message.
+//
+// Feed sessions close their connection (See feed_session::WsFeedSession)
+impl Handler<ShutdownMessage> for LobbyFeed {
+    type Result = ();
+
+    fn handle(&mut self, _: ShutdownMessage, _: &mut Context<Self>) {
+        for subscriber in self.subscribers.keys() {
+            let _ = subscriber.do_send(FeedMessage::Shutdown);
+        }
+        self.subscribers.clear();
+    }
+}
@@ -0,0 +1,160 @@
+use super::codec::{Codec, Frame};
+use super::errors::{ErrorMessage, WebsocketError};
+use super::feed::LobbyFeed;
+use super::messages::{FeedClientMessage, FeedMessage, LobbyFilter, Subscribe, Unsubscribe};
+use crate::frontend::helper::log_error;
+use actix::prelude::*;
+use actix_web_actors::ws;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+// How often heartbeat pings are sent
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+// How long before lack of client response causes a timeout
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Session following the open games (e.g. games/overview.html)
+pub struct WsFeedSession {
+    pub hb: Instant,
+    // Lobby feed
+    pub addr: Addr<LobbyFeed>,
+    // games the client is interested in
+    pub filter: LobbyFilter,
+    // negotiated protocol version (See messages::negotiate_version)
+    pub version: u16,
+    // negotiated encoding of messages
+    pub codec: Codec,
+}
+
+impl Actor for WsFeedSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hb(ctx);
+
+        self.send(
+            &FeedMessage::Hello {
+                version: self.version,
+                encoding: self.codec,
+            },
+            ctx,
+        );
+        self.subscribe(ctx);
+    }
+
+    fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
+        self.addr.do_send(Unsubscribe {
+            addr: ctx.address().recipient(),
+        });
+
+        Running::Stop
+    }
+}
+
+// Handle messages from the lobby feed, we simply send it to peer websocket
+impl Handler<FeedMessage> for WsFeedSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: FeedMessage, ctx: &mut Self::Context) {
+        self.send(&msg, ctx);
+
+        if let FeedMessage::Shutdown = msg {
+            ctx.close(Some(ws::CloseReason {
+                code: ws::CloseCode::Restart,
+                description: Some("Server restarting".to_owned()),
+            }));
+            ctx.stop();
+        }
+    }
+}
+
+// WebSocket message handler
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsFeedSession {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        let msg = match msg {
+            Err(_) => return ctx.stop(),
+            Ok(msg) => msg,
+        };
+
+        match msg {
+            ws::Message::Ping(msg) => {
+                self.hb = Instant::now();
+                ctx.pong(&msg);
+            }
+            ws::Message::Pong(_) => {
+                self.hb = Instant::now();
+            }
+            ws::Message::Text(text) => {
+                let message = serde_json::from_str::<FeedClientMessage>(&text)
+                    .map_err(|_| WebsocketError::MessageFormatError());
+                self.handle_message(message, ctx);
+            }
+            ws::Message::Binary(data) => {
+                let message = self.codec.decode::<FeedClientMessage>(&data);
+                self.handle_message(message, ctx);
+            }
+            ws::Message::Close(reason) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            ws::Message::Continuation(_) => {
+                self.send_error(WebsocketError::UnimplementedError(), ctx);
+                ctx.stop();
+            }
+            ws::Message::Nop => (),
+        }
+    }
+}
+
+impl WsFeedSession {
+    fn handle_message(
+        &mut self,
+        message: Result<FeedClientMessage, WebsocketError>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        match message {
+            Ok(FeedClientMessage::Filter(filter)) => {
+                self.filter = filter;
+                self.subscribe(ctx);
+            }
+            Err(why) => self.send_error(why, ctx),
+        }
+    }
+
+    // (re)subscribe with the current filter. The feed answers with the matching games
+    fn subscribe(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr.do_send(Subscribe {
+            addr: ctx.address().recipient(),
+            filter: self.filter.clone(),
+        });
+    }
+
+    // encode message with the negotiated codec and send it to the client
+    fn send<T: Serialize>(&self, message: &T, ctx: &mut ws::WebsocketContext<Self>) {
+        match self.codec.encode(message) {
+            Ok(Frame::Text(text)) => ctx.text(text),
+            Ok(Frame::Binary(data)) => ctx.binary(data),
+            Err(why) => log_error(
+                "[Session Error]",
+                format!("Failed to encode message. Description: {}", why),
+            ),
+        }
+    }
+
+    fn send_error(&self, error: WebsocketError, ctx: &mut ws::WebsocketContext<Self>) {
+        self.send(&FeedMessage::Error(ErrorMessage::from(&error)), ctx);
+    }
+
+    // helper method that sends ping to client every second and checks heartbeats from client
+    fn hb(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                // stopping unsubscribes from the feed
+                ctx.stop();
+                return;
+            }
+
+            ctx.ping(b"");
+        });
+    }
+}
@@ -1,63 +1,502 @@
 // imports
-use super::errors::WebsocketError;
-use super::models::Game;
-use crate::graph::graph::ResizableGraphState;
-use crate::graph::models::MOVE;
+use super::clock::PlayerClock;
+use super::codec::Codec;
+use super::errors::{ErrorMessage, WebResult, WebsocketError};
+use super::models::{LobbyEntry, TurnKind};
+use crate::db::models::{ChatMessage, GameStatus, ResultReason};
+use crate::graph::graph::{Figure, ResizableGraphState};
+use crate::graph::models::{FIELD, MOVE};
 use actix::prelude::*;
-use dashmap::DashMap;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use strum::{EnumVariantNames, VariantNames};
 use uuid::Uuid;
 
-// message and relative deserializer for client and server -> session messages
-#[derive(Message, Serialize, Deserialize)]
+/*
+Protocol
+---
+Messages are JSON objects tagged by `action` with the payload (if any) in `data`:
+{"action": "make_move", "data": {"figure": 1, "move": [0, 0, 0, 5, 0, 0]}}
+
+The protocol version is negotiated when connecting (/games/ws/{id}?version={version}).
+Clients may additionally choose a binary encoding of the same messages (See codec::Codec).
+Broadcast messages carry a per-game sequence number (See GameEvent).
+Moves may carry a client chosen request id next to `action` (See ClientRequest):
+{"action": "make_move", "id": "{uuid}", "data": {...}} Dropped sessions resume
+with /games/ws/{id}?version={version}&resume={token}&seq={last received seq}.
+Open games are pushed by the lobby feed (/games/feed?version={version}, See FeedMessage).
+Bump PROTOCOL_VERSION on every breaking change to ClientMessage or ServerMessage and
+regenerate the TypeScript codes with `pentagame-online protocol`.
+*/
+pub const PROTOCOL_VERSION: u16 = 1;
+// versions the server is able to speak (oldest first)
+pub const SUPPORTED_VERSIONS: [u16; 1] = [1];
+
+// check version requested by client
+pub fn negotiate_version(requested: Option<&str>) -> WebResult<u16> {
+    let requested = match requested {
+        Some(raw) => raw.parse::<u16>().map_err(|_| {
+            WebsocketError::VersionError(format!("'{}' is not a valid protocol version", raw))
+        })?,
+        None => {
+            return Err(WebsocketError::VersionError(format!(
+                "Missing protocol version. Connect with ?version={}",
+                PROTOCOL_VERSION
+            )));
+        }
+    };
+
+    if SUPPORTED_VERSIONS.contains(&requested) {
+        Ok(requested)
+    } else {
+        Err(WebsocketError::VersionError(format!(
+            "Protocol version {} isn't supported. Supported versions: {:?}",
+            requested, SUPPORTED_VERSIONS
+        )))
+    }
+}
+
+// client -> session messages
+#[derive(Deserialize, Debug, EnumVariantNames, JsonSchema)]
+#[serde(tag = "action", content = "data", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ClientMessage {
+    // fetch current board
+    FetchMoves,
+    GetMetadata,
+    MakeMove {
+        figure: u8,
+        #[serde(rename = "move")]
+        action: [i16; 6],
+    },
+    // forced after beating a black stopper or reaching a corner (See ServerMessage::Turn)
+    PlaceStopper {
+        figure: u8,
+        field: FIELD,
+    },
+    // running or paused games only
+    Resign,
+    // the game ends in a draw once every remaining player offered or accepted it
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    // lobby only. Running games are left by resigning
+    LeaveGame,
+    // lobby only. Changing seat or icon resets the ready check
+    ChooseSeat {
+        seat: u8,
+    },
+    ChooseIcon {
+        icon: String,
+    },
+    Ready {
+        ready: bool,
+    },
+    // host only. Requires every player to be seated and ready
+    StartGame,
+    // host only. Aborts the game
+    StopGame {
+        #[serde(default)]
+        message: Option<String>,
+    },
+    // host only
+    PauseGame,
+    // host only
+    ResumeGame,
+    // host only
+    AssignTeam {
+        user: Uuid,
+        team: u8,
+    },
+    Chat {
+        message: String,
+    },
+    // host only
+    MuteChat {
+        user: Uuid,
+        muted: bool,
+    },
+    // host only (lobby). Banned players can't join the game again
+    Kick {
+        user: Uuid,
+        #[serde(default)]
+        ban: bool,
+    },
+    // host only
+    TransferHost {
+        user: Uuid,
+    },
+    // host only. Locked lobbies don't accept new players
+    LockLobby {
+        locked: bool,
+    },
+}
+
+// client -> session message with an optional request id. Moves with an id are answered with
+// ServerMessage::Ack or ServerMessage::Rejected and applied once, even when resent after a reconnect
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct ClientRequest {
+    #[serde(default)]
+    pub id: Option<Uuid>,
+    #[serde(flatten)]
+    pub message: ClientMessage,
+}
+
+// read-only subset of ClientMessage available to spectators
+#[derive(Deserialize, Debug, EnumVariantNames, JsonSchema)]
+#[serde(tag = "action", content = "data", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SpectatorMessage {
+    FetchMoves,
+    GetMetadata,
+}
+
+// session/ game room -> client messages
+#[derive(Serialize, Clone, Debug, EnumVariantNames, JsonSchema)]
+#[serde(tag = "action", content = "data", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ServerMessage {
+    // first message of every connection. Events with a sequence number above `seq` are new to
+    // this session. `token` resumes the session within the grace window (See actor::RESUME_GRACE)
+    Hello {
+        version: u16,
+        encoding: Codec,
+        // not set for spectators
+        token: Option<Uuid>,
+        seq: u64,
+        // missed events are replayed after this message
+        resumed: bool,
+    },
+    Moves(ResizableGraphState),
+    Metadata(QueryGameResponse),
+    // the session fell too far behind and skipped events. Replaces the state of the client
+    Resync {
+        metadata: QueryGameResponse,
+        moves: ResizableGraphState,
+    },
+    PlayerJoined {
+        user: Uuid,
+        name: String,
+    },
+    MoveMade {
+        user: Uuid,
+        figure: String,
+        #[serde(rename = "move")]
+        action: [i16; 6],
+        // clocks of timed games (See clock::Clocks)
+        clocks: Vec<PlayerClock>,
+    },
+    // {user} has to act next. Forced stopper placements stay with the player that moved
+    Turn {
+        user: Uuid,
+        kind: TurnKind,
+        clocks: Vec<PlayerClock>,
+    },
+    StopperPlaced {
+        user: Uuid,
+        figure: String,
+        field: FIELD,
+        clocks: Vec<PlayerClock>,
+    },
+    PlayerLeft {
+        user: Uuid,
+    },
+    // connection lost. The player may still reconnect within the grace window
+    PlayerDisconnected {
+        user: Uuid,
+    },
+    PlayerReconnected {
+        user: Uuid,
+    },
+    // number of spectators changed
+    Spectators {
+        count: usize,
+    },
+    // lifecycle transition (See db::models::GameStatus). `message` is given by the host when stopping
+    Status {
+        status: GameStatus,
+        message: Option<String>,
+    },
+    PlayerWon {
+        user: Uuid,
+    },
+    // resigned or abandoned. Partners in team games are eliminated together
+    PlayerEliminated {
+        user: Uuid,
+        reason: ResultReason,
+    },
+    DrawOffered {
+        user: Uuid,
+    },
+    DrawDeclined {
+        user: Uuid,
+    },
+    // final result. No winners for draws and games abandoned by everyone
+    GameOver {
+        winners: Vec<Uuid>,
+        reason: ResultReason,
+    },
+    TeamAssigned {
+        user: Uuid,
+        team: u8,
+    },
+    // lobby choices of a player changed
+    Lobby(LobbyEntry),
+    Chat(ChatEntry),
+    // latest chat messages for new sessions
+    ChatHistory(Vec<ChatEntry>),
+    ChatMuted {
+        user: Uuid,
+        muted: bool,
+    },
+    // the session of a kicked player is closed after this event
+    PlayerKicked {
+        user: Uuid,
+        banned: bool,
+    },
+    HostChanged {
+        user: Uuid,
+    },
+    LobbyLocked {
+        locked: bool,
+    },
+    // a newer session of the player took over (See models::MAX_SESSIONS). The connection is
+    // closed after this event
+    Evicted,
+    // the server shuts down in {seconds}. Running games continue after the restart
+    ShutdownNotice {
+        seconds: u64,
+    },
+    // the server shuts down now. The connection is closed after this event
+    Shutdown,
+    // the move request {id} was applied. Resent requests applied before are marked `duplicate`
+    Ack {
+        id: Uuid,
+        duplicate: bool,
+    },
+    // the move request {id} was refused
+    Rejected {
+        id: Uuid,
+        error: ErrorMessage,
+    },
+    Error(ErrorMessage),
+}
+
+#[derive(Serialize, Clone, Debug, JsonSchema)]
+pub struct ChatEntry {
+    pub user: Uuid,
+    pub message: String,
+    // unix timestamp
+    pub sent: i64,
+}
+
+impl From<ChatMessage> for ChatEntry {
+    fn from(message: ChatMessage) -> ChatEntry {
+        ChatEntry {
+            user: message.user_id,
+            message: message.message,
+            sent: message.sent,
+        }
+    }
+}
+
+// open game as listed by the lobby feed (See feed::LobbyFeed)
+#[derive(Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GameListing {
+    pub id: i32,
+    pub name: String,
+    pub icon: String,
+    // private games require the pin to join
+    pub private: bool,
+    pub players: usize,
+    pub status: GameStatus,
+}
+
+// games a lobby feed session is interested in. Unset fields match every game
+#[derive(Deserialize, Clone, Debug, Default, JsonSchema)]
+pub struct LobbyFilter {
+    pub private: Option<bool>,
+    pub min_players: Option<usize>,
+    pub max_players: Option<usize>,
+}
+
+impl LobbyFilter {
+    pub fn matches(&self, listing: &GameListing) -> bool {
+        self.private
+            .map_or(true, |private| listing.private == private)
+            && self.min_players.map_or(true, |min| listing.players >= min)
+            && self.max_players.map_or(true, |max| listing.players <= max)
+    }
+}
+
+// lobby feed client -> session messages
+#[derive(Deserialize, Debug, EnumVariantNames, JsonSchema)]
+#[serde(tag = "action", content = "data", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum FeedClientMessage {
+    // replace the filter. The matching games are sent again
+    Filter(LobbyFilter),
+}
+
+// lobby feed -> client messages
+#[derive(Message, Serialize, Clone, Debug, EnumVariantNames, JsonSchema)]
+#[rtype(result = "()")]
+#[serde(tag = "action", content = "data", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum FeedMessage {
+    Hello { version: u16, encoding: Codec },
+    // all open games matching the filter (newest first)
+    Games(Vec<GameListing>),
+    // a game matching the filter was created or changed
+    GameListed(GameListing),
+    // a game is over or doesn't match the filter anymore
+    GameUnlisted { id: i32 },
+    // the server shuts down now. The connection is closed after this event
+    Shutdown,
+    Error(ErrorMessage),
+}
+
+// broadcast message of a game with its per-game sequence number
+#[derive(Message, Serialize, Clone, Debug, JsonSchema)]
 #[rtype(result = "()")]
-pub struct SessionMessage {
-    /*
-    | action | description                  | data                 |
-    | ------ | ---------------------------- | -------------------- |
-    | 0      | {user} joined room           | {"user": {user}}     |
-    | 1      | {user} made move             | {                    |
-    |        |                              |  "user": {user},     |
-    |        |                              |  "move": String      |
-    |        |                              | }                    |
-    | 2      | {user} needs to place figure | {"user": {user}}     |
-    | 3      | {user} placed figure         | {                    |
-    |        |                              |  "user": {user},     |
-    |        |                              |  "move": String      |
-    |        |                              | }                    |
-    | 4      | {user} disconnected          | {"user": {user}}     |
-    | 5      | Login                        | {                    |
-    |        |                              |  "name": String,     |
-    |        |                              |  "password": String, |
-    |        |                              | }                    |
-
-    Login is bound to websocket as cookie so no logout action required
-    */
-    pub action: u8,
-    pub data: DashMap<String, String>,
-}
-
-// Messages for session -> game server communications
-#[derive(Message)]
-#[rtype(result = "Result<(Game, Vec<(Uuid, String)>), WebsocketError>")]
-pub struct QueryGameMessage {
-    pub game: Game,
+pub struct GameEvent {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub message: ServerMessage,
+}
+
+#[derive(Serialize, Clone, Debug, JsonSchema)]
+pub struct QueryGameResponse {
+    pub name: String,
+    pub description: String,
+    pub icon: String,
     pub players: Vec<(Uuid, String)>,
+    // turn (See models::Turn)
+    pub state: u8,
+    pub status: GameStatus,
+    // seats, icons and ready checks in player order
+    pub lobby: Vec<LobbyEntry>,
+    pub host: Uuid,
+    pub locked: bool,
+    // resigned or abandoned players
+    pub eliminated: Vec<Uuid>,
+    // players with a pending draw offer
+    pub draw_offers: Vec<Uuid>,
+    pub clocks: Vec<PlayerClock>,
+    pub pin: Vec<i16>,
+    pub spectators: usize,
+}
+
+// TypeScript enums for the action tags (static/ts/interfaces/protocol.ts)
+pub fn typescript_protocol() -> String {
+    fn codes(name: &str, variants: &[&str]) -> String {
+        let members: Vec<String> = variants
+            .iter()
+            .map(|variant| format!("  {} = '{}',", variant.to_uppercase(), variant))
+            .collect();
+        format!("export enum {} {{\n{}\n}}\n", name, members.join("\n"))
+    }
+
+    format!(
+        "// protocol.ts - part of pentagame online under GPLv3.0 @ cobalt\n// Generated by `pentagame-online protocol`. Don't edit manually\n\nexport const PROTOCOL_VERSION = {};\n\n{}\n{}\n{}\n{}",
+        PROTOCOL_VERSION,
+        codes("REQUEST_CODES", ClientMessage::VARIANTS),
+        codes("RESPONSE_CODES", ServerMessage::VARIANTS),
+        codes("FEED_REQUEST_CODES", FeedClientMessage::VARIANTS),
+        codes("FEED_RESPONSE_CODES", FeedMessage::VARIANTS)
+    )
 }
 
+// Messages for session -> game room communications
+#[derive(Message)]
+#[rtype(result = "Result<QueryGameResponse, WebsocketError>")]
+pub struct QueryGameMessage;
+
 #[derive(Message)]
 #[rtype(result = "Result<ResizableGraphState, WebsocketError>")]
-pub struct QueryMovesMessage {
-    pub gid: i32,
-}
+pub struct QueryMovesMessage;
 
 #[derive(Message)]
 #[rtype(result = "Result<(), WebsocketError>")]
 pub struct StartGameMessage {
-    pub gid: i32,
     pub uid: Uuid,
 }
 
+#[derive(Debug)]
+pub enum LobbyChange {
+    Seat(u8),
+    Icon(String),
+    Ready(bool),
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), WebsocketError>")]
+pub struct UpdateLobbyMessage {
+    pub uid: Uuid,
+    pub change: LobbyChange,
+}
+
+#[derive(Debug)]
+pub enum HostAction {
+    Kick { player: Uuid, ban: bool },
+    TransferHost(Uuid),
+    LockLobby(bool),
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), WebsocketError>")]
+pub struct HostActionMessage {
+    pub uid: Uuid,
+    pub action: HostAction,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), WebsocketError>")]
+pub struct ResignMessage {
+    pub uid: Uuid,
+}
+
+// leave a lobby. Running games are left by resigning
+#[derive(Message)]
+#[rtype(result = "Result<(), WebsocketError>")]
+pub struct LeaveGameMessage {
+    pub uid: Uuid,
+}
+
+#[derive(Debug)]
+pub enum DrawAction {
+    Offer,
+    Accept,
+    Decline,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), WebsocketError>")]
+pub struct DrawMessage {
+    pub uid: Uuid,
+    pub action: DrawAction,
+}
+
+// pause, resume or stop (abort) a game
+#[derive(Message)]
+#[rtype(result = "Result<(), WebsocketError>")]
+pub struct ChangeStatusMessage {
+    pub uid: Uuid,
+    pub status: GameStatus,
+    pub message: Option<String>,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), WebsocketError>")]
+pub struct AssignTeamMessage {
+    // host id
+    pub uid: Uuid,
+    // player to assign
+    pub player: Uuid,
+    pub team: u8,
+}
+
+// Returns false for resent requests that were applied before
 #[derive(Message)]
 #[rtype(result = "Result<bool, WebsocketError>")]
 pub struct MakeMoveMessage {
@@ -65,38 +504,130 @@ pub struct MakeMoveMessage {
     pub uid: Uuid,
     // move to make/ validate
     pub action: MOVE,
-    // related game id
-    pub gid: i32,
+    // client request id (See ClientRequest)
+    pub request: Option<Uuid>,
+}
+
+// token and last received event of a dropped session
+#[derive(Clone, Copy, Debug)]
+pub struct Resume {
+    pub token: Uuid,
+    pub seq: u64,
+}
+
+// Returns false for resent requests that were applied before
+#[derive(Message)]
+#[rtype(result = "Result<bool, WebsocketError>")]
+pub struct PlaceStopperMessage {
+    pub uid: Uuid,
+    pub figure: Figure,
+    pub field: FIELD,
+    // client request id (See ClientRequest)
+    pub request: Option<Uuid>,
+}
+
+#[derive(Message)]
+#[rtype(result = "Result<(), WebsocketError>")]
+pub struct SendChatMessage {
+    pub uid: Uuid,
+    pub message: String,
 }
 
-// New game session is created
 #[derive(Message)]
 #[rtype(result = "Result<(), WebsocketError>")]
+pub struct MuteChatMessage {
+    // host id
+    pub uid: Uuid,
+    // player to (un)mute
+    pub player: Uuid,
+    pub muted: bool,
+}
+
+// New game session is created
+#[derive(Message)]
+#[rtype(result = "Result<Welcome, WebsocketError>")]
 pub struct Connect {
     // session id (== user id)
-    pub addr: Recipient<SessionMessage>,
+    pub addr: Recipient<GameEvent>,
     pub uid: Uuid,
+    pub resume: Option<Resume>,
+}
+
+pub struct Welcome {
+    // token for resuming this session
+    pub token: Uuid,
+    // last event before the session joined
+    pub seq: u64,
+    pub resumed: bool,
+    // chat history (See models::CHAT_HISTORY_SIZE)
+    pub chat: Vec<ChatEntry>,
+}
+
+// New spectator session is created. Returns the latest event and chat history of the game
+#[derive(Message)]
+#[rtype(result = "Result<(u64, Vec<ChatEntry>), WebsocketError>")]
+pub struct SpectatorConnect {
+    pub addr: Recipient<GameEvent>,
+}
+
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SpectatorDisconnect {
+    pub addr: Recipient<GameEvent>,
 }
 
 // Session is disconnected
 #[derive(Message)]
 #[rtype(result = "Result<(), WebsocketError>")]
 pub struct Disconnect {
-    pub gid: i32,
     pub uid: Uuid,
-    pub addr: Recipient<SessionMessage>,
+    pub addr: Recipient<GameEvent>,
+}
+
+// Session worked through its backlog up to a resync (See ServerMessage::Resync)
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ResyncedMessage {
+    pub addr: Recipient<GameEvent>,
+}
+
+// Announce the shutdown of the server (See state::Rooms::notice_shutdown)
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct NoticeShutdownMessage {
+    pub seconds: u64,
 }
 
-// Messages (serialized data) from session/ server -> client
-#[derive(Serialize, Clone, Debug)]
-pub struct ServerMessage<G> {
-    pub action: u8,
-    pub data: G,
+// Store the room's state and close its sessions before the server stops
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ShutdownMessage;
+
+// Messages for lobby feed communications (See feed::LobbyFeed)
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Subscribe {
+    pub addr: Recipient<FeedMessage>,
+    pub filter: LobbyFilter,
 }
 
-// Messages (non generic - serialized data) from session -> client
-#[derive(Serialize, Clone, Debug)]
-pub struct SimpleServerMessage<'a> {
-    pub action: u8,
-    pub data: DashMap<&'a str, String>,
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Unsubscribe {
+    pub addr: Recipient<FeedMessage>,
 }
+
+// game was created or changed
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct PublishGame(pub GameListing);
+
+// game is over or was removed
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct UnlistGame(pub i32);
+
+// open games of the database. The feed is seeded once, rooms and routes keep it up to date
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SeedFeed(pub Vec<GameListing>);
@@ -0,0 +1,42 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// delivery counters shared by all game rooms (See actor::GameRoom::deliver)
+#[derive(Default)]
+pub struct Metrics {
+    // events that didn't fit into the outbound queue of a session
+    dropped: AtomicU64,
+    // events skipped while a session waited for its resync
+    coalesced: AtomicU64,
+    // full states sent to sessions that fell behind
+    resyncs: AtomicU64,
+}
+
+#[derive(Serialize)]
+pub struct MetricsSnapshot {
+    pub dropped: u64,
+    pub coalesced: u64,
+    pub resyncs: u64,
+}
+
+impl Metrics {
+    pub fn dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn coalesced(&self) {
+        self.coalesced.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn resynced(&self) {
+        self.resyncs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            dropped: self.dropped.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            resyncs: self.resyncs.load(Ordering::Relaxed),
+        }
+    }
+}
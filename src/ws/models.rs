@@ -1,10 +1,474 @@
+use super::clock::Clocks;
+use super::messages::{ChatEntry, GameEvent, GameListing, ServerMessage};
+use crate::db::models::{Game as GameRecord, GameStatus, UserGame};
+use crate::graph::rules::{RuleSet, Teams};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+// How many events of a game are kept for replaying them to reconnecting sessions
+pub const EVENT_LOG_SIZE: usize = 256;
+// How many chat messages new sessions receive
+pub const CHAT_HISTORY_SIZE: usize = 50;
+// Maximum length of chat messages (characters)
+pub const CHAT_MAX_LENGTH: usize = 500;
+// Players needed to start a game
+pub const MIN_PLAYERS: usize = 2;
+// Seats of a game (one figure set per seat)
+pub const MAX_PLAYERS: usize = 5;
+// Sessions (tabs or devices) a player may have open per game. The oldest makes room for new ones
+pub const MAX_SESSIONS: usize = 3;
+// Piece icons (bootstrap icons) players can choose from in the lobby
+pub const PIECE_ICONS: [&str; 8] = [
+    "bi-circle-fill",
+    "bi-square-fill",
+    "bi-triangle-fill",
+    "bi-diamond-fill",
+    "bi-pentagon-fill",
+    "bi-hexagon-fill",
+    "bi-star-fill",
+    "bi-heart-fill",
+];
+// How many applied move requests are remembered per player. Clients only resend their latest ones
+pub const REQUEST_WINDOW: usize = 16;
+// Window for the per-session chat rate limit (config::DEFAULT_RATE_LIMIT messages per window)
+pub const CHAT_RATE_WINDOW: Duration = Duration::from_secs(60);
+// Failed admin logins allowed per window before further attempts are refused
+pub const ADMIN_ATTEMPTS: usize = 5;
+pub const ADMIN_ATTEMPT_WINDOW: Duration = Duration::from_secs(300);
+
 pub struct Game {
     pub id: i32,
     pub name: String,
     pub description: String,
     pub pin: [u8; 6],
+    // joining requires the pin
+    pub private: bool,
     pub host: Uuid,
     pub icon: String,
+    pub status: GameStatus,
+    // locked lobbies don't accept new players
+    pub locked: bool,
+    pub rules: RuleSet,
+    // fingerprints of all earlier board positions (positional ko)
+    pub history: Vec<u64>,
+    // time left of every player (set up on start, See clock::Clocks)
+    pub clocks: Clocks,
+    // players in join order and in seat order once started. The index is the player's figure set
+    pub players: Vec<Uuid>,
+    // seat, piece icon and ready check chosen in the lobby
+    pub lobby: HashMap<Uuid, LobbyEntry>,
+    // team assignment for partnership games
+    pub teams: HashMap<Uuid, u8>,
+    // broadcast events (sequence numbers and replay)
+    pub events: EventLog,
+    // resume tokens of the players' sessions (token -> player)
+    pub resume_tokens: HashMap<Uuid, Uuid>,
+    // players that lost their connection and since when
+    pub disconnected: HashMap<Uuid, Instant>,
+    // latest chat messages (See CHAT_HISTORY_SIZE)
+    pub chat: VecDeque<ChatEntry>,
+    // players muted by the host
+    pub muted: HashSet<Uuid>,
+    // a gray stopper needs to be placed after the current black stopper
+    pub pending_gray: bool,
+    // players that resigned or abandoned the game. Their seats are skipped
+    pub eliminated: HashSet<Uuid>,
+    // players that offered (or accepted) a draw
+    pub draw_offers: HashSet<Uuid>,
+    // latest applied move requests of every player (See REQUEST_WINDOW). Resent requests aren't
+    // applied again
+    pub requests: HashMap<Uuid, VecDeque<Uuid>>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LobbyEntry {
+    pub user: Uuid,
+    pub seat: Option<u8>,
+    pub icon: Option<String>,
+    pub ready: bool,
+}
+
+/*
+Turn encoding of the game state (GameRoom::turn) as used by the client
+---
+| state | turn                                   |
+| ----- | -------------------------------------- |
+| 0     | game isn't running                     |
+| 1-5   | move by seat {state - 1}               |
+| 6-10  | black stopper by seat {state - 6}      |
+| 11-15 | gray stopper by seat {state - 11}      |
+| 16    | game finished                          |
+
+Seats are the player indices (See Game::players)
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Turn {
+    Lobby,
+    Move(u8),
+    BlackStopper(u8),
+    GrayStopper(u8),
+    Finished,
+}
+
+// kind of the forced action of a turn (See ServerMessage::Turn)
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnKind {
+    Move,
+    BlackStopper,
+    GrayStopper,
+}
+
+// fixed window rate limit
+#[derive(Clone)]
+pub struct RateLimit {
+    limit: usize,
+    window: Duration,
+    start: Instant,
+    count: usize,
+}
+
+// bounded log of the latest broadcast events of a game
+#[derive(Default)]
+pub struct EventLog {
+    seq: u64,
+    events: VecDeque<GameEvent>,
+}
+
+impl Game {
+    // in-memory game for a record loaded on the first connect
+    pub fn from_record(record: &GameRecord, host: Uuid, icon: String, pin: Vec<i16>) -> Game {
+        let private = !pin.is_empty();
+        let mut digits = [0_u8; 6];
+        for (digit, value) in digits.iter_mut().zip(pin) {
+            *digit = value as u8;
+        }
+
+        Game {
+            id: record.id,
+            name: record.name.clone(),
+            description: record.description.clone().unwrap_or_default(),
+            pin: digits,
+            private,
+            host,
+            icon,
+            status: record.status(),
+            locked: record.locked,
+            rules: record.rules(),
+            history: Vec::new(),
+            clocks: Clocks::new(record.rules().time_control(), &[]),
+            players: Vec::new(),
+            lobby: HashMap::new(),
+            teams: HashMap::new(),
+            events: EventLog::default(),
+            resume_tokens: HashMap::new(),
+            disconnected: HashMap::new(),
+            chat: VecDeque::with_capacity(CHAT_HISTORY_SIZE),
+            muted: HashSet::new(),
+            pending_gray: false,
+            eliminated: HashSet::new(),
+            draw_offers: HashSet::new(),
+            requests: HashMap::new(),
+        }
+    }
+
+    // seat after `seat` in seat order skipping eliminated players
+    pub fn next_seat(&self, seat: u8) -> u8 {
+        let count = self.players.len().max(1);
+        (1..=count)
+            .map(|offset| ((seat as usize + offset) % count) as u8)
+            .find(|next| !self.is_eliminated(*next))
+            .unwrap_or(seat)
+    }
+
+    pub fn is_eliminated(&self, seat: u8) -> bool {
+        self.players
+            .get(seat as usize)
+            .map_or(false, |player| self.eliminated.contains(player))
+    }
+
+    // players still in the game
+    pub fn remaining(&self) -> Vec<Uuid> {
+        self.players
+            .iter()
+            .filter(|player| !self.eliminated.contains(player))
+            .copied()
+            .collect()
+    }
+
+    // the player and their partners in team games
+    pub fn side(&self, uid: &Uuid) -> Vec<Uuid> {
+        match self.teams.get(uid) {
+            Some(team) if self.rules.teams => self
+                .players
+                .iter()
+                .filter(|player| self.teams.get(player) == Some(team))
+                .copied()
+                .collect(),
+            _ => vec![*uid],
+        }
+    }
+
+    // number of sides (players or teams) still in the game
+    pub fn remaining_sides(&self) -> usize {
+        let remaining = self.remaining();
+        if self.rules.teams {
+            remaining
+                .iter()
+                .filter_map(|player| self.teams.get(player))
+                .collect::<HashSet<_>>()
+                .len()
+        } else {
+            remaining.len()
+        }
+    }
+
+    pub fn push_chat(&mut self, entry: ChatEntry) {
+        if self.chat.len() == CHAT_HISTORY_SIZE {
+            self.chat.pop_front();
+        }
+        self.chat.push_back(entry);
+    }
+
+    // checks whether a move request of the player was applied before
+    pub fn applied(&self, uid: &Uuid, request: &Uuid) -> bool {
+        self.requests
+            .get(uid)
+            .map_or(false, |requests| requests.contains(request))
+    }
+
+    pub fn push_request(&mut self, uid: Uuid, request: Uuid) {
+        let requests = self.requests.entry(uid).or_default();
+        if requests.len() == REQUEST_WINDOW {
+            requests.pop_front();
+        }
+        requests.push_back(request);
+    }
+
+    // lobby entry of a player. Players that haven't chosen anything yet get an empty one
+    pub fn lobby_entry(&mut self, uid: Uuid) -> &mut LobbyEntry {
+        self.lobby.entry(uid).or_insert_with(|| LobbyEntry {
+            user: uid,
+            seat: None,
+            icon: None,
+            ready: false,
+        })
+    }
+
+    // entry of the lobby feed. Every joined player counts, connected or not
+    pub fn listing(&self) -> GameListing {
+        GameListing {
+            id: self.id,
+            name: self.name.clone(),
+            icon: self.icon.clone(),
+            private: self.private,
+            players: self.lobby.len(),
+            status: self.status,
+        }
+    }
+
+    // lobby entries in player order
+    pub fn lobby(&self) -> Vec<LobbyEntry> {
+        self.players
+            .iter()
+            .filter_map(|player| self.lobby.get(player).cloned())
+            .collect()
+    }
+
+    pub fn seat_taken(&self, seat: u8, uid: &Uuid) -> bool {
+        self.lobby
+            .values()
+            .any(|entry| entry.seat == Some(seat) && &entry.user != uid)
+    }
+
+    pub fn icon_taken(&self, icon: &str, uid: &Uuid) -> bool {
+        self.lobby
+            .values()
+            .any(|entry| entry.icon.as_deref() == Some(icon) && &entry.user != uid)
+    }
+
+    // every joined player is seated and ready, connected or not
+    pub fn all_ready(&self) -> bool {
+        self.lobby
+            .values()
+            .all(|entry| entry.ready && entry.seat.is_some())
+    }
+
+    // players with an open connection (or still within the resume grace)
+    pub fn connected(&self) -> impl Iterator<Item = &Uuid> {
+        self.players
+            .iter()
+            .filter(move |player| !self.disconnected.contains_key(player))
+    }
+
+    // forget a player that left the lobby
+    pub fn remove_player(&mut self, uid: &Uuid) {
+        self.players.retain(|player| player != uid);
+        self.lobby.remove(uid);
+        self.teams.remove(uid);
+        self.resume_tokens.retain(|_, player| player != uid);
+        self.disconnected.remove(uid);
+    }
+
+    // every joined player in the order of their chosen seats. The turn order and figure sets
+    // follow this order
+    pub fn seat_players(&mut self) {
+        let mut players: Vec<Uuid> = self.lobby.keys().copied().collect();
+        let lobby = &self.lobby;
+        players.sort_by_key(|player| lobby.get(player).and_then(|entry| entry.seat));
+        self.players = players;
+    }
+
+    pub fn player_index(&self, uid: &Uuid) -> Option<u8> {
+        self.players
+            .iter()
+            .position(|player| player == uid)
+            .map(|index| index as u8)
+    }
+
+    // team assignment in player order as required by the engine
+    pub fn teams(&self) -> Teams {
+        if self.rules.teams {
+            Teams(
+                self.players
+                    .iter()
+                    .map(|player| self.teams.get(player).copied())
+                    .collect(),
+            )
+        } else {
+            Teams::default()
+        }
+    }
+}
+
+impl From<&UserGame> for LobbyEntry {
+    fn from(record: &UserGame) -> LobbyEntry {
+        LobbyEntry {
+            user: record.user_id,
+            seat: record.seat.map(|seat| seat as u8),
+            icon: record.icon.clone(),
+            ready: record.ready,
+        }
+    }
+}
+
+impl EventLog {
+    // log continuing a snapshot's sequence. Earlier events can't be replayed anymore
+    pub fn restore(seq: u64) -> EventLog {
+        EventLog {
+            seq,
+            events: VecDeque::with_capacity(EVENT_LOG_SIZE),
+        }
+    }
+
+    // sequence number of the latest event (0 == no events yet)
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn push(&mut self, message: ServerMessage) -> GameEvent {
+        self.seq += 1;
+        let event = GameEvent {
+            seq: self.seq,
+            message,
+        };
+
+        if self.events.len() == EVENT_LOG_SIZE {
+            self.events.pop_front();
+        }
+        self.events.push_back(event.clone());
+
+        event
+    }
+
+    // events after `seq`. None if some of them were already dropped from the log
+    pub fn since(&self, seq: u64) -> Option<Vec<GameEvent>> {
+        if seq > self.seq {
+            return None;
+        }
+
+        match self.events.front() {
+            Some(oldest) if oldest.seq > seq + 1 => None,
+            // restored logs start without the earlier events (See EventLog::restore)
+            None if seq < self.seq => None,
+            _ => Some(
+                self.events
+                    .iter()
+                    .filter(|event| event.seq > seq)
+                    .cloned()
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Turn {
+    // seat and action of the player that has to act
+    pub fn active(&self) -> Option<(u8, TurnKind)> {
+        match *self {
+            Turn::Move(seat) => Some((seat, TurnKind::Move)),
+            Turn::BlackStopper(seat) => Some((seat, TurnKind::BlackStopper)),
+            Turn::GrayStopper(seat) => Some((seat, TurnKind::GrayStopper)),
+            Turn::Lobby | Turn::Finished => None,
+        }
+    }
+}
+
+impl From<u8> for Turn {
+    fn from(state: u8) -> Turn {
+        match state {
+            0 => Turn::Lobby,
+            1..=5 => Turn::Move(state - 1),
+            6..=10 => Turn::BlackStopper(state - 6),
+            11..=15 => Turn::GrayStopper(state - 11),
+            _ => Turn::Finished,
+        }
+    }
+}
+
+impl From<Turn> for u8 {
+    fn from(turn: Turn) -> u8 {
+        match turn {
+            Turn::Lobby => 0,
+            Turn::Move(seat) => seat + 1,
+            Turn::BlackStopper(seat) => seat + 6,
+            Turn::GrayStopper(seat) => seat + 11,
+            Turn::Finished => 16,
+        }
+    }
+}
+
+impl RateLimit {
+    pub fn new(limit: usize, window: Duration) -> RateLimit {
+        RateLimit {
+            limit,
+            window,
+            start: Instant::now(),
+            count: 0,
+        }
+    }
+
+    // count one action. Returns false if the limit of the current window is exceeded
+    pub fn check(&mut self) -> bool {
+        if self.start.elapsed() >= self.window {
+            self.start = Instant::now();
+            self.count = 0;
+        }
+
+        self.count += 1;
+        self.count <= self.limit
+    }
+
+    // the limit of the current window is used up. Doesn't count as action
+    pub fn exceeded(&self) -> bool {
+        !self.expired() && self.count >= self.limit
+    }
+
+    // the current window is over
+    pub fn expired(&self) -> bool {
+        self.start.elapsed() >= self.window
+    }
 }
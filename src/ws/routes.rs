@@ -1,61 +1,235 @@
 use crate::auth::User;
+use crate::config::DEFAULT_RATE_LIMIT;
+use crate::db::actions::{get_game_record, is_player, remove_user_game};
+use crate::db::models::GameStatus;
+use crate::frontend::errors::UserError;
 use crate::frontend::routes::UserResponse;
-use crate::state::AppState;
-use crate::ws::{actor::GameServer, session::WsGameSession};
-use actix::prelude::*;
-use actix_web::{web::block, web::Data, web::HttpResponse, web::Payload, HttpRequest};
+use crate::state::{AppState, Rooms};
+use crate::ws::codec::Codec;
+use crate::ws::errors::{WebResult, WebsocketError};
+use crate::ws::feed::LobbyFeed;
+use crate::ws::messages::{negotiate_version, LeaveGameMessage, LobbyFilter, Resume, SeedFeed};
+use crate::ws::models::{RateLimit, CHAT_RATE_WINDOW};
+use crate::ws::schema::PROTOCOL_SCHEMA;
+use crate::ws::{
+    feed_session::WsFeedSession, session::WsGameSession, spectator::WsSpectatorSession,
+};
+use actix_web::{
+    web::block, web::Data, web::HttpResponse, web::Path, web::Payload, web::Query, HttpRequest,
+};
 use actix_web_actors::ws;
+use serde::Deserialize;
+use uuid::Uuid;
 use std::time::Instant;
 
+#[derive(Deserialize)]
+pub struct ProtocolQuery {
+    // requested protocol version (See messages::negotiate_version)
+    pub version: Option<String>,
+    // requested message encoding (See codec::Codec)
+    pub encoding: Option<String>,
+    // resume token and last received event of a dropped session
+    pub resume: Option<Uuid>,
+    pub seq: Option<u64>,
+}
+
+// connection of a player to one of their games. Players may be connected to several games at once
 pub async fn game_route(
     req: HttpRequest,
     stream: Payload,
-    srv: Data<Addr<GameServer>>,
+    rooms: Data<Rooms>,
     state: Data<AppState>,
+    pool: Data<DbPool>,
     id: Option<User>,
-) -> Result<HttpResponse, APIError> {
+    path: Path<(i32,)>,
+    query: Query<ProtocolQuery>,
+) -> WebResult<HttpResponse> {
+    // unknown versions are refused before upgrading the connection
+    let version = negotiate_version(query.version.as_deref())?;
+    let codec = Codec::negotiate(query.encoding.as_deref())?;
+    let resume = query.resume.map(|token| Resume {
+        token,
+        seq: query.seq.unwrap_or(0),
+    });
     let user = guard_api_with_user(id, &state)?;
+    let gid = path.into_inner().0;
 
     /*
     this checks if the user already joined the game explicitly as the user might be only reconnecting
     */
+    let conn = pool.get()?;
     let sacrifice = user.id.clone();
-    let result = block(move || get_user_game(&conn, sacrifice)).await?;
-
-    let gid = match result {
-        Some(id) => id,
-        // check if game exists and if exists => join game
-        None => {
-            return Err(APIError::AuthorizationError(
-                "You haven't joined this game. Consider visiting /game/view/{id} and checking out the game's data, if available.".to_owned()
-            ));
-        }
-    };
+    if !block(move || is_player(&conn, gid, sacrifice)).await? {
+        return Err(WebsocketError::ValidationError(
+            "You haven't joined this game. Consider visiting /game/view/{id} and checking out the game's data, if available.".to_owned()
+        ));
+    }
+
+    // sessions of all workers share the game's room
+    let conn = pool.get()?;
+    let rooms = rooms.get_ref().clone();
+    let room = block(move || rooms.open(&conn, gid)).await?;
 
     Ok(ws::start(
         WsGameSession {
             user,
             game: gid,
             hb: Instant::now(),
-            addr: srv.get_ref().clone(),
+            addr: room,
+            version,
+            codec,
+            resume,
+            chat: RateLimit::new(DEFAULT_RATE_LIMIT, CHAT_RATE_WINDOW),
         },
         &req,
         stream,
     )?)
 }
 
-pub async fn get_game_leave_route(
+#[derive(Deserialize)]
+pub struct SpectateQuery {
+    pub version: Option<String>,
+    pub encoding: Option<String>,
+    // required for private games
+    pub pin: Option<String>,
+}
+
+// read-only connection to a game. Anyone may watch public games, private games require the pin
+pub async fn spectator_route(
+    req: HttpRequest,
+    stream: Payload,
+    rooms: Data<Rooms>,
+    pool: Data<DbPool>,
+    path: Path<(i32,)>,
+    query: Query<SpectateQuery>,
+) -> WebResult<HttpResponse> {
+    let version = negotiate_version(query.version.as_deref())?;
+    let codec = Codec::negotiate(query.encoding.as_deref())?;
+    let gid = path.into_inner().0;
+
+    let conn = pool.get()?;
+    let pin = block(move || check_game(&conn, gid)).await?;
+    if let Some(pin) = pin {
+        let mut stringified = String::new();
+        pin.iter()
+            .for_each(|number| stringified.push_str(&number.to_string()));
+
+        if query.pin.as_deref() != Some(stringified.as_str()) {
+            return Err(WebsocketError::AuthorizationError());
+        }
+    }
+
+    // spectators can only watch games with connected players
+    let room = rooms.get(gid).ok_or_else(|| {
+        WebsocketError::ValidationError("Nobody is playing this game at the moment".to_owned())
+    })?;
+
+    Ok(ws::start(
+        WsSpectatorSession {
+            game: gid,
+            hb: Instant::now(),
+            addr: room,
+            version,
+            codec,
+        },
+        &req,
+        stream,
+    )?)
+}
+
+#[derive(Deserialize)]
+pub struct FeedQuery {
+    pub version: Option<String>,
+    pub encoding: Option<String>,
+    // initial filter. It can be changed later on (See messages::FeedClientMessage)
+    pub private: Option<bool>,
+    pub min_players: Option<usize>,
+    pub max_players: Option<usize>,
+}
+
+// live listing of the open games. Anyone may follow it
+pub async fn feed_route(
+    req: HttpRequest,
+    stream: Payload,
+    rooms: Data<Rooms>,
+    pool: Data<DbPool>,
+    query: Query<FeedQuery>,
+) -> WebResult<HttpResponse> {
+    let version = negotiate_version(query.version.as_deref())?;
+    let codec = Codec::negotiate(query.encoding.as_deref())?;
+
+    // the feed only knows about games changed since the start. Open games are loaded once
+    if rooms.claim_seed() {
+        let conn = pool.get()?;
+        match block(move || LobbyFeed::load(&conn)).await {
+            Ok(games) => rooms.lobby().do_send(SeedFeed(games)),
+            Err(why) => {
+                rooms.release_seed();
+                return Err(why.into());
+            }
+        }
+    }
+
+    Ok(ws::start(
+        WsFeedSession {
+            hb: Instant::now(),
+            addr: rooms.lobby(),
+            filter: LobbyFilter {
+                private: query.private,
+                min_players: query.min_players,
+                max_players: query.max_players,
+            },
+            version,
+            codec,
+        },
+        &req,
+        stream,
+    )?)
+}
+
+// leave a lobby. Running games are left by resigning (See messages::ClientMessage::Resign)
+pub async fn post_game_leave_route(
     id: Option<SlimUser>,
     pool: Data<DbPool>,
+    rooms: Data<Rooms>,
+    path: Path<(i32,)>,
     req: HttpRequest,
     state: Data<AppState>,
 ) -> UserResponse {
     let user = guard_with_user(&req, &state, id)?;
+    let gid = path.into_inner().0;
+
+    // open rooms hold the lobby and let the player go themselves
+    if let Some(room) = rooms.get(gid) {
+        return match room.send(LeaveGameMessage { uid: user.id }).await {
+            Ok(Ok(_)) => Ok(redirect("/games/mine".to_owned())),
+            Ok(Err(WebsocketError::ValidationError(why))) => Err(UserError::ValidationError(why)),
+            Ok(Err(why)) => Err(UserError::InternalError(why.to_string())),
+            Err(why) => Err(UserError::InternalError(why.to_string())),
+        };
+    }
+
+    let conn = pool.get()?;
+    let record = block(move || get_game_record(&conn, gid)).await?;
+    if record.status() != GameStatus::Lobby {
+        return Err(UserError::ValidationError(
+            "Only lobbies can be left. Resign to leave a running game".to_owned(),
+        ));
+    }
 
     // leave game
     let conn = pool.get()?;
+    block(move || remove_user_game(&conn, gid, user.id)).await?;
 
-    block(move || leave_game(&conn, user.id)).await?;
+    Ok(redirect("/games/mine".to_owned()))
+}
+
+// JSON Schema of all websocket messages and JSON responses (See ws::schema)
+pub async fn get_protocol_schema() -> HttpResponse {
+    HttpResponse::Ok().json(&*PROTOCOL_SCHEMA)
+}
 
-    Ok(redirect("/".to_owned()))
+// events that couldn't be delivered to slow sessions (See metrics::Metrics)
+pub async fn get_metrics(rooms: Data<Rooms>) -> HttpResponse {
+    HttpResponse::Ok().json(rooms.metrics().snapshot())
 }
@@ -0,0 +1,67 @@
+use super::errors::ErrorMessage;
+use super::messages::{
+    ClientRequest, FeedClientMessage, FeedMessage, GameEvent, ServerMessage, SpectatorMessage,
+    PROTOCOL_VERSION,
+};
+use crate::editor::models::{EditAction, EditResponse, SaveTarget};
+use crate::editor::routes::SaveResponse;
+use crate::frontend::routes::{ActionStatus, QueryResult};
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Value};
+
+/*
+JSON Schema (draft 7) of the websocket protocol and the JSON bodies of the HTTP API.
+Shared types are collected in the root 'definitions'.
+
+websocket:
+    client: ClientRequest (client -> server)
+    spectator: SpectatorMessage (spectator -> server)
+    server: ServerMessage (server -> client)
+    event: ServerMessage broadcast to all sessions of a game (with sequence number)
+    feed_client: FeedClientMessage (lobby feed client -> server)
+    feed: FeedMessage (lobby feed server -> client)
+http:
+    requests: bodies accepted by JSON routes
+    responses: bodies returned by JSON routes (failed requests always return ErrorMessage)
+*/
+pub fn protocol_schema() -> Value {
+    let mut gen = SchemaSettings::draft07().into_generator();
+
+    let websocket = json!({
+        "client": gen.subschema_for::<ClientRequest>(),
+        "spectator": gen.subschema_for::<SpectatorMessage>(),
+        "server": gen.subschema_for::<ServerMessage>(),
+        // broadcast messages carry the per-game sequence number
+        "event": gen.subschema_for::<GameEvent>(),
+        "feed_client": gen.subschema_for::<FeedClientMessage>(),
+        "feed": gen.subschema_for::<FeedMessage>(),
+    });
+
+    let http = json!({
+        "requests": {
+            "EditAction": gen.subschema_for::<EditAction>(),
+            "SaveTarget": gen.subschema_for::<SaveTarget>(),
+        },
+        "responses": {
+            "ActionStatus": gen.subschema_for::<ActionStatus>(),
+            "QueryResult": gen.subschema_for::<QueryResult>(),
+            "EditResponse": gen.subschema_for::<EditResponse>(),
+            "SaveResponse": gen.subschema_for::<SaveResponse>(),
+            "ErrorMessage": gen.subschema_for::<ErrorMessage>(),
+        },
+    });
+
+    json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "title": "pentagame online protocol",
+        "version": PROTOCOL_VERSION,
+        "websocket": websocket,
+        "http": http,
+        "definitions": gen.definitions(),
+    })
+}
+
+lazy_static! {
+    // the schema only changes with the binary
+    pub static ref PROTOCOL_SCHEMA: Value = protocol_schema();
+}
@@ -1,16 +1,22 @@
-use super::actor::GameServer;
-use super::errors::{MESSAGE_FORMAT_ERROR, UNAUTHORIZED_ERROR, UNIMPLEMENTED_ERROR, WebsocketError};
+use super::actor::GameRoom;
+use super::codec::{Codec, Frame};
+use super::errors::{ErrorMessage, WebsocketError};
 use super::messages::{
-    Connect, Disconnect, MakeMoveMessage, QueryGameMessage, QueryMovesMessage, ServerMessage,
-    SessionMessage, StartGameMessage,
+    AssignTeamMessage, ChangeStatusMessage, ClientMessage, ClientRequest, Connect, Disconnect,
+    DrawAction, DrawMessage, GameEvent, HostAction, HostActionMessage, LeaveGameMessage,
+    LobbyChange, MakeMoveMessage, MuteChatMessage, PlaceStopperMessage, QueryGameMessage,
+    QueryMovesMessage, ResignMessage, Resume, ResyncedMessage, SendChatMessage, ServerMessage,
+    StartGameMessage, UpdateLobbyMessage,
 };
+use super::models::RateLimit;
 use crate::auth::User;
+use crate::config::CONFIG;
+use crate::db::models::GameStatus;
 use crate::frontend::helper::log_error;
-use crate::graph::models::Move;
+use crate::graph::models::{FIELD, MOVE};
 use actix::prelude::*;
 use actix_web_actors::ws;
 use serde::Serialize;
-use serde_json::to_string;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
@@ -19,18 +25,6 @@ const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
 // How long before lack of client response causes a timeout
 const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
 
-// response specific structs
-#[derive(Serialize)]
-pub struct QueryGameResponse {
-    name: String,
-    description: String,
-    icon: String,
-    players: Vec<(Uuid, String)>,
-    state: u8,
-    host: Uuid,
-    pin: Vec<i16>,
-}
-
 // Session specific struct
 #[derive(Clone)]
 pub struct WsGameSession {
@@ -39,40 +33,69 @@ pub struct WsGameSession {
     pub hb: Instant,
     // joined game
     pub game: i32,
-    // Game server
-    pub addr: Addr<GameServer>,
-    // bound identity 
+    // Game room
+    pub addr: Addr<GameRoom>,
+    // bound identity
     pub user: User,
+    // negotiated protocol version (See messages::negotiate_version)
+    pub version: u16,
+    // negotiated encoding of messages
+    pub codec: Codec,
+    // token and last event of a dropped session to resume
+    pub resume: Option<Resume>,
+    // chat rate limit of this session
+    pub chat: RateLimit,
 }
 
 impl Actor for WsGameSession {
     type Context = ws::WebsocketContext<Self>;
 
     // Method is called on actor start.
-    // register ws session with GameServer
+    // register ws session with GameRoom
     fn started(&mut self, ctx: &mut Self::Context) {
         // start heartbeat process on session start.
         self.hb(ctx);
 
+        // the room resyncs sessions that fall further behind (See actor::GameRoom::deliver)
+        ctx.set_mailbox_capacity(CONFIG.games.outbound_queue);
+
         // register address for server actor
         let addr = ctx.address();
         self.addr
             .send(Connect {
                 addr: addr.recipient(),
                 uid: self.user.id,
+                resume: self.resume,
             })
             .into_actor(self)
-            .then(|message_result, _, ctx| {
+            .then(|message_result, act, ctx| {
                 match message_result {
                     Ok(res) => {
                         match res {
-                            Ok(_) => (),
+                            // confirm negotiated protocol version and encoding
+                            Ok(welcome) => {
+                                act.send(
+                                    &ServerMessage::Hello {
+                                        version: act.version,
+                                        encoding: act.codec,
+                                        token: Some(welcome.token),
+                                        seq: welcome.seq,
+                                        resumed: welcome.resumed,
+                                    },
+                                    ctx,
+                                );
+
+                                // resumed sessions receive missed chat messages with the replay
+                                if !welcome.resumed {
+                                    act.send(&ServerMessage::ChatHistory(welcome.chat), ctx);
+                                }
+                            }
                             Err(_) => {
-                                ctx.text(UNAUTHORIZED_ERROR.clone());
+                                act.send_error(WebsocketError::AuthorizationError(), ctx);
                             }
                         };
                     }
-                    // something is wrong with game server
+                    // something is wrong with game room
                     Err(why) => {
                         log_error(
                             "[Session Error]",
@@ -88,10 +111,9 @@ impl Actor for WsGameSession {
     }
 
     fn stopping(&mut self, context: &mut Self::Context) -> Running {
-        // notify game server
+        // notify game room
         self.addr.do_send(Disconnect {
             addr: context.address().recipient(),
-            gid: self.game,
             uid: self.user.id,
         });
 
@@ -99,12 +121,59 @@ impl Actor for WsGameSession {
     }
 }
 
-// Handle messages from game server, we simply send it to peer websocket
-impl Handler<SessionMessage> for WsGameSession {
+// Handle messages from game room, we simply send it to peer websocket
+impl Handler<GameEvent> for WsGameSession {
     type Result = ();
 
-    fn handle(&mut self, msg: SessionMessage, ctx: &mut Self::Context) {
-        ctx.text(to_string(&msg).expect("The GameServer sends corrupt messages"));
+    fn handle(&mut self, msg: GameEvent, ctx: &mut Self::Context) {
+        self.send(&msg, ctx);
+
+        // the backlog before the resync has been sent
+        if let ServerMessage::Resync { .. } = msg.message {
+            self.addr.do_send(ResyncedMessage {
+                addr: ctx.address().recipient(),
+            });
+        }
+
+        // kicked players are disconnected right away
+        if let ServerMessage::PlayerKicked { user, .. } = msg.message {
+            if user == self.user.id {
+                ctx.close(Some(ws::CloseReason {
+                    code: ws::CloseCode::Policy,
+                    description: Some("Kicked from the game".to_owned()),
+                }));
+                ctx.stop();
+            }
+        }
+
+        // every session of a player that left the lobby is closed
+        if let ServerMessage::PlayerLeft { user } = msg.message {
+            if user == self.user.id {
+                ctx.close(Some(ws::CloseReason {
+                    code: ws::CloseCode::Normal,
+                    description: Some("Left the game".to_owned()),
+                }));
+                ctx.stop();
+            }
+        }
+
+        // a newer tab or device of the player took over
+        if let ServerMessage::Evicted = msg.message {
+            ctx.close(Some(ws::CloseReason {
+                code: ws::CloseCode::Policy,
+                description: Some("Game opened in another tab or device".to_owned()),
+            }));
+            ctx.stop();
+        }
+
+        // the game continues after the restart (See actor::GameRoom::save)
+        if let ServerMessage::Shutdown = msg.message {
+            ctx.close(Some(ws::CloseReason {
+                code: ws::CloseCode::Restart,
+                description: Some("Server restarting".to_owned()),
+            }));
+            ctx.stop();
+        }
     }
 }
 
@@ -127,190 +196,20 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsGameSession {
             ws::Message::Pong(_) => {
                 self.hb = Instant::now();
             }
-            ws::Message::Text(text) => {
-                match serde_json::from_str::<SessionMessage>(&text) {
-                    Ok(action) => {
-                        /*
-                        TODO: move specific handling to dedicated functions in the future (those should be always inlined)
-                        action & data
-                        ---
-                        | action | description         | data                | host only |
-                        | ------ | ------------------- | ----------------    | --------- |
-                        | 0      | fetch latest move   | {"all": boolean}    |           |
-                        | 1      | get game meta       | {}                  |     X     |
-                        | 2      | make move           | {"move": [MOVE]}    |     X     |
-                        | 3      | Place Stopper       | {"move": String}    |     X     |
-                        | 4      | leave game          | {}                  |     X     |
-                        | 5      | start game          | {"message": String} |     ✓     |
-                        | 6      | stop game           | {"message": String} |     ✓     |
-                        */
-                        match action.action {
-                            // fetch latest move
-                            0 => {
-                                if action.data.contains_key("all") {
-                                    self.addr
-                                        .send(QueryMovesMessage {
-                                            gid: self.game,
-                                        })
-                                        .into_actor(self)
-                                        .then(|res, _, ctx| {
-                                            let data = match res.expect("Gameserver crashed") {
-                                                Ok(data) => data,
-                                                Err(_) => {
-                                                    ctx.text("Internal Error: Database Error or Gameserver Crashed".to_owned());
-                                                    return fut::ready(()); // This doesn't return error as handled gracefully
-                                                }
-                                            };
-                                            ctx.text(
-                                                serde_json::to_string(&ServerMessage {
-                                                    action: 0,
-                                                    data,
-                                                })
-                                                .unwrap_or("Internal Error: Failed to parse message".to_owned()),
-                                            );
-
-                                            fut::ready(())
-                                        })
-                                        .wait(ctx);
-                                } else {
-                                    ctx.text(
-                                        serde_json::to_string(&ServerMessage {
-                                            action: u8::MAX,
-                                            data: "Missing data key: 'all'".to_owned(),
-                                        })
-                                        .unwrap(),
-                                    );
-                                    return ();
-                                }
-                            }
-                            1 => {
-                                self.addr.send(QueryGameMessage { gid: self.game })
-                            .into_actor(self)
-                            // Result<(String, String, i32), APIError>
-                            .then(|res, _, ctx| {
-                                let _ = match res {
-                                     Ok(result) => {
-                                        let (game, players) = match result {
-                                                Ok(meta) => {
-                                                    meta
-                                                },
-                                                Err(_) => {
-                                                    ctx.stop();
-                                                    return fut::ready(());
-                                                }
-                                        };
-
-                                        let message = ServerMessage {
-                                            action: 1,
-                                            data: QueryGameResponse {
-                                                game, players
-                                            }
-                                        };
-
-                                        let data = match serde_json::to_string(&message) {
-                                                Ok(data) => data,
-                                                Err(_) => {
-                                                    ctx.stop();
-                                                    return fut::ready(());
-                                                }
-                                        };
-
-                                        ctx.text(data);
-
-                                    }
-                                    // something is wrong with game server
-                                    Err(why) => {
-                                        log_error("[Session Error]", format!("Gameserver closed rpc connection. Description: {}", why));
-                                        
-                                        ctx.stop()
-                                    }
-                                };
-                                fut::ready(())
-
-                            })
-                            .wait(ctx);
-                            }
-                            2 => {
-                                let parsed_move = match Move::from_action(action.data) {
-                                    Ok(parsed_move) => parsed_move,
-                                    Err(e) => {
-                                        ctx.text(e.to_string());
-                                        return;
-                                    }
-                                };
-
-                                self.addr
-                                    .send(MakeMoveMessage {
-                                        action: parsed_move.action,
-                                        gid: self.game,
-                                        uid: self.user.id,
-                                    })
-                                    .into_actor(self)
-                                    .then(|res, _, ctx| {
-                                        let _ = match res {
-                                            Ok(result) => {
-                                                let state = match result {
-                                                    Ok(state) => state,
-                                                    Err(_) => {
-                                                        ctx.stop();
-                                                        return fut::ready(());
-                                                    }
-                                                };
-
-                                                println!("{:?}", state);
-
-                                                ctx.text(UNIMPLEMENTED_ERROR.clone());
-                                            }
-                                            // something is wrong with game server
-                                            Err(_) => ctx.stop(),
-                                        };
-                                        fut::ready(())
-                                    })
-                                    .wait(ctx);
-                            }
-                            5 => {
-                                let sacrifice = self.clone();
-                                self.addr
-                                    .send(StartGameMessage {
-                                        gid: self.game,
-                                        uid: self.user.id,
-                                    })
-                                    .into_actor(self)
-                                    .then(move |res, _, ctx| {
-                                        match res {
-                                            Ok(result) => {
-                                                match result {
-                                                    Ok(_) => (), // The gameserver handles sending messages to all participants
-                                                    Err(APIError::AuthorizationError {
-                                                        ..
-                                                    }) => {
-                                                        ctx.text(UNAUTHORIZED_ERROR.clone());
-                                                    }
-                                                    _ => {}
-                                                };
-                                            }
-                                            // either gameserver down or unauthorized call
-                                            Err(_) => {
-                                                WsGameSession::stop(&sacrifice, ctx);
-                                            }
-                                        };
-                                        fut::ready(())
-                                    })
-                                    .wait(ctx);
-                            }
-                            _ => ctx.text(UNIMPLEMENTED_ERROR.clone()),
-                        };
-                    }
-                    Err(_) => ctx.text(MESSAGE_FORMAT_ERROR.clone()),
-                }
-            }
-            ws::Message::Binary(_) => ctx.text(UNIMPLEMENTED_ERROR.clone()),
+            ws::Message::Text(text) => match serde_json::from_str::<ClientRequest>(&text) {
+                Ok(message) => self.handle_message(message, ctx),
+                Err(_) => self.send_error(WebsocketError::MessageFormatError(), ctx),
+            },
+            ws::Message::Binary(data) => match self.codec.decode::<ClientRequest>(&data) {
+                Ok(message) => self.handle_message(message, ctx),
+                Err(why) => self.send_error(why, ctx),
+            },
             ws::Message::Close(reason) => {
                 ctx.close(reason);
                 WsGameSession::stop(self, ctx);
             }
             ws::Message::Continuation(_) => {
-                ctx.text(UNIMPLEMENTED_ERROR.clone());
+                self.send_error(WebsocketError::UnimplementedError(), ctx);
                 WsGameSession::stop(self, ctx);
             }
             ws::Message::Nop => (),
@@ -319,20 +218,444 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsGameSession {
 }
 
 impl WsGameSession {
+    /*
+    | action       | description          | data                          | host only |
+    | ------------ | -------------------- | ----------------------------- | --------- |
+    | fetch_moves  | fetch current board  |                               |           |
+    | get_metadata | get game meta        |                               |           |
+    | make_move    | make move            | {"figure": u8, "move": MOVE}  |           |
+    | place_stopper| place stopper        | {"figure": u8, "field": FIELD}|           |
+    | resign       | resign               |                               |           |
+    | offer_draw   | offer draw           |                               |           |
+    | accept_draw  | accept draw offer    |                               |           |
+    | decline_draw | decline draw offer   |                               |           |
+    | leave_game   | leave game           |                               |           |
+    | choose_seat  | choose seat (lobby)  | {"seat": u8}                  |           |
+    | choose_icon  | choose piece icon    | {"icon": String}              |           |
+    | ready        | (un)mark ready       | {"ready": bool}               |           |
+    | start_game   | start game           |                               |     ✓     |
+    | stop_game    | stop (abort) game    | {"message": String?}          |     ✓     |
+    | pause_game   | pause game           |                               |     ✓     |
+    | resume_game  | resume paused game   |                               |     ✓     |
+    | assign_team  | assign team          | {"user": Uuid, "team": u8}    |     ✓     |
+    | chat         | send chat message    | {"message": String}           |           |
+    | mute_chat    | (un)mute player      | {"user": Uuid, "muted": bool} |     ✓     |
+    | kick         | kick (and ban) player| {"user": Uuid, "ban": bool?}  |     ✓     |
+    | transfer_host| hand over host rights| {"user": Uuid}                |     ✓     |
+    | lock_lobby   | (un)lock lobby       | {"locked": bool}              |     ✓     |
+
+    Moves (make_move, place_stopper) with a request id are answered with ack or rejected.
+    */
+    fn handle_message(&mut self, request: ClientRequest, ctx: &mut ws::WebsocketContext<Self>) {
+        let id = request.id;
+        match request.message {
+            ClientMessage::FetchMoves => self.fetch_moves(ctx),
+            ClientMessage::GetMetadata => self.get_metadata(ctx),
+            ClientMessage::MakeMove { figure, action } => {
+                self.make_move((action, figure.to_string()), id, ctx)
+            }
+            ClientMessage::PlaceStopper { figure, field } => {
+                self.place_stopper(figure.to_string(), field, id, ctx)
+            }
+            ClientMessage::Resign => self.resign(ctx),
+            ClientMessage::OfferDraw => self.draw(DrawAction::Offer, ctx),
+            ClientMessage::AcceptDraw => self.draw(DrawAction::Accept, ctx),
+            ClientMessage::DeclineDraw => self.draw(DrawAction::Decline, ctx),
+            ClientMessage::ChooseSeat { seat } => self.update_lobby(LobbyChange::Seat(seat), ctx),
+            ClientMessage::ChooseIcon { icon } => self.update_lobby(LobbyChange::Icon(icon), ctx),
+            ClientMessage::Ready { ready } => self.update_lobby(LobbyChange::Ready(ready), ctx),
+            ClientMessage::StartGame => self.start_game(ctx),
+            ClientMessage::StopGame { message } => {
+                self.change_status(GameStatus::Aborted, message, ctx)
+            }
+            ClientMessage::PauseGame => self.change_status(GameStatus::Paused, None, ctx),
+            ClientMessage::ResumeGame => self.change_status(GameStatus::Running, None, ctx),
+            ClientMessage::AssignTeam { user, team } => self.assign_team(user, team, ctx),
+            ClientMessage::Chat { message } => self.chat(message, ctx),
+            ClientMessage::MuteChat { user, muted } => self.mute_chat(user, muted, ctx),
+            ClientMessage::Kick { user, ban } => {
+                self.host_action(HostAction::Kick { player: user, ban }, ctx)
+            }
+            ClientMessage::TransferHost { user } => {
+                self.host_action(HostAction::TransferHost(user), ctx)
+            }
+            ClientMessage::LockLobby { locked } => {
+                self.host_action(HostAction::LockLobby(locked), ctx)
+            }
+            ClientMessage::LeaveGame => self.leave_game(ctx),
+        }
+    }
+
+    #[inline]
+    fn fetch_moves(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr
+            .send(QueryMovesMessage)
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(Ok(state)) => act.send(&ServerMessage::Moves(state), ctx),
+                    // This doesn't stop the session as it's handled gracefully
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // something is wrong with game room
+                    Err(why) => {
+                        log_error(
+                            "[Session Error]",
+                            format!("Gameserver closed rpc connection. Description: {}", why),
+                        );
+                        ctx.stop()
+                    }
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn get_metadata(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr
+            .send(QueryGameMessage)
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(Ok(meta)) => act.send(&ServerMessage::Metadata(meta), ctx),
+                    Ok(Err(_)) => ctx.stop(),
+                    // something is wrong with game room
+                    Err(why) => {
+                        log_error(
+                            "[Session Error]",
+                            format!("Gameserver closed rpc connection. Description: {}", why),
+                        );
+                        ctx.stop()
+                    }
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn make_move(&self, action: MOVE, id: Option<Uuid>, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr
+            .send(MakeMoveMessage {
+                action,
+                uid: self.user.id,
+                request: id,
+            })
+            .into_actor(self)
+            .then(move |res, act, ctx| {
+                match res {
+                    // the gameserver notifies all participants on success
+                    Ok(result) => act.answer(id, result, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn place_stopper(
+        &self,
+        figure: String,
+        field: FIELD,
+        id: Option<Uuid>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        self.addr
+            .send(PlaceStopperMessage {
+                uid: self.user.id,
+                figure,
+                field,
+                request: id,
+            })
+            .into_actor(self)
+            .then(move |res, act, ctx| {
+                match res {
+                    // the gameserver notifies all participants on success
+                    Ok(result) => act.answer(id, result, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn start_game(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        let sacrifice = self.clone();
+        self.addr
+            .send(StartGameMessage { uid: self.user.id })
+            .into_actor(self)
+            .then(move |res, act, ctx| {
+                match res {
+                    // The gameserver handles sending messages to all participants
+                    Ok(Ok(_)) => (),
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // gameserver down
+                    Err(_) => WsGameSession::stop(&sacrifice, ctx),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn update_lobby(&self, change: LobbyChange, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr
+            .send(UpdateLobbyMessage {
+                uid: self.user.id,
+                change,
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    // the gameserver notifies all participants on success
+                    Ok(Ok(_)) => (),
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn resign(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr
+            .send(ResignMessage { uid: self.user.id })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    // the gameserver notifies all participants on success
+                    Ok(Ok(_)) => (),
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn leave_game(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr
+            .send(LeaveGameMessage { uid: self.user.id })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    // the session is closed with the announcement (See Handler<GameEvent>)
+                    Ok(Ok(_)) => (),
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn draw(&self, action: DrawAction, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr
+            .send(DrawMessage {
+                uid: self.user.id,
+                action,
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    // the gameserver notifies all participants on success
+                    Ok(Ok(_)) => (),
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn host_action(&self, action: HostAction, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr
+            .send(HostActionMessage {
+                uid: self.user.id,
+                action,
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    // the gameserver notifies all participants on success
+                    Ok(Ok(_)) => (),
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn change_status(
+        &self,
+        status: GameStatus,
+        message: Option<String>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        self.addr
+            .send(ChangeStatusMessage {
+                uid: self.user.id,
+                status,
+                message,
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    // the gameserver notifies all participants on success
+                    Ok(Ok(_)) => (),
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn assign_team(&self, player: Uuid, team: u8, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr
+            .send(AssignTeamMessage {
+                uid: self.user.id,
+                player,
+                team,
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    // the gameserver notifies all participants on success
+                    Ok(Ok(_)) => (),
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn chat(&mut self, message: String, ctx: &mut ws::WebsocketContext<Self>) {
+        if !self.chat.check() {
+            return self.send_error(
+                WebsocketError::ValidationError(
+                    "Too many chat messages. Please wait a moment".to_owned(),
+                ),
+                ctx,
+            );
+        }
+
+        self.addr
+            .send(SendChatMessage {
+                uid: self.user.id,
+                message,
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    // the gameserver notifies all participants on success
+                    Ok(Ok(_)) => (),
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    #[inline]
+    fn mute_chat(&self, player: Uuid, muted: bool, ctx: &mut ws::WebsocketContext<Self>) {
+        self.addr
+            .send(MuteChatMessage {
+                uid: self.user.id,
+                player,
+                muted,
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    // the gameserver notifies all participants on success
+                    Ok(Ok(_)) => (),
+                    Ok(Err(why)) => act.send_error(why, ctx),
+                    // something is wrong with game room
+                    Err(_) => ctx.stop(),
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    // encode message with the negotiated codec and send it to the client
+    fn send<T: Serialize>(&self, message: &T, ctx: &mut ws::WebsocketContext<Self>) {
+        match self.codec.encode(message) {
+            Ok(Frame::Text(text)) => ctx.text(text),
+            Ok(Frame::Binary(data)) => ctx.binary(data),
+            Err(why) => log_error(
+                "[Session Error]",
+                format!("Failed to encode message. Description: {}", why),
+            ),
+        }
+    }
+
+    fn send_error(&self, error: WebsocketError, ctx: &mut ws::WebsocketContext<Self>) {
+        self.send(&ServerMessage::Error(ErrorMessage::from(&error)), ctx);
+    }
+
+    // answer a move request. Requests without id only hear about errors
+    fn answer(
+        &self,
+        id: Option<Uuid>,
+        result: Result<bool, WebsocketError>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        match (id, result) {
+            (Some(id), Ok(applied)) => self.send(
+                &ServerMessage::Ack {
+                    id,
+                    duplicate: !applied,
+                },
+                ctx,
+            ),
+            (Some(id), Err(why)) => self.send(
+                &ServerMessage::Rejected {
+                    id,
+                    error: ErrorMessage::from(&why),
+                },
+                ctx,
+            ),
+            (None, Ok(_)) => (),
+            (None, Err(why)) => self.send_error(why, ctx),
+        }
+    }
+
     // helper method that sends ping to client every second.
     //
     // also this method checks heartbeats from client
     fn hb(&self, ctx: &mut ws::WebsocketContext<Self>) {
-        // clone game because &self wouldn't be guaranteed to satisfy 'static requirement of Context.run_interval
-        let gid = self.game.clone();
+        // clone user because &self wouldn't be guaranteed to satisfy 'static requirement of Context.run_interval
         let uid = self.user.id.clone();
 
         ctx.run_interval(HEARTBEAT_INTERVAL, move |act, ctx| {
             // check client heartbeats
             if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
-                // notify game server
+                // notify game room
                 act.addr.do_send(Disconnect {
-                    gid: gid,
                     uid: uid,
                     addr: ctx.address().recipient(),
                 });
@@ -350,7 +673,6 @@ impl WsGameSession {
 
     fn stop(session: &WsGameSession, ctx: &mut ws::WebsocketContext<WsGameSession>) {
         session.addr.do_send(Disconnect {
-            gid: session.game,
             uid: session.user.id,
             addr: ctx.address().recipient(),
         });
@@ -0,0 +1,205 @@
+use super::clock::Clocks;
+use super::models::{EventLog, Game, Turn};
+use crate::graph::errors::GraphErr;
+use crate::graph::graph::{GraphState, ResizableGraphState};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+// State of a running game that only lives in its room. Rooms store it periodically and before
+// shutting down (See actor::GameRoom::save) and continue from it when the game is opened again
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RoomSnapshot {
+    pub board: ResizableGraphState,
+    // turn encoding (See models::Turn)
+    pub turn: u8,
+    // players in seat order
+    pub players: Vec<Uuid>,
+    pub teams: HashMap<Uuid, u8>,
+    // time left of every player (milliseconds)
+    pub clocks: HashMap<Uuid, u64>,
+    pub history: Vec<u64>,
+    pub pending_gray: bool,
+    pub eliminated: Vec<Uuid>,
+    pub draw_offers: Vec<Uuid>,
+    pub muted: Vec<Uuid>,
+    // sequence number of the latest event. Sessions knowing it can resume with their token
+    pub seq: u64,
+    // token -> player
+    pub resume_tokens: HashMap<Uuid, Uuid>,
+    // latest applied move requests of every player (See models::REQUEST_WINDOW). Missing in
+    // snapshots of older servers
+    #[serde(default)]
+    pub requests: HashMap<Uuid, VecDeque<Uuid>>,
+}
+
+impl RoomSnapshot {
+    pub fn take(game: &Game, board: &GraphState, turn: Turn, now: Instant) -> RoomSnapshot {
+        RoomSnapshot {
+            board: board.into(),
+            turn: turn.into(),
+            players: game.players.clone(),
+            teams: game.teams.clone(),
+            clocks: game
+                .clocks
+                .times(now)
+                .into_iter()
+                .map(|(player, left)| (player, left.as_millis() as u64))
+                .collect(),
+            history: game.history.clone(),
+            pending_gray: game.pending_gray,
+            eliminated: game.eliminated.iter().copied().collect(),
+            draw_offers: game.draw_offers.iter().copied().collect(),
+            muted: game.muted.iter().copied().collect(),
+            seq: game.events.seq(),
+            resume_tokens: game.resume_tokens.clone(),
+            requests: game.requests.clone(),
+        }
+    }
+
+    // continue a freshly loaded game. Returns the board and the turn of the snapshot
+    pub fn restore(self, game: &mut Game) -> Result<(GraphState, Turn), GraphErr> {
+        // boards of running games may hold beaten stoppers off board (See GraphState::from_saved)
+        let board = GraphState::from_saved(self.board)?;
+
+        game.clocks = Clocks::restore(
+            game.rules.time_control(),
+            self.clocks
+                .into_iter()
+                .map(|(player, left)| (player, Duration::from_millis(left)))
+                .collect(),
+        );
+        game.players = self.players;
+        game.teams = self.teams;
+        game.history = self.history;
+        game.pending_gray = self.pending_gray;
+        game.eliminated = self.eliminated.into_iter().collect();
+        game.draw_offers = self.draw_offers.into_iter().collect();
+        game.muted = self.muted.into_iter().collect();
+        game.events = EventLog::restore(self.seq);
+        game.resume_tokens = self.resume_tokens;
+        game.requests = self.requests;
+
+        Ok((board, Turn::from(self.turn)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::Game as GameRecord;
+    use crate::graph::rules::MoveEffect;
+    use crate::ws::messages::ServerMessage;
+    use crate::ws::models::REQUEST_WINDOW;
+
+    fn record() -> GameRecord {
+        GameRecord {
+            id: 1,
+            name: "Snapshot".to_owned(),
+            state: 1,
+            description: None,
+            figures: 3,
+            swaps: true,
+            stoppers: true,
+            move_clock: None,
+            ko: 1,
+            teams: false,
+            base: None,
+            host: None,
+            locked: false,
+            abandon: 0,
+            result_reason: None,
+            base_time: Some(300),
+            increment: 5,
+            icon: "bi-circle-fill".to_owned(),
+            pin: None,
+        }
+    }
+
+    #[test]
+    fn game_in_progress_restores() {
+        let players = vec![Uuid::new_v4(), Uuid::new_v4()];
+        let mut game =
+            Game::from_record(&record(), players[0], "bi-circle-fill".to_owned(), vec![]);
+        game.players = players.clone();
+        game.clocks = Clocks::new(game.rules.time_control(), &players);
+        game.events.push(ServerMessage::Spectators { count: 1 });
+
+        // figure 2 beat black stopper 26 which still has to be placed again
+        let mut board = GraphState::empty();
+        let effect = MoveEffect {
+            swapped: None,
+            black_stopper: Some("26".to_owned()),
+            exited: false,
+            gray_stopper: false,
+        };
+        board.apply(&"2".to_owned(), &[0, 0, 0], &effect);
+        game.history.push(board.fingerprint());
+
+        // snapshots are stored as JSON (See actor::GameRoom::save)
+        let now = Instant::now();
+        let snapshot = RoomSnapshot::take(&game, &board, Turn::BlackStopper(0), now);
+        let data = serde_json::to_string(&snapshot).unwrap();
+
+        let mut fresh =
+            Game::from_record(&record(), players[0], "bi-circle-fill".to_owned(), vec![]);
+        let (restored, turn) = serde_json::from_str::<RoomSnapshot>(&data)
+            .unwrap()
+            .restore(&mut fresh)
+            .unwrap();
+
+        assert_eq!(turn, Turn::BlackStopper(0));
+        assert_eq!(restored.fingerprint(), board.fingerprint());
+        assert_eq!(fresh.players, players);
+        assert_eq!(fresh.history, game.history);
+        assert_eq!(fresh.events.seq(), game.events.seq());
+        assert_eq!(fresh.clocks.times(now), game.clocks.times(now));
+    }
+
+    #[test]
+    fn requests_are_kept_within_window() {
+        let player = Uuid::new_v4();
+        let mut game = Game::from_record(&record(), player, "bi-circle-fill".to_owned(), vec![]);
+        let requests: Vec<Uuid> = (0..REQUEST_WINDOW + 2).map(|_| Uuid::new_v4()).collect();
+        for request in &requests {
+            game.push_request(player, *request);
+        }
+
+        assert!(!game.applied(&player, &requests[1]));
+        assert!(game.applied(&player, &requests[2]));
+
+        let snapshot =
+            RoomSnapshot::take(&game, &GraphState::empty(), Turn::Move(0), Instant::now());
+        let data = serde_json::to_string(&snapshot).unwrap();
+
+        let mut fresh = Game::from_record(&record(), player, "bi-circle-fill".to_owned(), vec![]);
+        serde_json::from_str::<RoomSnapshot>(&data)
+            .unwrap()
+            .restore(&mut fresh)
+            .unwrap();
+        assert_eq!(fresh.requests[&player].len(), REQUEST_WINDOW);
+        assert!(fresh.applied(&player, &requests[REQUEST_WINDOW + 1]));
+        assert!(!fresh.applied(&player, &requests[1]));
+    }
+
+    #[test]
+    fn snapshots_without_requests_load() {
+        let snapshot = RoomSnapshot::take(
+            &Game::from_record(
+                &record(),
+                Uuid::new_v4(),
+                "bi-circle-fill".to_owned(),
+                vec![],
+            ),
+            &GraphState::empty(),
+            Turn::Move(0),
+            Instant::now(),
+        );
+        let mut data = serde_json::to_value(&snapshot).unwrap();
+        data.as_object_mut().unwrap().remove("requests");
+
+        let snapshot: RoomSnapshot = serde_json::from_value(data).unwrap();
+        assert!(snapshot.requests.is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+use super::actor::GameRoom;
+use super::codec::{Codec, Frame};
+use super::errors::{ErrorMessage, WebsocketError};
+use super::messages::{
+    ClientMessage, GameEvent, QueryGameMessage, QueryMovesMessage, ResyncedMessage, ServerMessage,
+    SpectatorConnect, SpectatorDisconnect, SpectatorMessage,
+};
+use crate::config::CONFIG;
+use crate::frontend::helper::log_error;
+use actix::prelude::*;
+use actix_web_actors::ws;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+// How often heartbeat pings are sent
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+// How long before lack of client response causes a timeout
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Read-only session watching a game. Spectators don't count as players
+pub struct WsSpectatorSession {
+    pub hb: Instant,
+    // watched game
+    pub game: i32,
+    // Game room
+    pub addr: Addr<GameRoom>,
+    // negotiated protocol version (See messages::negotiate_version)
+    pub version: u16,
+    // negotiated encoding of messages
+    pub codec: Codec,
+}
+
+impl Actor for WsSpectatorSession {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.hb(ctx);
+        ctx.set_mailbox_capacity(CONFIG.games.outbound_queue);
+
+        self.addr
+            .send(SpectatorConnect {
+                addr: ctx.address().recipient(),
+            })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                match res {
+                    Ok(Ok((seq, chat))) => {
+                        act.send(
+                            &ServerMessage::Hello {
+                                version: act.version,
+                                encoding: act.codec,
+                                token: None,
+                                seq,
+                                resumed: false,
+                            },
+                            ctx,
+                        );
+                        act.send(&ServerMessage::ChatHistory(chat), ctx);
+                    }
+                    Ok(Err(why)) => {
+                        act.send_error(why, ctx);
+                        ctx.stop();
+                    }
+                    // something is wrong with game room
+                    Err(why) => {
+                        log_error(
+                            "[Session Error]",
+                            format!("Gameserver closed rpc connection. Description: {}", why),
+                        );
+                        ctx.stop()
+                    }
+                };
+                fut::ready(())
+            })
+            .wait(ctx);
+    }
+
+    fn stopping(&mut self, ctx: &mut Self::Context) -> Running {
+        self.addr.do_send(SpectatorDisconnect {
+            addr: ctx.address().recipient(),
+        });
+
+        Running::Stop
+    }
+}
+
+// Handle messages from game room, we simply send it to peer websocket
+impl Handler<GameEvent> for WsSpectatorSession {
+    type Result = ();
+
+    fn handle(&mut self, msg: GameEvent, ctx: &mut Self::Context) {
+        self.send(&msg, ctx);
+
+        // the backlog before the resync has been sent
+        if let ServerMessage::Resync { .. } = msg.message {
+            self.addr.do_send(ResyncedMessage {
+                addr: ctx.address().recipient(),
+            });
+        }
+
+        if let ServerMessage::Shutdown = msg.message {
+            ctx.close(Some(ws::CloseReason {
+                code: ws::CloseCode::Restart,
+                description: Some("Server restarting".to_owned()),
+            }));
+            ctx.stop();
+        }
+    }
+}
+
+// WebSocket message handler
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSpectatorSession {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        let msg = match msg {
+            Err(_) => return ctx.stop(),
+            Ok(msg) => msg,
+        };
+
+        match msg {
+            ws::Message::Ping(msg) => {
+                self.hb = Instant::now();
+                ctx.pong(&msg);
+            }
+            ws::Message::Pong(_) => {
+                self.hb = Instant::now();
+            }
+            ws::Message::Text(text) => {
+                let message = serde_json::from_str::<SpectatorMessage>(&text).map_err(|_| {
+                    WsSpectatorSession::refuse(serde_json::from_str::<ClientMessage>(&text).is_ok())
+                });
+                self.handle_message(message, ctx);
+            }
+            ws::Message::Binary(data) => {
+                let message = match self.codec.decode::<SpectatorMessage>(&data) {
+                    Err(WebsocketError::MessageFormatError()) => Err(WsSpectatorSession::refuse(
+                        self.codec.decode::<ClientMessage>(&data).is_ok(),
+                    )),
+                    result => result,
+                };
+                self.handle_message(message, ctx);
+            }
+            ws::Message::Close(reason) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            ws::Message::Continuation(_) => {
+                self.send_error(WebsocketError::UnimplementedError(), ctx);
+                ctx.stop();
+            }
+            ws::Message::Nop => (),
+        }
+    }
+}
+
+impl WsSpectatorSession {
+    // player actions are refused explicitly instead of being reported as malformed
+    fn refuse(player_action: bool) -> WebsocketError {
+        if player_action {
+            WebsocketError::AuthorizationError()
+        } else {
+            WebsocketError::MessageFormatError()
+        }
+    }
+
+    fn handle_message(
+        &mut self,
+        message: Result<SpectatorMessage, WebsocketError>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        match message {
+            Ok(SpectatorMessage::FetchMoves) => {
+                self.addr
+                    .send(QueryMovesMessage)
+                    .into_actor(self)
+                    .then(|res, act, ctx| {
+                        match res {
+                            Ok(Ok(state)) => act.send(&ServerMessage::Moves(state), ctx),
+                            Ok(Err(why)) => act.send_error(why, ctx),
+                            // something is wrong with game room
+                            Err(_) => ctx.stop(),
+                        };
+                        fut::ready(())
+                    })
+                    .wait(ctx);
+            }
+            Ok(SpectatorMessage::GetMetadata) => {
+                self.addr
+                    .send(QueryGameMessage)
+                    .into_actor(self)
+                    .then(|res, act, ctx| {
+                        match res {
+                            Ok(Ok(meta)) => act.send(&ServerMessage::Metadata(meta), ctx),
+                            Ok(Err(why)) => act.send_error(why, ctx),
+                            // something is wrong with game room
+                            Err(_) => ctx.stop(),
+                        };
+                        fut::ready(())
+                    })
+                    .wait(ctx);
+            }
+            Err(why) => self.send_error(why, ctx),
+        }
+    }
+
+    // encode message with the negotiated codec and send it to the client
+    fn send<T: Serialize>(&self, message: &T, ctx: &mut ws::WebsocketContext<Self>) {
+        match self.codec.encode(message) {
+            Ok(Frame::Text(text)) => ctx.text(text),
+            Ok(Frame::Binary(data)) => ctx.binary(data),
+            Err(why) => log_error(
+                "[Session Error]",
+                format!("Failed to encode message. Description: {}", why),
+            ),
+        }
+    }
+
+    fn send_error(&self, error: WebsocketError, ctx: &mut ws::WebsocketContext<Self>) {
+        self.send(&ServerMessage::Error(ErrorMessage::from(&error)), ctx);
+    }
+
+    // helper method that sends ping to client every second and checks heartbeats from client
+    fn hb(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                // stopping notifies the game room
+                ctx.stop();
+                return;
+            }
+
+            ctx.ping(b"");
+        });
+    }
+}
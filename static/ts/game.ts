@@ -9,13 +9,61 @@ import {
     on_load,
 } from './utils';
 import { Board } from './pentamath/models';
-import { Request, MetadataQuery, StartGameAction } from './interfaces/requests';
+import {
+    PROTOCOL_VERSION,
+    Request,
+    MoveRequest,
+    MetadataQuery,
+    StartGameAction,
+    ChooseSeatAction,
+    ChooseIconAction,
+    ReadyAction,
+    StopGameAction,
+    PauseGameAction,
+    ResumeGameAction,
+    AssignTeamAction,
+    ChatAction,
+    MuteChatAction,
+    KickAction,
+    TransferHostAction,
+    LockLobbyAction,
+    ResignAction,
+    OfferDrawAction,
+    AcceptDrawAction,
+    DeclineDrawAction,
+} from './interfaces/requests';
 import {
     RESPONSE_CODES,
+    Ack,
+    ChatEntry,
+    ChatHistory,
+    ChatMessage,
+    ChatMuted,
+    DrawOffer,
+    ErrorResponse,
+    GameOver,
+    Hello,
+    HostChanged,
+    LobbyLocked,
+    PlayerKicked,
+    PlayerEliminated,
     Metadata,
+    MoveMade,
     NewPlayer,
+    PlayerClock,
+    PlayerPresence,
+    Rejected,
     Response,
-    GameStarted,
+    Resync,
+    ShutdownNotice,
+    Spectators,
+    GameStatus,
+    Lobby,
+    LobbyEntry,
+    ResultReason,
+    Status,
+    TeamAssigned,
+    Turn,
 } from './interfaces/responses';
 import { sanitize } from 'dompurify';
 import { LRUBuffer, Websocket, WebsocketBuilder } from 'websocket-ts';
@@ -24,29 +72,54 @@ import { create, Svg, SVG } from '@svgdotjs/svg.js';
 
 // constants
 const host = 'localhost:8443';
-const route = '/games/ws/';
-const url =
-    location.protocol === 'https:'
-        ? `wss://${host}${route}`
-        : `ws://${host}${route}`;
+const base = location.protocol === 'https:' ? `wss://${host}` : `ws://${host}`;
+// piece icons to choose from in the lobby (See ws::models::PIECE_ICONS)
+const PIECE_ICONS = [
+    'bi-circle-fill',
+    'bi-square-fill',
+    'bi-triangle-fill',
+    'bi-diamond-fill',
+    'bi-pentagon-fill',
+    'bi-hexagon-fill',
+    'bi-star-fill',
+    'bi-heart-fill',
+];
+// seats of a game (one figure set per seat)
+const MAX_PLAYERS = 5;
+// resume token and last received event of the current connection (one per game)
+const RESUME_KEY = 'pentagame-resume';
+
+function resume_key(game: string): string {
+    return `${RESUME_KEY}-${game}`;
+}
+
+// moves that weren't answered yet (See Game.move)
+const PENDING_KEY = 'pentagame-pending';
+
+// resumes an earlier connection of this tab if available (See Game.process_hello)
+function connection_url(game: string, spectate: string | undefined): string {
+    // spectators pass on the pin of private games (/games/watch/{id}?pin={pin})
+    if (spectate !== undefined) {
+        let pin = new URLSearchParams(location.search).get('pin');
+        let target = `${base}/games/spectate/${spectate}?version=${PROTOCOL_VERSION}`;
+        return pin === null ? target : `${target}&pin=${encodeURIComponent(pin)}`;
+    }
+
+    let url = `${base}/games/ws/${game}?version=${PROTOCOL_VERSION}`;
+    let resume = sessionStorage.getItem(resume_key(game));
+    if (resume === null) {
+        return url;
+    }
+
+    let { token, seq } = JSON.parse(resume);
+    return `${url}&resume=${token}&seq=${seq}`;
+}
 
 // test
 
 /*
-action & data
----
-| action | description         | data                | host only |
-| ------ | ------------------- | ----------------    | --------- |
-| 0      | fetch latest move   | {"all": boolean}    |           |
-| 1      | get game meta       | {}                  |     X     |
-| 2      | make move           | {"move": [MOVE]}    |     X     |
-| 3      | Place Stopper       | {"move": String}    |     X     |
-| 4      | leave game          | {}                  |     X     |
-| 5      | start game          | {"message": String} |     ✓     |
-| 6      | stop game           | {"message": String} |     ✓     |
-
-NOTE: In general all request codes respond with the same response code. 
-      This distinction in constants is done to mark unimplemented responses
+The request and response codes are generated from the server's message types
+(See interfaces/protocol.ts). Messages are tagged JSON objects: {"action": String, "data": any}
 */
 
 export class Game {
@@ -56,18 +129,38 @@ export class Game {
         icon: string;
         pin: string;
         host: string;
+        locked: boolean; // lobby doesn't accept new players
         shift: boolean;
         players: string[][];
+        teams: { [user: string]: number }; // partnership games only
+        offline: { [user: string]: boolean }; // disconnected players within grace window
+        muted: { [user: string]: boolean }; // players muted by the host
+        lobby: { [user: string]: LobbyEntry }; // seats, icons and ready checks
+        eliminated: { [user: string]: boolean }; // resigned or abandoned players
+        draw_offers: { [user: string]: boolean }; // pending draw offers
+        clocks: { [user: string]: PlayerClock }; // timed games only
+        clocks_received: number; // when the clocks were last synced (performance.now)
     };
+    result: { winners: string[]; reason: ResultReason }; // final result (See process_game_over)
+    resume: { token: string; seq: number };
+    resume_key: string; // sessionStorage key of the resume token (See resume_key)
+    pending: { [id: string]: MoveRequest }; // unanswered moves by request id
+    pending_key: string; // sessionStorage key of the unanswered moves
+    spectating: boolean; // read-only connection
+    kicked: boolean;
+    evicted: boolean; // the game was opened in another tab or on another device
+    restarting: boolean; // the server announced its shutdown
     socket: Websocket;
     user: String[];
-    state: number;
+    state: number; // turn
+    status: GameStatus;
     board: Board;
 
     // null == unknown
     constructor() {
         // internal content
         this.state = null;
+        this.status = null;
         this.ui = {
             description: null,
             name: null,
@@ -75,17 +168,40 @@ export class Game {
             shift: false,
             pin: null,
             host: null,
+            locked: false,
             players: null,
+            teams: {},
+            offline: {},
+            muted: {},
+            lobby: {},
+            eliminated: {},
+            draw_offers: {},
+            clocks: {},
+            clocks_received: 0,
         };
+        this.result = null;
+        this.resume = null;
+        this.kicked = false;
+        this.evicted = false;
+        this.restarting = false;
         this.user = null;
 
         // board
         this.board = null;
 
         // websocket to server
-        console.log(`[WS]: Connecting to ${url} 🐈`);
+        let dataset = document.getElementById('board').dataset;
+        let spectate = dataset.spectate;
+        this.spectating = spectate !== undefined;
+        this.resume_key = resume_key(dataset.game);
+        this.pending_key = `${PENDING_KEY}-${dataset.game}`;
+        this.pending = JSON.parse(
+            sessionStorage.getItem(this.pending_key) ?? '{}'
+        );
+        let target = connection_url(dataset.game, spectate);
+        console.log(`[WS]: Connecting to ${target} 🐈`);
         try {
-            this.socket = new WebsocketBuilder(url)
+            this.socket = new WebsocketBuilder(target)
                 .withBuffer(new LRUBuffer(10))
                 // Not really needed though it would be neat to catch all outgoing messages when losing connection
                 .onClose(this.socket_close())
@@ -124,6 +240,9 @@ export class Game {
             this.board = new Board(viewbox, SVG().addTo(container), true, true);
             console.log('[GAME]: Initialized game board');
         }
+
+        // the server keeps the authoritative clocks. They're only counted down locally between syncs
+        setInterval(() => this.update_clocks(), 250);
     }
 
     redraw_board(shift?: boolean) {
@@ -152,13 +271,97 @@ export class Game {
     socket_message(): (socket: Websocket, event: MessageEvent) => void {
         return (_: Websocket, event: MessageEvent) => {
             let data = Response.from_string(event.data);
+
+            // remember last received event for resuming
+            if (data.seq !== undefined && this.resume !== null) {
+                this.resume.seq = data.seq;
+                this.store_resume();
+            }
+
             switch (data.action) {
-                case RESPONSE_CODES.GET_GAME_METADATA:
+                case RESPONSE_CODES.HELLO:
+                    this.process_hello(data);
+                    break;
+                case RESPONSE_CODES.METADATA:
                     this.process_metadata(data);
                     break;
+                case RESPONSE_CODES.RESYNC:
+                    this.process_resync(data);
+                    break;
+                case RESPONSE_CODES.SPECTATORS:
+                    this.process_spectators(data);
+                    break;
+                case RESPONSE_CODES.STATUS:
+                    this.process_status(data);
+                    break;
                 case RESPONSE_CODES.PLAYER_JOINED:
                     this.process_new_player(data);
                     break;
+                case RESPONSE_CODES.PLAYER_LEFT:
+                case RESPONSE_CODES.PLAYER_DISCONNECTED:
+                case RESPONSE_CODES.PLAYER_RECONNECTED:
+                    this.process_presence(data);
+                    break;
+                case RESPONSE_CODES.TEAM_ASSIGNED:
+                    this.process_team_assigned(data);
+                    break;
+                case RESPONSE_CODES.LOBBY:
+                    this.process_lobby(data);
+                    break;
+                case RESPONSE_CODES.TURN:
+                    this.process_turn(data);
+                    break;
+                case RESPONSE_CODES.MOVE_MADE:
+                case RESPONSE_CODES.STOPPER_PLACED:
+                    // the board is refreshed by the following turn
+                    this.process_clocks((<MoveMade>data).data.clocks);
+                    break;
+                case RESPONSE_CODES.CHAT:
+                    this.process_chat(data);
+                    break;
+                case RESPONSE_CODES.CHAT_HISTORY:
+                    this.process_chat_history(data);
+                    break;
+                case RESPONSE_CODES.CHAT_MUTED:
+                    this.process_chat_muted(data);
+                    break;
+                case RESPONSE_CODES.PLAYER_KICKED:
+                    this.process_kick(data);
+                    break;
+                case RESPONSE_CODES.HOST_CHANGED:
+                    this.process_host_changed(data);
+                    break;
+                case RESPONSE_CODES.LOBBY_LOCKED:
+                    this.process_lobby_locked(data);
+                    break;
+                case RESPONSE_CODES.PLAYER_ELIMINATED:
+                    this.process_eliminated(data);
+                    break;
+                case RESPONSE_CODES.DRAW_OFFERED:
+                case RESPONSE_CODES.DRAW_DECLINED:
+                    this.process_draw(data);
+                    break;
+                case RESPONSE_CODES.GAME_OVER:
+                    this.process_game_over(data);
+                    break;
+                case RESPONSE_CODES.EVICTED:
+                    this.evicted = true;
+                    break;
+                case RESPONSE_CODES.SHUTDOWN_NOTICE:
+                    this.process_shutdown_notice(data);
+                    break;
+                case RESPONSE_CODES.SHUTDOWN:
+                    this.restarting = true;
+                    break;
+                case RESPONSE_CODES.ACK:
+                    this.process_ack(data);
+                    break;
+                case RESPONSE_CODES.REJECTED:
+                    this.process_rejected(data);
+                    break;
+                case RESPONSE_CODES.ERROR:
+                    this.process_error(data);
+                    break;
                 default:
                     create_alert(
                         0,
@@ -172,18 +375,66 @@ export class Game {
 
     socket_close(): (socket: Websocket, event: CloseEvent) => void {
         return (_: Websocket, event: CloseEvent) => {
+            // kicked players can't resume (See process_kick)
+            if (this.kicked) {
+                return;
+            }
+
+            // reloading takes over again and closes the oldest other session
+            if (this.evicted) {
+                create_modal(
+                    'This game was opened in another tab or on another device.',
+                    'Game',
+                    (_) => {
+                        window.location.reload();
+                    },
+                    1,
+                    'Continue here'
+                );
+                return;
+            }
+
+            // reloading resumes the session (See connection_url)
+            if (this.restarting) {
+                create_modal(
+                    'The server is restarting. Reconnect in a moment to continue your game.',
+                    'Server',
+                    (_) => {
+                        window.location.reload();
+                    },
+                    1,
+                    'Reconnect'
+                );
+                return;
+            }
+
             create_modal(
                 'Server closed connection. Game was possibly ended.',
                 'Websocket Error',
                 (_) => {
-                    window.location.href = '/';
+                    window.location.reload();
                 },
                 1,
-                'Redirect to landing page'
+                'Reconnect'
             );
         };
     }
 
+    store_resume() {
+        sessionStorage.setItem(this.resume_key, JSON.stringify(this.resume));
+    }
+
+    store_pending() {
+        sessionStorage.setItem(this.pending_key, JSON.stringify(this.pending));
+    }
+
+    // moves are kept until the server answers. It applies each request once
+    move(request: MoveRequest) {
+        this.pending[request.id] = request;
+        this.store_pending();
+        this.send_message(request);
+    }
+
     send_message_callback(socket: Websocket, data: Request, counter: number) {
         // timeout counter
         if (counter > 20) {
@@ -220,11 +471,130 @@ export class Game {
     }
 
     // data processing
-    process_game_start(req: GameStarted) {
-        console.log('[WS]: Game Started');
+    process_hello(rep: Hello) {
+        console.log(`[WS]: Speaking protocol version ${rep.data.version} 🐾`);
+
+        // spectators can't resume
+        if (rep.data.token === null) {
+            return;
+        }
 
-        // update state
-        this.state = 1;
+        // missed events are replayed after a resumed hello and update the sequence number
+        let seq = rep.data.seq;
+        if (rep.data.resumed && this.resume === null) {
+            seq = JSON.parse(sessionStorage.getItem(this.resume_key)).seq;
+        }
+        this.resume = { token: rep.data.token, seq: seq };
+        this.store_resume();
+
+        // moves sent before the connection dropped may have been lost
+        Object.values(this.pending).forEach((request) =>
+            this.send_message(request)
+        );
+    }
+
+    process_ack(rep: Ack) {
+        if (rep.data.duplicate) {
+            console.log(`[WS]: Move ${rep.data.id} was already applied 🐾`);
+        }
+        delete this.pending[rep.data.id];
+        this.store_pending();
+    }
+
+    process_rejected(rep: Rejected) {
+        delete this.pending[rep.data.id];
+        this.store_pending();
+        create_alert(
+            0,
+            sanitize(`[WS]: ${rep.data.error.message} (${rep.data.error.code})`),
+            'Move rejected'
+        );
+    }
+
+    process_chat(rep: ChatMessage) {
+        this.append_chat(rep.data);
+    }
+
+    process_chat_history(rep: ChatHistory) {
+        document.getElementById('game-chat').innerHTML = '';
+        rep.data.forEach((entry) => this.append_chat(entry));
+    }
+
+    process_chat_muted(rep: ChatMuted) {
+        if (rep.data.muted) {
+            this.ui.muted[rep.data.user] = true;
+        } else {
+            delete this.ui.muted[rep.data.user];
+        }
+
+        // update ui
+        this.update_players();
+    }
+
+    process_spectators(rep: Spectators) {
+        this.update_spectators(rep.data.count);
+    }
+
+    process_presence(rep: PlayerPresence) {
+        let user = rep.data.user;
+        if (rep.action === RESPONSE_CODES.PLAYER_DISCONNECTED) {
+            this.ui.offline[user] = true;
+        } else {
+            delete this.ui.offline[user];
+        }
+
+        if (rep.action === RESPONSE_CODES.PLAYER_LEFT && this.ui.players !== null) {
+            this.ui.players = this.ui.players.filter(
+                (player) => player[0] != user
+            );
+        }
+
+        // update ui
+        this.update_players();
+    }
+
+    process_error(rep: ErrorResponse) {
+        create_alert(
+            0,
+            sanitize(`[WS]: ${rep.data.message} (${rep.data.code})`),
+            'Websocket Error'
+        );
+    }
+
+    process_status(rep: Status) {
+        console.log(`[WS]: Game is ${rep.data.status}`);
+        this.status = rep.data.status;
+
+        if (rep.data.message !== null) {
+            create_alert(1, 'Game stopped by host', sanitize(rep.data.message));
+        }
+
+        // finished games keep the winner message (See update_message)
+        if (this.status === 'lobby' || this.status === 'aborted') {
+            this.state = 0;
+        }
+
+        // update ui
+        this.update_message();
+        this.update_controls();
+        this.update_lobby();
+    }
+
+    process_turn(rep: Turn) {
+        if (this.ui.players === null) {
+            return;
+        }
+
+        // seats follow the player order (See update_message for the state encoding)
+        let seat = this.ui.players.findIndex((player) => player[0] == rep.data.user);
+        let offset = { move: 1, black_stopper: 6, gray_stopper: 11 }[rep.data.kind];
+        this.state = offset + seat;
+        this.update_message();
+        this.process_clocks(rep.data.clocks);
+
+        if (this.user !== null && this.user[0] == rep.data.user) {
+            create_alert(1, 'Game', "It's your turn");
+        }
     }
 
     process_metadata(req: Metadata) {
@@ -232,11 +602,19 @@ export class Game {
 
         // name, description, state, players, host, icon, pin
         this.state = req.data.state;
+        this.status = req.data.status;
+        this.ui.lobby = {};
+        req.data.lobby.forEach((entry) => (this.ui.lobby[entry.user] = entry));
         this.ui.description = req.data.description;
         this.ui.icon = req.data.icon;
         this.ui.players = req.data.players;
         this.ui.pin = req.data.pin;
         this.ui.host = req.data.host;
+        this.ui.locked = req.data.locked;
+        this.ui.eliminated = {};
+        req.data.eliminated.forEach((user) => (this.ui.eliminated[user] = true));
+        this.ui.draw_offers = {};
+        req.data.draw_offers.forEach((user) => (this.ui.draw_offers[user] = true));
         if (sanitize(req.data.name) == '') {
             this.ui.name = 'Unsafe Name';
         } else {
@@ -244,6 +622,10 @@ export class Game {
         }
 
         // trigger callbacks to update UI
+        this.process_clocks(req.data.clocks);
+        this.update_spectators(req.data.spectators);
+        this.update_controls();
+        this.update_lobby();
         this.update_name();
         this.update_description();
         this.update_pin();
@@ -251,7 +633,7 @@ export class Game {
 
     process_new_player(rep: NewPlayer) {
         // [uid, username]
-        let player = rep.data.player.split('|');
+        let player = [rep.data.user, rep.data.name];
 
         // check if already initialized
         if (this.ui.players === null) {
@@ -271,6 +653,141 @@ export class Game {
         this.update_players();
     }
 
+    process_shutdown_notice(rep: ShutdownNotice) {
+        create_alert(
+            1,
+            'Server',
+            `The server restarts in ${rep.data.seconds} seconds. Your game continues afterwards.`
+        );
+    }
+
+    process_kick(rep: PlayerKicked) {
+        let user = rep.data.user;
+        if (this.user !== null && this.user[0] == user) {
+            this.kicked = true;
+            sessionStorage.removeItem(this.resume_key);
+            create_modal(
+                rep.data.banned
+                    ? 'You have been banned from this game.'
+                    : 'You have been kicked from this game.',
+                'Game',
+                (_) => {
+                    window.location.href = '/games/';
+                },
+                1,
+                'Back to games'
+            );
+            return;
+        }
+
+        if (this.ui.players !== null) {
+            this.ui.players = this.ui.players.filter((player) => player[0] != user);
+        }
+        delete this.ui.lobby[user];
+
+        // update ui
+        this.update_players();
+        this.update_lobby();
+    }
+
+    process_host_changed(rep: HostChanged) {
+        this.ui.host = rep.data.user;
+        if (this.user !== null && this.user[0] == rep.data.user) {
+            create_alert(1, 'Game', 'You are the host now');
+        }
+
+        // update ui
+        this.update_players();
+        this.update_controls();
+    }
+
+    process_lobby_locked(rep: LobbyLocked) {
+        this.ui.locked = rep.data.locked;
+
+        // update ui
+        this.update_controls();
+    }
+
+    process_lobby(rep: Lobby) {
+        this.ui.lobby[rep.data.user] = rep.data;
+
+        // update ui
+        this.update_players();
+        this.update_lobby();
+    }
+
+    process_clocks(clocks: PlayerClock[]) {
+        this.ui.clocks = {};
+        clocks.forEach((clock) => (this.ui.clocks[clock.user] = clock));
+        this.ui.clocks_received = performance.now();
+
+        // update ui
+        this.update_clocks();
+    }
+
+    process_eliminated(rep: PlayerEliminated) {
+        this.ui.eliminated[rep.data.user] = true;
+        this.ui.draw_offers = {};
+        create_alert(
+            1,
+            'Game',
+            sanitize(
+                `${this.player_name(rep.data.user)} ${
+                    {
+                        won: 'won',
+                        resigned: 'resigned',
+                        abandoned: 'abandoned the game',
+                        draw: 'agreed to a draw',
+                        timeout: 'ran out of time',
+                    }[rep.data.reason]
+                }`
+            )
+        );
+
+        // update ui
+        this.update_players();
+        this.update_controls();
+    }
+
+    process_draw(rep: DrawOffer) {
+        let name = this.player_name(rep.data.user);
+        if (rep.action === RESPONSE_CODES.DRAW_OFFERED) {
+            this.ui.draw_offers[rep.data.user] = true;
+            create_alert(1, 'Game', sanitize(`${name} offers a draw`));
+        } else {
+            this.ui.draw_offers = {};
+            create_alert(1, 'Game', sanitize(`${name} declined the draw`));
+        }
+
+        // update ui
+        this.update_controls();
+    }
+
+    process_resync(rep: Resync) {
+        console.log('[WS]: Fell behind. Resynced with the server 🐢');
+        this.process_metadata(<Metadata>{
+            action: RESPONSE_CODES.METADATA,
+            data: rep.data.metadata,
+        });
+    }
+
+    process_game_over(rep: GameOver) {
+        this.result = rep.data;
+        this.ui.draw_offers = {};
+        this.state = 16;
+
+        // update ui
+        this.update_message();
+        this.update_controls();
+    }
+
+    process_team_assigned(rep: TeamAssigned) {
+        this.ui.teams[rep.data.user] = rep.data.team;
+
+        // update ui
+        this.update_players();
+    }
+
     // ui updates
     update_name() {
         let name = document.getElementById('game-name');
@@ -284,7 +801,11 @@ export class Game {
         let message = document.getElementById('game-state');
 
         // evaluate and update
-        if (this.state == 0) {
+        if (this.status === 'paused') {
+            message.innerHTML = 'Game is paused';
+        } else if (this.status === 'aborted') {
+            message.innerHTML = 'Game was stopped';
+        } else if (this.state == 0) {
             message.innerHTML = 'Game is not running';
         } else if (this.state < 6) {
             message.innerHTML = sanitize(
@@ -292,32 +813,98 @@ export class Game {
             );
         } else if (this.state < 11) {
             message.innerHTML = sanitize(
-                `Waiting for black stopper by of ${
-                    this.ui.players[this.state - 1][1]
+                `Waiting for black stopper by ${
+                    this.ui.players[this.state - 6][1]
                 }`
             );
         } else if (this.state < 16) {
             message.innerHTML = sanitize(
-                `Waiting for gray stopper by of ${
-                    this.ui.players[this.state - 1][1]
+                `Waiting for gray stopper by ${
+                    this.ui.players[this.state - 11][1]
                 }`
             );
+        } else if (this.result === null) {
+            message.innerHTML = 'Game is over';
+        } else if (this.result.reason === 'draw') {
+            message.innerHTML = 'Game ended in a draw';
         } else {
-            let winners = '',
-                winner_amount = this.state - 10;
-            for (let i = 0; i < winner_amount; i++) {
-                if (i + 2 == winner_amount) {
-                    winners.concat(`${this.ui.players[i]} and `);
-                } else {
-                    winners.concat(`${this.ui.players[i]}, `);
-                }
-            }
+            let winners = this.result.winners.map((user) => this.player_name(user));
+            let reason = {
+                won: '',
+                resigned: ' by resignation',
+                abandoned: ' by abandonment',
+                timeout: ' on time',
+            }[this.result.reason];
             message.innerHTML = sanitize(
-                `Game won by ${winners} Congratulations!`
+                `Game won by ${winners.join(' and ')}${reason}. Congratulations!`
             );
         }
     }
 
+    // count down the running clock since the last sync
+    update_clocks() {
+        let elapsed = performance.now() - this.ui.clocks_received;
+        for (let user in this.ui.clocks) {
+            let clock = this.ui.clocks[user];
+            let badge = document.getElementById(`player-clock-${user}`);
+            if (badge === null) {
+                continue;
+            }
+
+            let remaining = clock.remaining;
+            if (clock.running && this.status === 'running') {
+                remaining = Math.max(0, remaining - elapsed);
+            }
+            let seconds = Math.ceil(remaining / 1000);
+            badge.innerHTML = `${Math.floor(seconds / 60)}:${(seconds % 60)
+                .toString()
+                .padStart(2, '0')}`;
+            badge.classList.toggle('bg-danger', clock.running);
+            badge.classList.toggle('bg-dark', !clock.running);
+        }
+    }
+
+    // display name of a player (falls back to the id)
+    player_name(user: string): string {
+        if (this.ui.players !== null) {
+            let player = this.ui.players.find((player) => player[0] == user);
+            if (player !== undefined) {
+                return player[1];
+            }
+        }
+        return user;
+    }
+
+    append_chat(entry: ChatEntry) {
+        let container = document.getElementById('game-chat');
+        let name = entry.user;
+        if (this.ui.players !== null) {
+            let player = this.ui.players.find((player) => player[0] == entry.user);
+            if (player !== undefined) {
+                name = player[1];
+            }
+        }
+
+        let time = new Date(entry.sent * 1000).toLocaleTimeString();
+        let item = build_element(
+            'li',
+            ['list-group-item', 'bg-dark', 'text-white'],
+            {},
+            `<small class="text-muted">${sanitize(time)}</small> <b>${sanitize(
+                name
+            )}</b>: ${sanitize(entry.message)}`
+        );
+        container.appendChild(item);
+        container.scrollTop = container.scrollHeight;
+    }
+
+    update_spectators(count: number) {
+        let spectators = document.getElementById('game-spectators');
+        if (spectators !== null) {
+            spectators.innerHTML = sanitize(count.toString());
+        }
+    }
+
     update_description() {
         // set description
         let description = document.getElementById('game-description');
@@ -383,6 +970,121 @@ export class Game {
                 )}">${points}</span>`;
             }
 
+            if (user[0] in this.ui.clocks) {
+                item.innerHTML += ` <span class="badge bg-dark rounded-pill" id="player-clock-${sanitize(
+                    user[0]
+                )}"></span>`;
+            }
+
+            if (user[0] in this.ui.eliminated) {
+                item.innerHTML += ` <span class="badge bg-danger rounded-pill">Out</span>`;
+            }
+
+            if (user[0] in this.ui.offline) {
+                item.innerHTML += ` <span class="badge bg-warning text-dark rounded-pill">Disconnected</span>`;
+            }
+
+            // lobby choices
+            let entry = this.ui.lobby[user[0]];
+            if (entry !== undefined) {
+                if (entry.icon !== null) {
+                    item.innerHTML = `<i class="bi ${sanitize(entry.icon)}"></i> ${
+                        item.innerHTML
+                    }`;
+                }
+                if (entry.seat !== null) {
+                    item.innerHTML += ` <span class="badge bg-secondary rounded-pill">Seat ${
+                        entry.seat + 1
+                    }</span>`;
+                }
+                if (entry.ready && this.status === 'lobby') {
+                    item.innerHTML += ` <i class="bi bi-check-circle-fill text-success"></i>`;
+                }
+            }
+
+            // team badge and (host only, before start) team toggle
+            if (user[0] in this.ui.teams) {
+                item.innerHTML += ` <span class="badge bg-secondary rounded-pill">Team ${
+                    this.ui.teams[user[0]] + 1
+                }</span>`;
+            }
+
+            if (
+                !this.spectating &&
+                this.user !== null &&
+                this.user[0] == this.ui.host &&
+                this.state == 0
+            ) {
+                let toggle = build_element(
+                    'button',
+                    ['btn', 'btn-sm', 'btn-outline-light'],
+                    { type: 'button' },
+                    '<i class="bi bi-people-fill"></i>'
+                );
+                toggle.addEventListener('click', (event) => {
+                    event.preventDefault();
+                    this.assign_team(
+                        user[0],
+                        this.ui.teams[user[0]] === 0 ? 1 : 0
+                    );
+                });
+                item.appendChild(toggle);
+            }
+
+            // host chat moderation
+            if (user[0] in this.ui.muted) {
+                item.innerHTML += ` <i class="bi bi-mic-mute-fill"></i>`;
+            }
+
+            if (
+                !this.spectating &&
+                this.user !== null &&
+                this.user[0] == this.ui.host &&
+                user[0] != this.ui.host
+            ) {
+                let muted = user[0] in this.ui.muted;
+                let mute = build_element(
+                    'button',
+                    ['btn', 'btn-sm', 'btn-outline-light'],
+                    { type: 'button' },
+                    muted
+                        ? '<i class="bi bi-mic-fill"></i>'
+                        : '<i class="bi bi-mic-mute"></i>'
+                );
+                mute.addEventListener('click', (event) => {
+                    event.preventDefault();
+                    this.send_message(new MuteChatAction(user[0], !muted));
+                });
+                item.appendChild(mute);
+
+                // hand over host rights and (lobby only) kick or ban
+                let promote = build_element(
+                    'button',
+                    ['btn', 'btn-sm', 'btn-outline-light'],
+                    { type: 'button', title: 'Make host' },
+                    '<i class="bi bi-award"></i>'
+                );
+                promote.addEventListener('click', (event) => {
+                    event.preventDefault();
+                    this.send_message(new TransferHostAction(user[0]));
+                });
+                item.appendChild(promote);
+
+                if (this.status === 'lobby') {
+                    let kick = build_element(
+                        'button',
+                        ['btn', 'btn-sm', 'btn-outline-danger'],
+                        { type: 'button', title: 'Kick (shift: ban)' },
+                        '<i class="bi bi-person-x"></i>'
+                    );
+                    kick.addEventListener('click', (event) => {
+                        event.preventDefault();
+                        this.send_message(new KickAction(user[0], event.shiftKey));
+                    });
+                    item.appendChild(kick);
+                }
+            }
+
             // append new player item to container
             container.appendChild(item);
         }
@@ -391,6 +1093,101 @@ export class Game {
         this.update_tooltips();
     }
 
+    // seat, icon and ready selection of the own player (lobby only)
+    update_lobby() {
+        let card = document.getElementById('game-lobby');
+        if (card === null || this.user === null) {
+            return;
+        }
+
+        card.hidden = this.status !== 'lobby';
+        let own = this.ui.lobby[<string>this.user[0]];
+        let others = Object.values(this.ui.lobby).filter(
+            (entry) => entry.user != this.user[0]
+        );
+
+        // choices of other players are disabled
+        let seats = <HTMLSelectElement>document.getElementById('game-seat');
+        seats.innerHTML = '<option value="" disabled>Seat</option>';
+        for (let seat = 0; seat < MAX_PLAYERS; seat++) {
+            let option = <HTMLOptionElement>build_element(
+                'option',
+                [],
+                { value: seat.toString() },
+                `Seat ${seat + 1}`
+            );
+            option.disabled = others.some((entry) => entry.seat === seat);
+            seats.appendChild(option);
+        }
+        seats.value = own !== undefined && own.seat !== null ? own.seat.toString() : '';
+
+        let icons = <HTMLSelectElement>document.getElementById('game-icon');
+        icons.innerHTML = '<option value="" disabled>Icon</option>';
+        PIECE_ICONS.forEach((icon) => {
+            let option = <HTMLOptionElement>build_element(
+                'option',
+                [],
+                { value: icon },
+                icon.replace('bi-', '').replace('-fill', '')
+            );
+            option.disabled = others.some((entry) => entry.icon === icon);
+            icons.appendChild(option);
+        });
+        icons.value = own !== undefined && own.icon !== null ? own.icon : '';
+
+        let ready = document.getElementById('game-ready');
+        let is_ready = own !== undefined && own.ready;
+        ready.classList.toggle('btn-success', is_ready);
+        ready.classList.toggle('btn-outline-light', !is_ready);
+        ready.innerText = is_ready ? 'Ready' : 'Not ready';
+    }
+
+    // host controls follow the host and the lifecycle (lobby -> running <-> paused)
+    update_controls() {
+        let start_btn = document.getElementById('start-btn');
+        let pause_btn = document.getElementById('pause-btn');
+        let stop_btn = document.getElementById('stop-btn');
+        let lock_btn = document.getElementById('lock-btn');
+        let draw_btn = document.getElementById('draw-btn');
+        let resign_btn = document.getElementById('resign-btn');
+        if (
+            start_btn === null ||
+            pause_btn === null ||
+            stop_btn === null ||
+            lock_btn === null ||
+            draw_btn === null ||
+            resign_btn === null
+        ) {
+            return;
+        }
+
+        // resigning and draws are open to every player still in the game
+        let playing =
+            this.user !== null &&
+            !(this.user[0] in this.ui.eliminated) &&
+            (this.status === 'running' || this.status === 'paused');
+        draw_btn.hidden = !playing;
+        resign_btn.hidden = !playing;
+        draw_btn.innerHTML = this.draw_pending()
+            ? '<i class="bi bi-hand-thumbs-up-fill"></i>'
+            : '<i class="bi bi-hand-thumbs-up"></i>';
+
+        let host = this.user !== null && this.user[0] == this.ui.host;
+        start_btn.hidden = !host || this.status !== 'lobby';
+        lock_btn.hidden = !host || this.status !== 'lobby';
+        pause_btn.hidden =
+            !host || (this.status !== 'running' && this.status !== 'paused');
+        stop_btn.hidden =
+            !host || this.status === 'finished' || this.status === 'aborted';
+        pause_btn.innerHTML =
+            this.status === 'paused'
+                ? '<i class="bi bi-play-circle"></i>'
+                : '<i class="bi bi-pause-circle"></i>';
+        lock_btn.innerHTML = this.ui.locked
+            ? '<i class="bi bi-lock-fill"></i>'
+            : '<i class="bi bi-unlock"></i>';
+    }
+
     update_tooltips() {
         let tooltip_els = document.querySelectorAll(
             '[data-bs-toggle="tooltip"]'
@@ -404,6 +1201,66 @@ export class Game {
     start_game() {
         this.send_message(new StartGameAction());
     }
+
+    choose_seat(seat: number) {
+        this.send_message(new ChooseSeatAction(seat));
+    }
+
+    choose_icon(icon: string) {
+        this.send_message(new ChooseIconAction(icon));
+    }
+
+    toggle_ready() {
+        let own = this.user === null ? undefined : this.ui.lobby[<string>this.user[0]];
+        this.send_message(new ReadyAction(own === undefined || !own.ready));
+    }
+
+    toggle_lock() {
+        this.send_message(new LockLobbyAction(!this.ui.locked));
+    }
+
+    toggle_pause() {
+        if (this.status === 'paused') {
+            this.send_message(new ResumeGameAction());
+        } else {
+            this.send_message(new PauseGameAction());
+        }
+    }
+
+    stop_game(message: string | null) {
+        this.send_message(new StopGameAction(message));
+    }
+
+    // another player offered a draw
+    draw_pending(): boolean {
+        return Object.keys(this.ui.draw_offers).some(
+            (user) => this.user === null || user != this.user[0]
+        );
+    }
+
+    toggle_draw() {
+        if (!this.draw_pending()) {
+            this.send_message(new OfferDrawAction());
+        } else if (confirm('Accept the draw? Cancel declines it')) {
+            this.send_message(new AcceptDrawAction());
+        } else {
+            this.send_message(new DeclineDrawAction());
+        }
+    }
+
+    resign() {
+        this.send_message(new ResignAction());
+    }
+
+    assign_team(user: string, team: number) {
+        this.send_message(new AssignTeamAction(user, team));
+    }
+
+    send_chat(message: string) {
+        if (message.trim() !== '') {
+            this.send_message(new ChatAction(message));
+        }
+    }
 }
 
 // will be reworked later
@@ -448,7 +1305,35 @@ on_load(() => {
             download('board.svg', instance.board.container.svg());
         });
 
-    // host-only fab binds
+    // chat (players only)
+    let chat_form = document.getElementById('game-chat-form');
+    if (chat_form !== undefined && chat_form !== null) {
+        chat_form.addEventListener('submit', (event) => {
+            event.preventDefault();
+            let input = <HTMLInputElement>(
+                document.getElementById('game-chat-input')
+            );
+            instance.send_chat(input.value);
+            input.value = '';
+        });
+    }
+
+    // lobby (players only)
+    let lobby = document.getElementById('game-lobby');
+    if (lobby !== null) {
+        document.getElementById('game-seat').addEventListener('change', (event) => {
+            instance.choose_seat(parseInt((<HTMLSelectElement>event.target).value, 10));
+        });
+        document.getElementById('game-icon').addEventListener('change', (event) => {
+            instance.choose_icon((<HTMLSelectElement>event.target).value);
+        });
+        document.getElementById('game-ready').addEventListener('click', (event) => {
+            event.preventDefault();
+            instance.toggle_ready();
+        });
+    }
+
+    // host fab binds (See update_controls)
     let start_btn = document.getElementById('start-btn');
     if (start_btn !== undefined && start_btn !== null) {
         start_btn.addEventListener('click', (event) => {
@@ -456,4 +1341,50 @@ on_load(() => {
             instance.start_game();
         });
     }
+
+    let lock_btn = document.getElementById('lock-btn');
+    if (lock_btn !== null) {
+        lock_btn.addEventListener('click', (event) => {
+            event.preventDefault();
+            instance.toggle_lock();
+        });
+    }
+
+    let pause_btn = document.getElementById('pause-btn');
+    if (pause_btn !== null) {
+        pause_btn.addEventListener('click', (event) => {
+            event.preventDefault();
+            instance.toggle_pause();
+        });
+    }
+
+    let stop_btn = document.getElementById('stop-btn');
+    if (stop_btn !== null) {
+        stop_btn.addEventListener('click', (event) => {
+            event.preventDefault();
+            let message = prompt('Stop the game for everyone? You may give a reason');
+            if (message !== null) {
+                instance.stop_game(message.trim() === '' ? null : message);
+            }
+        });
+    }
+
+    // player fab binds
+    let draw_btn = document.getElementById('draw-btn');
+    if (draw_btn !== null) {
+        draw_btn.addEventListener('click', (event) => {
+            event.preventDefault();
+            instance.toggle_draw();
+        });
+    }
+
+    let resign_btn = document.getElementById('resign-btn');
+    if (resign_btn !== null) {
+        resign_btn.addEventListener('click', (event) => {
+            event.preventDefault();
+            if (confirm('Resign this game?')) {
+                instance.resign();
+            }
+        });
+    }
 });
@@ -0,0 +1,77 @@
+// protocol.ts - part of pentagame online under GPLv3.0 @ cobalt
+// Generated by `pentagame-online protocol`. Don't edit manually
+
+export const PROTOCOL_VERSION = 1;
+
+export enum REQUEST_CODES {
+  FETCH_MOVES = 'fetch_moves',
+  GET_METADATA = 'get_metadata',
+  MAKE_MOVE = 'make_move',
+  PLACE_STOPPER = 'place_stopper',
+  RESIGN = 'resign',
+  OFFER_DRAW = 'offer_draw',
+  ACCEPT_DRAW = 'accept_draw',
+  DECLINE_DRAW = 'decline_draw',
+  LEAVE_GAME = 'leave_game',
+  CHOOSE_SEAT = 'choose_seat',
+  CHOOSE_ICON = 'choose_icon',
+  READY = 'ready',
+  START_GAME = 'start_game',
+  STOP_GAME = 'stop_game',
+  PAUSE_GAME = 'pause_game',
+  RESUME_GAME = 'resume_game',
+  ASSIGN_TEAM = 'assign_team',
+  CHAT = 'chat',
+  MUTE_CHAT = 'mute_chat',
+  KICK = 'kick',
+  TRANSFER_HOST = 'transfer_host',
+  LOCK_LOBBY = 'lock_lobby',
+}
+
+export enum RESPONSE_CODES {
+  HELLO = 'hello',
+  MOVES = 'moves',
+  METADATA = 'metadata',
+  RESYNC = 'resync',
+  PLAYER_JOINED = 'player_joined',
+  MOVE_MADE = 'move_made',
+  TURN = 'turn',
+  STOPPER_PLACED = 'stopper_placed',
+  PLAYER_LEFT = 'player_left',
+  PLAYER_DISCONNECTED = 'player_disconnected',
+  PLAYER_RECONNECTED = 'player_reconnected',
+  SPECTATORS = 'spectators',
+  STATUS = 'status',
+  PLAYER_WON = 'player_won',
+  PLAYER_ELIMINATED = 'player_eliminated',
+  DRAW_OFFERED = 'draw_offered',
+  DRAW_DECLINED = 'draw_declined',
+  GAME_OVER = 'game_over',
+  TEAM_ASSIGNED = 'team_assigned',
+  LOBBY = 'lobby',
+  CHAT = 'chat',
+  CHAT_HISTORY = 'chat_history',
+  CHAT_MUTED = 'chat_muted',
+  PLAYER_KICKED = 'player_kicked',
+  HOST_CHANGED = 'host_changed',
+  LOBBY_LOCKED = 'lobby_locked',
+  EVICTED = 'evicted',
+  SHUTDOWN_NOTICE = 'shutdown_notice',
+  SHUTDOWN = 'shutdown',
+  ACK = 'ack',
+  REJECTED = 'rejected',
+  ERROR = 'error',
+}
+
+export enum FEED_REQUEST_CODES {
+  FILTER = 'filter',
+}
+
+export enum FEED_RESPONSE_CODES {
+  HELLO = 'hello',
+  GAMES = 'games',
+  GAME_LISTED = 'game_listed',
+  GAME_UNLISTED = 'game_unlisted',
+  SHUTDOWN = 'shutdown',
+  ERROR = 'error',
+}
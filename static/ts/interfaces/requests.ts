@@ -1,23 +1,64 @@
 // responses.ts - part of pentagame online under GPLv3.0 @ cobalt
 // Collection of interfaces for requests intended as request to main server
 
-export enum REQUEST_CODES {
-  FETCH_LATEST_MOVE = 0,
-  GET_GAME_METADATA = 1,
-  MAKE_MOVE = 2,
-  PLACE_STOPPER = 3,
-  LEAVE_GAME = 4,
-  START_GAME = 5,
-  STOP_GAME = 6,
-}
+import { FEED_REQUEST_CODES, REQUEST_CODES } from './protocol';
+export { FEED_REQUEST_CODES, REQUEST_CODES, PROTOCOL_VERSION } from './protocol';
 
 export abstract class Request {
   // attributes
-  action: number;
+  action: REQUEST_CODES;
   data: any;
+  id?: string; // moves only (See MoveRequest)
 
   as_string(): string {
-    return JSON.stringify({ action: this.action, data: this.data });
+    return JSON.stringify({
+      action: this.action,
+      id: this.id,
+      data: this.data,
+    });
+  }
+}
+
+// random (v4) uuid
+function request_id(): string {
+  let bytes = crypto.getRandomValues(new Uint8Array(16));
+  bytes[6] = (bytes[6] & 0x0f) | 0x40;
+  bytes[8] = (bytes[8] & 0x3f) | 0x80;
+  let hex = Array.from(bytes, (byte) => byte.toString(16).padStart(2, '0'));
+  return [
+    hex.slice(0, 4),
+    hex.slice(4, 6),
+    hex.slice(6, 8),
+    hex.slice(8, 10),
+    hex.slice(10),
+  ]
+    .map((part) => part.join(''))
+    .join('-');
+}
+
+// moves are answered with ack or rejected referencing their id. The server
+// applies every id once, so pending moves may be resent after reconnecting
+export abstract class MoveRequest extends Request {
+  id = request_id();
+}
+
+export class MakeMoveAction extends MoveRequest {
+  action = REQUEST_CODES.MAKE_MOVE;
+  data: { figure: number; move: number[] };
+
+  constructor(figure: number, move: number[]) {
+    super();
+    this.data = { figure: figure, move: move };
+  }
+}
+
+export class PlaceStopperAction extends MoveRequest {
+  action = REQUEST_CODES.PLACE_STOPPER;
+  data: { figure: number; field: number[] };
+
+  constructor(figure: number, field: number[]) {
+    super();
+    this.data = { figure: figure, field: field };
   }
 }
 
@@ -27,11 +68,157 @@ export class WhoamI extends Request {
 }
 
 export class MetadataQuery extends Request {
-  action = REQUEST_CODES.GET_GAME_METADATA;
-  data = {};
+  action = REQUEST_CODES.GET_METADATA;
+  data = undefined;
 }
 
 export class StartGameAction extends Request {
   action = REQUEST_CODES.START_GAME;
-  data = {};
+  data = undefined;
+}
+
+export class ChooseSeatAction extends Request {
+  action = REQUEST_CODES.CHOOSE_SEAT;
+  data: { seat: number };
+
+  constructor(seat: number) {
+    super();
+    this.data = { seat: seat };
+  }
+}
+
+export class ChooseIconAction extends Request {
+  action = REQUEST_CODES.CHOOSE_ICON;
+  data: { icon: string };
+
+  constructor(icon: string) {
+    super();
+    this.data = { icon: icon };
+  }
+}
+
+export class ReadyAction extends Request {
+  action = REQUEST_CODES.READY;
+  data: { ready: boolean };
+
+  constructor(ready: boolean) {
+    super();
+    this.data = { ready: ready };
+  }
+}
+
+export class StopGameAction extends Request {
+  action = REQUEST_CODES.STOP_GAME;
+  data: { message: string | null };
+
+  constructor(message: string | null) {
+    super();
+    this.data = { message: message };
+  }
+}
+
+export class PauseGameAction extends Request {
+  action = REQUEST_CODES.PAUSE_GAME;
+  data = undefined;
+}
+
+export class ResumeGameAction extends Request {
+  action = REQUEST_CODES.RESUME_GAME;
+  data = undefined;
+}
+
+export class ResignAction extends Request {
+  action = REQUEST_CODES.RESIGN;
+  data = undefined;
+}
+
+export class OfferDrawAction extends Request {
+  action = REQUEST_CODES.OFFER_DRAW;
+  data = undefined;
+}
+
+export class AcceptDrawAction extends Request {
+  action = REQUEST_CODES.ACCEPT_DRAW;
+  data = undefined;
+}
+
+export class DeclineDrawAction extends Request {
+  action = REQUEST_CODES.DECLINE_DRAW;
+  data = undefined;
+}
+
+export class AssignTeamAction extends Request {
+  action = REQUEST_CODES.ASSIGN_TEAM;
+  data: { user: string; team: number };
+
+  constructor(user: string, team: number) {
+    super();
+    this.data = { user: user, team: team };
+  }
+}
+
+export class ChatAction extends Request {
+  action = REQUEST_CODES.CHAT;
+  data: { message: string };
+
+  constructor(message: string) {
+    super();
+    this.data = { message: message };
+  }
+}
+
+export class MuteChatAction extends Request {
+  action = REQUEST_CODES.MUTE_CHAT;
+  data: { user: string; muted: boolean };
+
+  constructor(user: string, muted: boolean) {
+    super();
+    this.data = { user: user, muted: muted };
+  }
+}
+
+export class KickAction extends Request {
+  action = REQUEST_CODES.KICK;
+  data: { user: string; ban: boolean };
+
+  constructor(user: string, ban: boolean) {
+    super();
+    this.data = { user: user, ban: ban };
+  }
+}
+
+export class TransferHostAction extends Request {
+  action = REQUEST_CODES.TRANSFER_HOST;
+  data: { user: string };
+
+  constructor(user: string) {
+    super();
+    this.data = { user: user };
+  }
+}
+
+export class LockLobbyAction extends Request {
+  action = REQUEST_CODES.LOCK_LOBBY;
+  data: { locked: boolean };
+
+  constructor(locked: boolean) {
+    super();
+    this.data = { locked: locked };
+  }
+}
+
+// games the lobby feed should list. Unset fields match every game
+export interface FeedFilter {
+  private?: boolean;
+  min_players?: number;
+  max_players?: number;
+}
+
+export class FeedFilterAction {
+  action = FEED_REQUEST_CODES.FILTER;
+  data: FeedFilter;
+
+  constructor(filter: FeedFilter) {
+    this.data = filter;
+  }
 }
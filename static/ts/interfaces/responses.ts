@@ -1,20 +1,14 @@
 // responses.ts - part of pentagame online under GPLv3.0 @ cobalt
 // Collection of interfaces for responses provided by server
 
-export enum RESPONSE_CODES {
-  FETCH_LATEST_MOVE = 0,
-  GET_GAME_METADATA = 1,
-  // unimplemented MAKE_MOVE: 2,
-  // unimplemented PLAYER_JOINED: 3,
-  LEAVE_GAME = 4,
-  START_GAME = 5,
-  // Unimplemented STOP_GAME: 6,
-  PLAYER_JOINED = 7,
-}
+import { FEED_RESPONSE_CODES, RESPONSE_CODES } from './protocol';
+export { FEED_RESPONSE_CODES, RESPONSE_CODES } from './protocol';
 
 export class Response {
-  action: number;
+  action: RESPONSE_CODES;
   data: any;
+  // sequence number of broadcast events (not set for direct responses)
+  seq?: number;
 
   static from_string(source: string): Response {
     try {
@@ -26,27 +20,293 @@ export class Response {
   }
 }
 
+export class Hello extends Response {
+  action = RESPONSE_CODES.HELLO;
+  data: {
+    version: number;
+    encoding: string;
+    token: string | null; // null for spectators
+    seq: number;
+    resumed: boolean;
+  };
+}
+
 export class NewPlayer extends Response {
   action = RESPONSE_CODES.PLAYER_JOINED;
   data: {
-    player: string;
+    user: string;
+    name: string;
   };
 }
 
+export class PlayerPresence extends Response {
+  action:
+    | RESPONSE_CODES.PLAYER_LEFT
+    | RESPONSE_CODES.PLAYER_DISCONNECTED
+    | RESPONSE_CODES.PLAYER_RECONNECTED;
+  data: {
+    user: string;
+  };
+}
+
+// time left of a player in timed games (milliseconds)
+export interface PlayerClock {
+  user: string;
+  remaining: number;
+  running: boolean;
+}
+
 export class Metadata extends Response {
-  action = RESPONSE_CODES.GET_GAME_METADATA;
+  action = RESPONSE_CODES.METADATA;
   data: {
     name: string;
     description: string;
     icon: string;
     players: string[][];
-    state: number;
+    state: number; // turn
+    status: GameStatus;
+    lobby: LobbyEntry[];
     host: string;
+    locked: boolean;
+    eliminated: string[];
+    draw_offers: string[];
+    clocks: PlayerClock[];
     pin: string;
+    spectators: number;
   };
 }
 
-export class GameStarted extends Response {
-  action = RESPONSE_CODES.START_GAME;
-  data = {};
+// full state for sessions that fell too far behind. Replaces the local state
+export class Resync extends Response {
+  action = RESPONSE_CODES.RESYNC;
+  data: {
+    metadata: Metadata['data'];
+    moves: { locations: [number[], string][] };
+  };
+}
+
+// player that has to act next. Stoppers are placed by the player that moved
+export class Turn extends Response {
+  action = RESPONSE_CODES.TURN;
+  data: {
+    user: string;
+    kind: 'move' | 'black_stopper' | 'gray_stopper';
+    clocks: PlayerClock[];
+  };
+}
+
+export class MoveMade extends Response {
+  action = RESPONSE_CODES.MOVE_MADE;
+  data: {
+    user: string;
+    figure: string;
+    move: number[];
+    clocks: PlayerClock[];
+  };
+}
+
+export class StopperPlaced extends Response {
+  action = RESPONSE_CODES.STOPPER_PLACED;
+  data: {
+    user: string;
+    figure: string;
+    field: number[];
+    clocks: PlayerClock[];
+  };
+}
+
+export class Spectators extends Response {
+  action = RESPONSE_CODES.SPECTATORS;
+  data: {
+    count: number;
+  };
+}
+
+export type GameStatus = 'lobby' | 'running' | 'paused' | 'finished' | 'aborted';
+
+// lifecycle transition. The host may give a reason when stopping
+export class Status extends Response {
+  action = RESPONSE_CODES.STATUS;
+  data: {
+    status: GameStatus;
+    message: string | null;
+  };
+}
+
+export type ResultReason = 'won' | 'resigned' | 'abandoned' | 'draw' | 'timeout';
+
+// resigned or abandoned. Partners in team games are eliminated together
+export class PlayerEliminated extends Response {
+  action = RESPONSE_CODES.PLAYER_ELIMINATED;
+  data: {
+    user: string;
+    reason: ResultReason;
+  };
+}
+
+// used for draw_offered and draw_declined
+export class DrawOffer extends Response {
+  data: {
+    user: string;
+  };
+}
+
+export class GameOver extends Response {
+  action = RESPONSE_CODES.GAME_OVER;
+  data: {
+    winners: string[];
+    reason: ResultReason;
+  };
+}
+
+export class TeamAssigned extends Response {
+  action = RESPONSE_CODES.TEAM_ASSIGNED;
+  data: {
+    user: string;
+    team: number;
+  };
+}
+
+// seat (turn order), piece icon and ready check of a player in the lobby
+export interface LobbyEntry {
+  user: string;
+  seat: number | null;
+  icon: string | null;
+  ready: boolean;
+}
+
+export class Lobby extends Response {
+  action = RESPONSE_CODES.LOBBY;
+  data: LobbyEntry;
+}
+
+export interface ChatEntry {
+  user: string;
+  message: string;
+  sent: number; // unix timestamp
+}
+
+export class ChatMessage extends Response {
+  action = RESPONSE_CODES.CHAT;
+  data: ChatEntry;
+}
+
+export class ChatHistory extends Response {
+  action = RESPONSE_CODES.CHAT_HISTORY;
+  data: ChatEntry[];
+}
+
+export class ChatMuted extends Response {
+  action = RESPONSE_CODES.CHAT_MUTED;
+  data: {
+    user: string;
+    muted: boolean;
+  };
+}
+
+export class PlayerKicked extends Response {
+  action = RESPONSE_CODES.PLAYER_KICKED;
+  data: {
+    user: string;
+    banned: boolean;
+  };
+}
+
+export class HostChanged extends Response {
+  action = RESPONSE_CODES.HOST_CHANGED;
+  data: {
+    user: string;
+  };
+}
+
+export class LobbyLocked extends Response {
+  action = RESPONSE_CODES.LOBBY_LOCKED;
+  data: {
+    locked: boolean;
+  };
+}
+
+// a newer session of the player took over. The connection is closed after this event
+export class Evicted extends Response {
+  action = RESPONSE_CODES.EVICTED;
+}
+
+// the server restarts in {seconds}. Running games continue afterwards
+export class ShutdownNotice extends Response {
+  action = RESPONSE_CODES.SHUTDOWN_NOTICE;
+  data: {
+    seconds: number;
+  };
+}
+
+// the connection is closed after this event
+export class Shutdown extends Response {
+  action = RESPONSE_CODES.SHUTDOWN;
+}
+
+// the move request {id} was applied. Resent requests applied before
+// are marked `duplicate`
+export class Ack extends Response {
+  action = RESPONSE_CODES.ACK;
+  data: {
+    id: string;
+    duplicate: boolean;
+  };
+}
+
+export class Rejected extends Response {
+  action = RESPONSE_CODES.REJECTED;
+  data: {
+    id: string;
+    error: {
+      code: number;
+      message: string;
+    };
+  };
+}
+
+export class ErrorResponse extends Response {
+  action = RESPONSE_CODES.ERROR;
+  data: {
+    code: number;
+    message: string;
+  };
+}
+
+// open game as listed by the lobby feed (/games/feed)
+export interface GameListing {
+  id: number;
+  name: string;
+  icon: string;
+  private: boolean; // joining requires the pin
+  players: number;
+  status: GameStatus;
+}
+
+export class FeedResponse {
+  action: FEED_RESPONSE_CODES;
+  data: any;
+
+  static from_string(source: string): FeedResponse {
+    return <FeedResponse>JSON.parse(source);
+  }
+}
+
+// all open games matching the filter (newest first)
+export class FeedGames extends FeedResponse {
+  action = FEED_RESPONSE_CODES.GAMES;
+  data: GameListing[];
+}
+
+export class FeedGameListed extends FeedResponse {
+  action = FEED_RESPONSE_CODES.GAME_LISTED;
+  data: GameListing;
+}
+
+// the game is over or doesn't match the filter anymore
+export class FeedGameUnlisted extends FeedResponse {
+  action = FEED_RESPONSE_CODES.GAME_UNLISTED;
+  data: {
+    id: number;
+  };
 }
@@ -1,111 +1,185 @@
-import { getJSONP, init_ui, on_load, build_element } from './utils';
+import { init_ui, on_load, build_element } from './utils';
+import {
+    FeedFilter,
+    FeedFilterAction,
+    PROTOCOL_VERSION,
+} from './interfaces/requests';
+import {
+    FEED_RESPONSE_CODES,
+    FeedGameListed,
+    FeedGames,
+    FeedGameUnlisted,
+    FeedResponse,
+    GameListing,
+} from './interfaces/responses';
 import { sanitize } from 'dompurify';
 
-on_load(() => {
-    // init ui
-    init_ui('OVERVIEW');
+// games are pushed by the lobby feed (See ws::feed::LobbyFeed)
+const base =
+    location.protocol === 'https:'
+        ? `wss://${location.host}`
+        : `ws://${location.host}`;
+const FEED_URL = `${base}/games/feed?version=${PROTOCOL_VERSION}`;
+// delay before reconnecting a dropped feed (milliseconds)
+const RECONNECT_DELAY = 5000;
+
+class Overview {
+    games: Map<number, GameListing> = new Map();
+    socket: WebSocket | null = null;
+    list: HTMLElement;
+
+    constructor(list: HTMLElement) {
+        this.list = list;
+    }
+
+    connect() {
+        this.socket = new WebSocket(FEED_URL);
+        this.socket.onopen = () => {
+            console.log('[OVERVIEW]: Following open games 🐾');
+            this.send_filter();
+        };
+        this.socket.onmessage = (event) =>
+            this.process(FeedResponse.from_string(event.data));
+        this.socket.onclose = () => {
+            console.log('[OVERVIEW]: Feed closed. Reconnecting 💤');
+            setTimeout(() => this.connect(), RECONNECT_DELAY);
+        };
+    }
+
+    // filter from the controls of games/overview.html
+    filter(): FeedFilter {
+        let visibility = (<HTMLSelectElement>(
+            document.getElementById('filter-visibility')
+        )).value;
+        let min = (<HTMLInputElement>(
+            document.getElementById('filter-min-players')
+        )).value;
+        let max = (<HTMLInputElement>(
+            document.getElementById('filter-max-players')
+        )).value;
+
+        let filter: FeedFilter = {};
+        if (visibility !== 'all') {
+            filter.private = visibility === 'private';
+        }
+        if (min !== '') {
+            filter.min_players = parseInt(min);
+        }
+        if (max !== '') {
+            filter.max_players = parseInt(max);
+        }
+        return filter;
+    }
 
-    console.log('[OVERVIEW]: Starting to fetch latest games');
-
-    // load overview
-    let auth_status = <HTMLInputElement>document.getElementById('auth-status');
-
-    // remove placeholder
-    let container = document.getElementById('games-container');
-    container.innerHTML = '';
-
-    console.log('[OVERVIEW]: Fetching latest games');
-    let games = getJSONP('/api/games/latest'); // This is not guarded ATM
-
-    console.log(`[OVERVIEW]: Fetched a total of ${games.length}`);
-
-    if (games.length > 0) {
-        // create join form
-        let join_form = build_element('form', ['w-80']),
-            join_input_group = build_element('div', ['input-group', 'mb-2']),
-            join_target_field = build_element('input', ['form-control'], {
-                placeholder: 'GAME ID',
-                'aria-label': 'Game ID for joining a game directly',
-                'aria-describedby': 'join-game-label',
-                id: 'game-id',
-                name: 'game-id',
-                type: 'text',
-            }),
-            join_form_submit_button = build_element(
-                'button',
-                ['btn', 'btn-outline-dark'],
-                {
-                    type: 'submit',
-                },
-                'Join Game'
+    // the feed answers with all matching games
+    send_filter() {
+        if (this.socket !== null && this.socket.readyState === WebSocket.OPEN) {
+            this.socket.send(
+                JSON.stringify(new FeedFilterAction(this.filter()))
             );
+        }
+    }
 
-        // style form and form elements
-        join_form.addEventListener('submit', (event) => {
-            event.preventDefault();
-            let id = (<HTMLInputElement>document.getElementById('game-id'))
-                .value;
-            window.location.href = `/games/join/${id}`;
-        });
-        // glue form together and attach to container
-        join_input_group.appendChild(join_target_field);
-        join_input_group.appendChild(join_form_submit_button);
-        join_form.appendChild(join_input_group);
-        container.appendChild(join_input_group);
-
-        // create game list
-        let game_list = document.createElement('ul');
-        game_list.classList.add(
-            'list-group',
-            'border-1',
-            'border-dark',
-            'list-group-flush'
-        );
-
-        for (let i = 0; i < games.length; i++) {
-            let fragment = games[i],
-                game = build_element(
+    process(rep: FeedResponse) {
+        switch (rep.action) {
+            case FEED_RESPONSE_CODES.GAMES:
+                this.games.clear();
+                (<FeedGames>rep).data.forEach((game) =>
+                    this.games.set(game.id, game)
+                );
+                break;
+            case FEED_RESPONSE_CODES.GAME_LISTED:
+                let listed = (<FeedGameListed>rep).data;
+                this.games.set(listed.id, listed);
+                break;
+            case FEED_RESPONSE_CODES.GAME_UNLISTED:
+                this.games.delete((<FeedGameUnlisted>rep).data.id);
+                break;
+            case FEED_RESPONSE_CODES.SHUTDOWN:
+                // the feed is reconnected once the server is back (See connect)
+                console.log('[OVERVIEW]: Server is restarting 💤');
+                return;
+            case FEED_RESPONSE_CODES.ERROR:
+                console.error(`[OVERVIEW]: ${rep.data.message}`);
+                return;
+            default:
+                return;
+        }
+
+        this.render();
+    }
+
+    render() {
+        this.list.innerHTML = '';
+
+        if (this.games.size === 0) {
+            this.list.appendChild(
+                build_element(
                     'a',
-                    ['list-group-item', 'dark-link'],
+                    ['btn', 'btn-block', 'btn-outline-dark'],
                     {
-                        href: `/games/join/${fragment[0]}`,
+                        href: '/games/create',
                     },
+                    'Be the first to create a game'
+                )
+            );
+            return;
+        }
 
-                    `<i class="bi ${sanitize(fragment[2])}"></i>
-      ${sanitize(fragment[1]).trim()}
-      <span class="text-darker">#${fragment[0]}</span>
+        // newest games first
+        Array.from(this.games.values())
+            .sort((a, b) => b.id - a.id)
+            .forEach((game) => {
+                let lock = game.private
+                    ? '<i class="bi bi-lock-fill"></i>'
+                    : '';
+                let status =
+                    game.status === 'lobby'
+                        ? ''
+                        : `<span class="badge bg-dark">${game.status}</span>`;
+
+                this.list.appendChild(
+                    build_element(
+                        'a',
+                        ['list-group-item', 'dark-link'],
+                        {
+                            href:
+                                game.status === 'lobby'
+                                    ? `/games/join/${game.id}`
+                                    : `/games/watch/${game.id}`,
+                        },
+                        `<i class="bi ${sanitize(game.icon)}"></i>
+      ${sanitize(game.name).trim()} ${lock}
+      <span class="text-darker">#${game.id} (${game.players})</span> ${status}
       `
+                    )
                 );
+            });
+    }
+}
 
-            games.appendChild(game);
-        }
+on_load(() => {
+    // init ui
+    init_ui('OVERVIEW');
 
-        if (games.length > 5) {
-            // build and attach "see more" link
-            let see_more = build_element(
-                'a',
-                ['list-group-item', 'dark-link'],
-                { href: '/games/browse/1' },
-                'See more <br /> <i class="bi bi-arrow-down-square-fill"></i>'
-            );
+    // join a game directly by its id
+    document
+        .getElementById('join-form')
+        .addEventListener('submit', (event) => {
+            event.preventDefault();
+            let id = (<HTMLInputElement>document.getElementById('game-id'))
+                .value;
+            window.location.href = `/games/join/${id}`;
+        });
 
-            games.appendChild(see_more);
-        }
+    let overview = new Overview(document.getElementById('games-container'));
+    ['filter-visibility', 'filter-min-players', 'filter-max-players'].forEach(
+        (id) =>
+            document
+                .getElementById(id)
+                .addEventListener('change', () => overview.send_filter())
+    );
+    overview.connect();
 
-        container.appendChild(games);
-    } else {
-        // create placeholder elements
-        let link = build_element(
-            'a',
-            ['btn', 'btn-block', 'btn-outline-dark'],
-            {
-                href: '/games/create',
-            },
-            'Be the first to create a game'
-        );
-
-        // append link to container
-        container.appendChild(link);
-    }
     console.log('[OVERVIEW]: Done page-specific initalizing UI');
 });
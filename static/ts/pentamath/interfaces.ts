@@ -17,15 +17,37 @@ interface FigurePosition {
     figure: string;
 }
 
+interface Handicap {
+    HeadStart: { player: number; figures: number };
+}
+
+interface StartingPosition {
+    locations: { locations: [number[], string][] };
+    handicaps: Handicap[];
+}
+
 interface Game {
     players: string[];
     base: FigurePosition[];
     moves: Move[];
+    starting_position?: StartingPosition; // custom position/ handicaps as recorded by the server
+}
+
+enum KO_RULES {
+    OFF = 0,
+    SIMPLE = 1,
+    POSITIONAL = 2,
 }
 
 interface RuleSet {
     figure: Number; // figures required to win
     compression: Boolean;
+    swaps: Boolean; // figures may swap places with other players' figures
+    stoppers: Boolean; // gray stoppers are awarded
+    move_clock?: Number; // seconds per move
+    base_time?: Number; // seconds per player (chess-style clock)
+    increment?: Number; // seconds added after every turn
+    ko: KO_RULES;
 }
 
 class GameFile {
@@ -42,6 +64,9 @@ class GameFile {
             this.rule_set = {
                 figure: game.players.length - 2,
                 compression: true,
+                swaps: true,
+                stoppers: true,
+                ko: KO_RULES.SIMPLE,
             };
         }
     }